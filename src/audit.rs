@@ -0,0 +1,435 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::models::{parse_byte_size, ExecutionOutcome, Script};
+use crate::params::ParameterValues;
+use crate::system::{self, Identity};
+
+/// The audit settings. Like the access policy, they are kept outside the
+/// users' own configuration so that only root can turn auditing off.
+pub const AUDIT_FILE: &str = "/etc/toolbox/audit.toml";
+
+/// Rotated audit files kept when `keep` is not set.
+const DEFAULT_KEEP: usize = 5;
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// The audit settings file:
+///
+/// ```toml
+/// file = "/var/log/toolbox/audit.jsonl"  # JSON lines
+/// max_size = "10M"        # rotate to audit.jsonl.1 ... past this size
+/// keep = 5                # rotated files kept
+/// syslog = true           # authpriv facility
+/// journald = true
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AuditConfig {
+    /// File to append one JSON line per run to.
+    file: Option<String>,
+    /// Rotate `file` before it grows past this size, e.g. `10M`.
+    max_size: Option<String>,
+    /// Rotated files to keep; 5 if unset.
+    keep: Option<usize>,
+    syslog: bool,
+    journald: bool,
+}
+
+/// One script run, as written to every audit sink.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEvent {
+    /// Name of `uid`, looked up rather than taken from the environment.
+    pub user: String,
+    /// Real uid of the process.
+    pub uid: u32,
+    /// Who ran the menu through sudo, when `user` is root because of it.
+    pub sudo_user: Option<String>,
+    pub sudo_uid: Option<u32>,
+    pub host: String,
+    pub script: PathBuf,
+    pub name: String,
+    /// SHA-256 of the script as it was run.
+    pub sha256: Option<String>,
+    pub revision: Option<String>,
+    pub source: Option<String>,
+    /// Parameter values, with passwords masked.
+    pub parameters: serde_json::Map<String, serde_json::Value>,
+    pub elevated: bool,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    pub duration_ms: u64,
+    /// `exited`, `signalled`, `timed_out`, `cancelled` or `refused`.
+    pub status: &'static str,
+    /// Why a `refused` run was refused.
+    pub reason: Option<String>,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub success: bool,
+}
+
+impl AuditEvent {
+    pub fn new(
+        identity: &Identity,
+        script: &Script,
+        parameters: &ParameterValues,
+        outcome: &ExecutionOutcome,
+        sha256: Option<String>,
+        elevated: bool,
+    ) -> Self {
+        let (sudo_user, sudo_uid) = system::sudo_user().unzip();
+        Self {
+            user: identity.user.clone(),
+            uid: identity.uid,
+            sudo_user,
+            sudo_uid: sudo_uid.flatten(),
+            host: outcome.hostname.clone(),
+            script: script.path.clone(),
+            name: script.name.clone(),
+            sha256,
+            revision: script.revision.clone(),
+            source: script.source.clone(),
+            parameters: parameters.masked_object(),
+            elevated,
+            started_at: outcome.started_at,
+            finished_at: outcome.finished_at,
+            duration_ms: outcome.duration_ms(),
+            status: outcome.status(),
+            reason: None,
+            exit_code: outcome.exit_code,
+            signal: outcome.signal,
+            success: outcome.success(),
+        }
+    }
+
+    /// One-line summary for sinks that carry a message besides the event.
+    fn summary(&self) -> String {
+        let user = match &self.sudo_user {
+            Some(sudo_user) => format!("{} (sudo from {})", self.user, sudo_user),
+            None => self.user.clone(),
+        };
+        if let Some(reason) = &self.reason {
+            return format!("{} was refused {}: {}", user, self.script.display(), reason);
+        }
+        format!(
+            "{} ran {}{}: {}",
+            user,
+            self.script.display(),
+            if self.elevated { " as root" } else { "" },
+            if self.success { "succeeded".to_string() } else { format!("failed ({})", self.status) }
+        )
+    }
+}
+
+/// Somewhere audit events are sent. A failing sink is reported but never
+/// stops a script from running.
+pub trait AuditSink: Send + Sync {
+    /// Named in warnings when the sink fails.
+    fn name(&self) -> &str;
+    fn record(&self, event: &AuditEvent) -> Result<()>;
+}
+
+/// The configured audit sinks; does nothing when there are none.
+#[derive(Clone, Default)]
+pub struct Auditor {
+    sinks: Arc<Vec<Box<dyn AuditSink>>>,
+}
+
+impl Auditor {
+    pub fn new(sinks: Vec<Box<dyn AuditSink>>) -> Self {
+        Self { sinks: Arc::new(sinks) }
+    }
+
+    /// The sinks set in `AUDIT_FILE`; none without the file.
+    pub fn load() -> Result<Self> {
+        let path = Path::new(AUDIT_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read audit settings: {}", path.display()))?;
+        let config = toml::from_str(&content).with_context(|| format!("Invalid audit settings: {}", path.display()))?;
+        Self::from_config(&config)
+    }
+
+    fn from_config(config: &AuditConfig) -> Result<Self> {
+        let mut sinks: Vec<Box<dyn AuditSink>> = Vec::new();
+        if let Some(file) = &config.file {
+            let max_size = match &config.max_size {
+                Some(size) => Some(parse_byte_size(size).ok_or_else(|| anyhow!("Invalid audit max_size '{}'", size))?),
+                None => None,
+            };
+            sinks.push(Box::new(JsonLinesSink::new(
+                crate::expand_tilde(file),
+                max_size,
+                config.keep.unwrap_or(DEFAULT_KEEP),
+            )));
+        }
+        if config.syslog {
+            sinks.push(Box::new(SyslogSink));
+        }
+        if config.journald {
+            sinks.push(Box::new(JournaldSink));
+        }
+        Ok(Self::new(sinks))
+    }
+
+    pub fn record(&self, event: &AuditEvent) {
+        for sink in self.sinks.iter() {
+            if let Err(e) = sink.record(event) {
+                eprintln!("Warning: audit {} failed: {:#}", sink.name(), e);
+            }
+        }
+    }
+}
+
+/// Appends one JSON object per line to a file, rotating it to `FILE.1`,
+/// `FILE.2`, ... once it reaches `max_size`.
+pub struct JsonLinesSink {
+    path: PathBuf,
+    max_size: Option<u64>,
+    keep: usize,
+    /// Serialises the runs of a parallel batch; other processes rely on
+    /// `O_APPEND` to keep lines whole.
+    lock: Mutex<()>,
+}
+
+impl JsonLinesSink {
+    pub fn new(path: PathBuf, max_size: Option<u64>, keep: usize) -> Self {
+        Self { path, max_size, keep, lock: Mutex::new(()) }
+    }
+
+    fn rotate(&self) -> Result<()> {
+        if self.keep == 0 {
+            return fs::remove_file(&self.path).with_context(|| format!("Failed to remove {}", self.path.display()));
+        }
+        let rotated = |index: usize| PathBuf::from(format!("{}.{}", self.path.display(), index));
+        // The oldest falls off the end; a missing one is not an error
+        let _ = fs::remove_file(rotated(self.keep));
+        for index in (1..self.keep).rev() {
+            let _ = fs::rename(rotated(index), rotated(index + 1));
+        }
+        fs::rename(&self.path, rotated(1)).with_context(|| format!("Failed to rotate {}", self.path.display()))
+    }
+}
+
+impl AuditSink for JsonLinesSink {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn record(&self, event: &AuditEvent) -> Result<()> {
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let (Some(max_size), Ok(metadata)) = (self.max_size, fs::metadata(&self.path)) {
+            if metadata.len() > 0 && metadata.len() + line.len() as u64 > max_size {
+                self.rotate()?;
+            }
+        }
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.exists()) {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .mode(0o640)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// Sends each event to the local syslog's authpriv facility, next to the
+/// access policy's refusals.
+pub struct SyslogSink;
+
+impl AuditSink for SyslogSink {
+    fn name(&self) -> &str {
+        "syslog"
+    }
+
+    fn record(&self, event: &AuditEvent) -> Result<()> {
+        let priority = if event.success { libc::LOG_INFO } else { libc::LOG_NOTICE };
+        system::syslog(priority, &format!("{} {}", event.summary(), serde_json::to_string(event)?));
+        Ok(())
+    }
+}
+
+/// Sends each event to journald as a structured entry, with the event's
+/// fields as `TOOLBOX_*` fields.
+pub struct JournaldSink;
+
+impl AuditSink for JournaldSink {
+    fn name(&self) -> &str {
+        "journald"
+    }
+
+    fn record(&self, event: &AuditEvent) -> Result<()> {
+        let datagram = journald_datagram(event)?;
+        let socket = UnixDatagram::unbound()?;
+        let sent = socket
+            .send_to(&datagram, JOURNALD_SOCKET)
+            .with_context(|| format!("Failed to reach journald at {}", JOURNALD_SOCKET))?;
+        if sent != datagram.len() {
+            bail!("journald accepted {} of {} bytes", sent, datagram.len());
+        }
+        Ok(())
+    }
+}
+
+/// `event` as a journald native protocol datagram: `NAME=value` lines, or
+/// for values with a newline the name, a little-endian length and the raw
+/// value.
+fn journald_datagram(event: &AuditEvent) -> Result<Vec<u8>> {
+    let mut fields = vec![
+        ("MESSAGE", event.summary()),
+        ("PRIORITY", if event.success { "6" } else { "5" }.to_string()),
+        ("SYSLOG_IDENTIFIER", "toolbox".to_string()),
+        ("TOOLBOX_USER", event.user.clone()),
+        ("TOOLBOX_UID", event.uid.to_string()),
+        ("TOOLBOX_SCRIPT", event.script.display().to_string()),
+        ("TOOLBOX_STATUS", event.status.to_string()),
+        ("TOOLBOX_EVENT", serde_json::to_string(event)?),
+    ];
+    if let Some(sudo_user) = &event.sudo_user {
+        fields.push(("TOOLBOX_SUDO_USER", sudo_user.clone()));
+    }
+    if let Some(sha256) = &event.sha256 {
+        fields.push(("TOOLBOX_SHA256", sha256.clone()));
+    }
+    if let Some(reason) = &event.reason {
+        fields.push(("TOOLBOX_REASON", reason.clone()));
+    }
+    if let Some(exit_code) = event.exit_code {
+        fields.push(("TOOLBOX_EXIT_CODE", exit_code.to_string()));
+    }
+
+    let mut datagram = Vec::new();
+    for (name, value) in fields {
+        datagram.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            datagram.push(b'\n');
+            datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            datagram.push(b'=');
+        }
+        datagram.extend_from_slice(value.as_bytes());
+        datagram.push(b'\n');
+    }
+    Ok(datagram)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn event(script: &str) -> AuditEvent {
+        let identity = Identity { uid: 1000, user: "alice".to_string(), groups: Vec::new() };
+        let now = chrono::Utc::now();
+        let outcome = ExecutionOutcome {
+            exit_code: Some(0),
+            signal: None,
+            core_dumped: false,
+            timed_out: false,
+            cancelled: false,
+            refused: false,
+            started_at: now,
+            finished_at: now,
+            hostname: "host".to_string(),
+            user: "alice".to_string(),
+        };
+        let script = Script::new("script".to_string(), PathBuf::from(script), "TopLevel".to_string());
+        AuditEvent::new(&identity, &script, &ParameterValues::default(), &outcome, None, false)
+    }
+
+    /// The scripts recorded in `path`, oldest first; empty if it does not exist.
+    fn recorded(path: &Path) -> Vec<String> {
+        fs::read_to_string(path)
+            .unwrap_or_default()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["script"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    fn rotated(path: &Path, index: usize) -> PathBuf {
+        PathBuf::from(format!("{}.{}", path.display(), index))
+    }
+
+    #[test]
+    fn file_grows_until_max_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log").join("audit.jsonl");
+        let sink = JsonLinesSink::new(path.clone(), Some(1024 * 1024), 2);
+        for script in ["a.sh", "b.sh", "c.sh"] {
+            sink.record(&event(script)).unwrap();
+        }
+        assert_eq!(recorded(&path), ["a.sh", "b.sh", "c.sh"]);
+        assert!(!rotated(&path, 1).exists());
+    }
+
+    #[test]
+    fn rotation_shifts_files_and_drops_the_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        // Room for one event per file
+        let size = serde_json::to_string(&event("a.sh")).unwrap().len() as u64 + 1;
+        let sink = JsonLinesSink::new(path.clone(), Some(size), 2);
+        for script in ["a.sh", "b.sh", "c.sh", "d.sh"] {
+            sink.record(&event(script)).unwrap();
+        }
+        assert_eq!(recorded(&path), ["d.sh"]);
+        assert_eq!(recorded(&rotated(&path, 1)), ["c.sh"]);
+        assert_eq!(recorded(&rotated(&path, 2)), ["b.sh"]);
+        assert!(!rotated(&path, 3).exists());
+    }
+
+    #[test]
+    fn keep_zero_starts_the_file_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let sink = JsonLinesSink::new(path.clone(), Some(1), 0);
+        sink.record(&event("a.sh")).unwrap();
+        sink.record(&event("b.sh")).unwrap();
+        assert_eq!(recorded(&path), ["b.sh"]);
+        assert!(!rotated(&path, 1).exists());
+    }
+
+    #[test]
+    fn passwords_are_masked() {
+        let mut parameters = ParameterValues::default();
+        parameters.insert("host", "db1".to_string(), false);
+        parameters.insert("password", "hunter2".to_string(), true);
+        let mut event = event("a.sh");
+        event.parameters = parameters.masked_object();
+
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(!json.contains("hunter2"), "{}", json);
+        assert_eq!(event.parameters["host"], "db1");
+        assert_ne!(event.parameters["password"], "hunter2");
+    }
+
+    #[test]
+    fn journald_values_with_newlines_are_length_prefixed() {
+        let script = "/opt/toolbox/odd\nname.sh";
+        let datagram = journald_datagram(&event(script)).unwrap();
+
+        assert!(datagram.windows(22).any(|field| field == b"SYSLOG_IDENTIFIER=tool"));
+        let mut expected = b"\nTOOLBOX_SCRIPT\n".to_vec();
+        expected.extend_from_slice(&(script.len() as u64).to_le_bytes());
+        expected.extend_from_slice(script.as_bytes());
+        expected.push(b'\n');
+        assert!(datagram.windows(expected.len()).any(|field| field == expected.as_slice()));
+        // The JSON copy escapes the newline, so it stays a plain field
+        assert!(datagram.windows(14).any(|field| field == b"TOOLBOX_EVENT="));
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::Result;
+use serde::Deserialize;
+use std::io::{self, Write};
+
+use crate::database::Database;
+use crate::display::ScriptExecutor;
+use crate::glyphs::{self, Glyphs};
+use crate::models::{ExecutionOutcome, Script};
+use crate::params::{self, ParameterValues};
+use crate::system;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchMode {
+    /// One after another, each with the terminal to itself.
+    Sequential,
+    /// All at once, with output prefixed by script name.
+    Parallel,
+}
+
+impl BatchMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BatchMode::Sequential => "sequential",
+            BatchMode::Parallel => "parallel",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum FailurePolicy {
+    /// Skip (sequential) or terminate (parallel) the rest after a failure.
+    #[default]
+    #[serde(rename = "stop")]
+    StopOnError,
+    #[serde(rename = "continue")]
+    Continue,
+}
+
+impl FailurePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailurePolicy::StopOnError => "stop",
+            FailurePolicy::Continue => "continue",
+        }
+    }
+}
+
+/// Run the scripts marked in the menu as one batch. The mode, failure
+/// policy and every script's parameters are asked for before anything
+/// starts, so a long batch does not stop halfway to wait for input. The
+/// runs are recorded in history under a shared `batch_runs` row. Returns
+/// false when the batch was cancelled before anything ran.
+pub async fn run(executor: &ScriptExecutor, database: &Database, scripts: &[Script], glyphs: &Glyphs) -> Result<bool> {
+    println!("{} Batch of {} scripts:", glyphs.package, scripts.len());
+    for (i, script) in scripts.iter().enumerate() {
+        println!("  {:>2}. {}", i + 1, script.display_name());
+    }
+    println!();
+
+    // Nothing runs if any of the scripts is refused
+    if let Some((script, denied)) = scripts.iter().find_map(|script| Some((script, executor.access_denied(script)?))) {
+        println!("{} {}", glyphs.unavailable, denied);
+        executor.refuse(script, &ParameterValues::default(), None, &denied);
+        return cancelled("Batch", glyphs);
+    }
+
+    let Some(mode) = choose(
+        "Run [s]equentially or in [p]arallel?",
+        &[("s", BatchMode::Sequential), ("p", BatchMode::Parallel)],
+    )?
+    else {
+        return cancelled("Batch", glyphs);
+    };
+    let Some(policy) = choose(
+        "On failure [s]top or [c]ontinue?",
+        &[("s", FailurePolicy::StopOnError), ("c", FailurePolicy::Continue)],
+    )?
+    else {
+        return cancelled("Batch", glyphs);
+    };
+    println!();
+
+    let mut runs = Vec::new();
+    for script in scripts {
+        let Some(parameters) = params::collect(script, glyphs)? else {
+            return cancelled("Batch", glyphs);
+        };
+        runs.push((script.clone(), parameters));
+    }
+
+    if !authenticate(executor, scripts, "scripts", glyphs) {
+        return cancelled("Batch", glyphs);
+    }
+
+    let batch_id = database.start_batch_run(mode.as_str(), policy.as_str(), runs.len(), None).await?;
+    let stop_on_error = policy == FailurePolicy::StopOnError;
+
+    let outcomes: Vec<Option<ExecutionOutcome>> = match mode {
+        BatchMode::Sequential => {
+            let mut outcomes = Vec::new();
+            let mut stopped = false;
+            for (i, (script, parameters)) in runs.iter().enumerate() {
+                if stopped {
+                    outcomes.push(None);
+                    continue;
+                }
+
+                println!("{} Batch #{} - script {} of {}", glyphs.package, batch_id, i + 1, runs.len());
+                let outcome = executor.execute(script, parameters).await?;
+                record(database, script, &outcome, parameters, batch_id).await?;
+
+                // Ctrl+C stops the whole batch, whatever the policy
+                stopped = outcome.cancelled || (stop_on_error && !outcome.success());
+                outcomes.push(Some(outcome));
+                println!();
+            }
+            outcomes
+        }
+        BatchMode::Parallel => {
+            println!("{} Batch #{} - running {} scripts in parallel", glyphs.package, batch_id, runs.len());
+            let outcomes = executor.execute_parallel(runs.clone(), stop_on_error).await?;
+            for ((script, parameters), outcome) in runs.iter().zip(&outcomes) {
+                record(database, script, outcome, parameters, batch_id).await?;
+            }
+            outcomes.into_iter().map(Some).collect()
+        }
+    };
+
+    let succeeded = outcomes.iter().flatten().filter(|outcome| outcome.success()).count();
+    let skipped = outcomes.iter().filter(|outcome| outcome.is_none()).count();
+    let failed = outcomes.len() - succeeded - skipped;
+    database.finish_batch_run(batch_id, succeeded, failed, skipped).await?;
+
+    let rows: Vec<(&str, Summary)> = runs
+        .iter()
+        .zip(&outcomes)
+        .map(|((script, _), outcome)| (script.display_name(), outcome.as_ref().map_or(Summary::Skipped, Summary::Ran)))
+        .collect();
+    print_summary(&format!("{} Batch #{} summary", glyphs.package, batch_id), &rows, glyphs);
+    Ok(true)
+}
+
+async fn record(
+    database: &Database,
+    script: &Script,
+    outcome: &ExecutionOutcome,
+    parameters: &ParameterValues,
+    batch_id: i64,
+) -> Result<()> {
+    database
+        .record_execution(script, outcome, parameters.to_history_json().as_deref(), Some(batch_id))
+        .await
+}
+
+/// What became of one script of a batch or step of a workflow.
+pub enum Summary<'a> {
+    Ran(&'a ExecutionOutcome),
+    Skipped,
+    /// Not started because of an error, with the message.
+    Error(&'a str),
+}
+
+/// Print the table that ends a batch or workflow: after `heading`, one
+/// line per labelled script or step with its result and duration.
+pub fn print_summary(heading: &str, rows: &[(&str, Summary)], glyphs: &Glyphs) {
+    let icon_width = [glyphs.success, glyphs.failure, glyphs.timeout, glyphs.cancelled, glyphs.skipped]
+        .iter()
+        .map(|icon| glyphs::display_width(icon))
+        .max()
+        .unwrap_or(0);
+    let label_width = rows
+        .iter()
+        .map(|(label, _)| glyphs::display_width(label))
+        .max()
+        .unwrap_or(0);
+
+    println!("{}", "=".repeat(60));
+    println!("{}", heading);
+    for (label, summary) in rows {
+        let (icon, result) = match summary {
+            Summary::Ran(outcome) if outcome.success() => (glyphs.success, "ok".to_string()),
+            Summary::Ran(outcome) if outcome.timed_out => (glyphs.timeout, outcome.describe()),
+            Summary::Ran(outcome) if outcome.cancelled => (glyphs.cancelled, outcome.describe()),
+            Summary::Ran(outcome) => (glyphs.failure, outcome.describe()),
+            Summary::Skipped => (glyphs.skipped, "skipped".to_string()),
+            Summary::Error(message) => (glyphs.failure, message.to_string()),
+        };
+        let duration = match summary {
+            Summary::Ran(outcome) => format!("{:>8.1}s", outcome.duration_ms() as f64 / 1000.0),
+            _ => String::new(),
+        };
+        println!(
+            "  {} {}  {:<20} {}",
+            glyphs::pad(icon, icon_width),
+            glyphs::pad(label, label_width),
+            result,
+            duration
+        );
+    }
+}
+
+/// Validate sudo credentials once before a batch or workflow starts, when
+/// any of its `scripts` needs them, so that it does not stop halfway for
+/// a password (parallel scripts could not prompt at all). `what` names the
+/// scripts in the message. Returns whether it may go ahead.
+pub fn authenticate<'a>(
+    executor: &ScriptExecutor,
+    scripts: impl IntoIterator<Item = &'a Script>,
+    what: &str,
+    glyphs: &Glyphs,
+) -> bool {
+    if !scripts.into_iter().any(|script| executor.needs_sudo(script)) {
+        return true;
+    }
+
+    println!("{} Some {} require root privileges - validating sudo credentials...", glyphs.sudo, what);
+    match system::validate_sudo() {
+        Ok(()) => true,
+        Err(e) => {
+            println!("{} {}", glyphs.failure, e);
+            false
+        }
+    }
+}
+
+/// Report that a batch or workflow (`what`) was given up before anything
+/// ran; the result is what its `run` returns then.
+pub fn cancelled<T: Default>(what: &str, glyphs: &Glyphs) -> Result<T> {
+    println!("{} {} cancelled - nothing was run", glyphs.cancelled, what);
+    Ok(T::default())
+}
+
+/// Ask a one-letter question; an empty answer picks the first choice and
+/// end of input cancels.
+fn choose<T: Copy>(question: &str, choices: &[(&str, T)]) -> Result<Option<T>> {
+    loop {
+        print!("{} [{}] ", question, choices[0].0);
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer)? == 0 {
+            println!();
+            return Ok(None);
+        }
+        let answer = answer.trim().to_lowercase();
+        if answer.is_empty() {
+            return Ok(Some(choices[0].1));
+        }
+        if let Some((_, choice)) = choices.iter().find(|(key, _)| answer.starts_with(key)) {
+            return Ok(Some(*choice));
+        }
+
+        let keys: Vec<&str> = choices.iter().map(|(key, _)| *key).collect();
+        println!("  Please answer {}", keys.join(" or "));
+    }
+}
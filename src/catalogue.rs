@@ -0,0 +1,358 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::{Elevation, ResourceLimits, Script};
+use crate::roots::Roots;
+use crate::scanner::ScriptScanner;
+
+/// Version written by `toolbox export --format json`. Version 1 is the
+/// bare array produced by `extract_script_metadata.sh`.
+pub const CATALOGUE_VERSION: u32 = 2;
+
+// Values `extract_script_metadata.sh` writes for tags a script does not set
+const V1_NO_DESCRIPTION: &str = "No description available";
+const V1_DEFAULT_ICON: &str = "📝";
+const V1_DEFAULT_ORDER: i32 = 999;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Versioned catalogue carrying everything the database knows.
+    Json,
+    /// The `docs/scripts_metadata.json` schema, for the existing doc tooling.
+    JsonV1,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "json" => Some(Format::Json),
+            "json-v1" => Some(Format::JsonV1),
+            _ => None,
+        }
+    }
+}
+
+/// One entry of `scripts_metadata.json`. Booleans are strings there, and
+/// unset tags are written as their defaults.
+#[derive(Serialize, Deserialize)]
+struct EntryV1 {
+    name: String,
+    filename: String,
+    path: String,
+    category: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    detailed_description: String,
+    #[serde(default)]
+    info_url: String,
+    #[serde(default)]
+    icon: String,
+    #[serde(default)]
+    color: String,
+    #[serde(default)]
+    order: Option<i32>,
+    #[serde(default)]
+    is_default: String,
+    #[serde(default)]
+    separator: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    author: String,
+    #[serde(default)]
+    has_parameters: String,
+    #[serde(default)]
+    file_size: u64,
+    #[serde(default)]
+    last_modified: i64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CatalogueV2 {
+    version: u32,
+    generated_at: chrono::DateTime<chrono::Utc>,
+    scripts: Vec<EntryV2>,
+}
+
+/// The v1 fields with real types, plus the metadata v1 leaves out. Unset
+/// tags are omitted rather than written as defaults, so an import gives
+/// back exactly what was exported.
+#[derive(Serialize, Deserialize)]
+struct EntryV2 {
+    name: String,
+    filename: String,
+    path: String,
+    category: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    menu_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    detailed_description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    integration: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    info_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    order: Option<i32>,
+    #[serde(default)]
+    is_default: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    separator: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    roles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    #[serde(default)]
+    has_parameters: bool,
+    /// The `#JSON_PARAMS_START` block as written, so parameter order survives.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parameters: Option<String>,
+    #[serde(default)]
+    elevation: Elevation,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "ResourceLimits::is_empty")]
+    limits: ResourceLimits,
+    /// Label of the toolbox root `path` is relative to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    overrides: Option<String>,
+    #[serde(default)]
+    file_size: u64,
+    #[serde(default)]
+    last_modified: i64,
+}
+
+/// Scripts read from a catalogue file.
+pub struct Catalogue {
+    pub version: u32,
+    pub scripts: Vec<Script>,
+    /// Scripts whose parameter definitions the file does not carry (v1 only
+    /// says whether there are any); a scan picks them up.
+    pub missing_parameters: usize,
+}
+
+/// Render the scripts as a catalogue, with paths relative to their toolbox root.
+pub fn export(scripts: &[Script], roots: &Roots, format: Format) -> Result<String> {
+    let json = match format {
+        Format::Json => serde_json::to_string_pretty(&CatalogueV2 {
+            version: CATALOGUE_VERSION,
+            generated_at: chrono::Utc::now(),
+            scripts: scripts.iter().map(|script| entry_v2(script, roots)).collect(),
+        })?,
+        Format::JsonV1 => serde_json::to_string_pretty(
+            &scripts.iter().map(|script| entry_v1(script, roots)).collect::<Vec<_>>(),
+        )?,
+    };
+    Ok(json + "\n")
+}
+
+/// Read a catalogue of either version. Relative paths are resolved against
+/// the toolbox roots, and dependencies are checked on this host.
+pub fn import(path: &Path, roots: &Roots) -> Result<Catalogue> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read catalogue: {}", path.display()))?;
+    let value: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid JSON in {}", path.display()))?;
+
+    let (version, mut scripts): (u32, Vec<(Script, bool)>) = match value {
+        serde_json::Value::Array(_) => {
+            let entries: Vec<EntryV1> = serde_json::from_value(value)
+                .with_context(|| format!("Invalid version 1 catalogue: {}", path.display()))?;
+            let scripts = entries.into_iter().map(|entry| from_v1(entry, roots)).collect();
+            (1, scripts)
+        }
+        serde_json::Value::Object(ref object) => {
+            let version = object.get("version").and_then(serde_json::Value::as_u64);
+            if version != Some(CATALOGUE_VERSION as u64) {
+                bail!(
+                    "Unsupported catalogue version {} in {} (expected {})",
+                    version.map_or("<missing>".to_string(), |version| version.to_string()),
+                    path.display(),
+                    CATALOGUE_VERSION
+                );
+            }
+            let catalogue: CatalogueV2 = serde_json::from_value(value)
+                .with_context(|| format!("Invalid catalogue: {}", path.display()))?;
+            let scripts = catalogue.scripts.into_iter().map(|entry| from_v2(entry, roots)).collect();
+            (CATALOGUE_VERSION, scripts)
+        }
+        _ => bail!("{} is not a script catalogue", path.display()),
+    };
+
+    let missing_parameters = scripts.iter().filter(|(_, has_parameters)| *has_parameters).count();
+    let scanner = ScriptScanner::new(roots.clone());
+    for (script, _) in &mut scripts {
+        script.dependency_available = scanner.check_dependency_available(script);
+    }
+
+    Ok(Catalogue {
+        version,
+        scripts: scripts.into_iter().map(|(script, _)| script).collect(),
+        missing_parameters,
+    })
+}
+
+fn entry_v1(script: &Script, roots: &Roots) -> EntryV1 {
+    let (file_size, last_modified) = file_stats(&script.path);
+    EntryV1 {
+        name: script.display_name().to_string(),
+        filename: file_name(&script.path),
+        path: roots.relative(&script.path).to_string_lossy().to_string(),
+        category: script.category.clone(),
+        description: script.description.clone().unwrap_or_else(|| V1_NO_DESCRIPTION.to_string()),
+        detailed_description: script.detailed_description.clone().unwrap_or_default(),
+        info_url: script.info_url.clone().unwrap_or_default(),
+        icon: script.icon.clone().unwrap_or_else(|| V1_DEFAULT_ICON.to_string()),
+        color: script.color.clone().unwrap_or_default(),
+        order: Some(script.order.unwrap_or(V1_DEFAULT_ORDER)),
+        is_default: script.is_default.to_string(),
+        separator: script.separator.clone().unwrap_or_default(),
+        tags: script.tags.clone(),
+        author: script.author.clone().unwrap_or_default(),
+        has_parameters: script.has_parameters().to_string(),
+        file_size,
+        last_modified,
+    }
+}
+
+fn entry_v2(script: &Script, roots: &Roots) -> EntryV2 {
+    let (file_size, last_modified) = file_stats(&script.path);
+    EntryV2 {
+        name: script.display_name().to_string(),
+        filename: file_name(&script.path),
+        path: roots.relative(&script.path).to_string_lossy().to_string(),
+        category: script.category.clone(),
+        menu_name: script.menu_name.clone(),
+        description: script.description.clone(),
+        detailed_description: script.detailed_description.clone(),
+        integration: script.integration.clone(),
+        info_url: script.info_url.clone(),
+        icon: script.icon.clone(),
+        color: script.color.clone(),
+        order: script.order,
+        is_default: script.is_default,
+        separator: script.separator.clone(),
+        tags: script.tags.clone(),
+        roles: script.roles.clone(),
+        author: script.author.clone(),
+        has_parameters: script.has_parameters(),
+        parameters: script.parameters.clone().filter(|_| script.has_parameters()),
+        elevation: script.elevation,
+        timeout_secs: script.timeout_secs,
+        limits: script.limits,
+        source: script.source.clone(),
+        overrides: script.overrides.clone(),
+        file_size,
+        last_modified,
+    }
+}
+
+/// The script and whether it has parameters the entry does not define.
+fn from_v1(entry: EntryV1, roots: &Roots) -> (Script, bool) {
+    let path = absolute_path(&entry.path, None, roots);
+    let mut script = Script::new(script_name(&entry.filename, &path), path, entry.category);
+    script.source = roots.label_of(&script.path);
+
+    if entry.name != script.name {
+        script.menu_name = Some(entry.name);
+    }
+    script.description = non_empty(entry.description).filter(|description| description != V1_NO_DESCRIPTION);
+    script.detailed_description = non_empty(entry.detailed_description);
+    script.info_url = non_empty(entry.info_url);
+    script.icon = non_empty(entry.icon);
+    script.color = non_empty(entry.color);
+    script.order = entry.order.filter(|&order| order != V1_DEFAULT_ORDER);
+    script.is_default = entry.is_default == "true";
+    script.separator = non_empty(entry.separator);
+    script.tags = entry.tags;
+    script.author = non_empty(entry.author);
+
+    (script, entry.has_parameters == "true")
+}
+
+fn from_v2(entry: EntryV2, roots: &Roots) -> (Script, bool) {
+    let path = absolute_path(&entry.path, entry.source.as_deref(), roots);
+    let mut script = Script::new(script_name(&entry.filename, &path), path, entry.category);
+    script.source = roots.label_of(&script.path);
+    script.overrides = entry.overrides.filter(|_| script.source.is_some());
+
+    script.menu_name = entry.menu_name;
+    script.description = entry.description;
+    script.detailed_description = entry.detailed_description;
+    script.integration = entry.integration;
+    script.info_url = entry.info_url;
+    script.icon = entry.icon;
+    script.color = entry.color;
+    script.order = entry.order;
+    script.is_default = entry.is_default;
+    script.separator = entry.separator;
+    script.tags = entry.tags;
+    script.roles = entry.roles;
+    script.author = entry.author;
+    script.parameters = entry.parameters;
+    script.elevation = entry.elevation;
+    script.timeout_secs = entry.timeout_secs;
+    script.limits = entry.limits;
+
+    let missing_parameters = entry.has_parameters && !script.has_parameters();
+    (script, missing_parameters)
+}
+
+/// Scripts are keyed by file stem, as the scanner does.
+fn script_name(filename: &str, path: &Path) -> String {
+    let filename = if filename.is_empty() { file_name(path) } else { filename.to_string() };
+    Path::new(&filename)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(&filename)
+        .to_string()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// A relative path is taken to be in the root labelled `source` when there
+/// is one, otherwise in the highest root that has the file.
+fn absolute_path(path: &str, source: Option<&str>, roots: &Roots) -> PathBuf {
+    let path = PathBuf::from(path);
+    if path.is_absolute() {
+        return path;
+    }
+    match source.and_then(|source| roots.find(source)) {
+        Some(root) => root.path.join(path),
+        None => roots.resolve(&path),
+    }
+}
+
+/// Size and modification time (seconds since the epoch), as
+/// `extract_script_metadata.sh` records them; zero when the file is missing.
+fn file_stats(path: &Path) -> (u64, i64) {
+    let Ok(metadata) = fs::metadata(path) else {
+        return (0, 0);
+    };
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |since_epoch| since_epoch.as_secs() as i64);
+    (metadata.len(), modified)
+}
+
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.trim().is_empty())
+}
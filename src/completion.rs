@@ -0,0 +1,379 @@
+use anyhow::Result;
+use clap::{Arg, Command};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::database::Database;
+use crate::manpage;
+use crate::migrations;
+use crate::models::{ParameterType, Script, ScriptParameter, Workflow};
+use crate::policy::Policy;
+use crate::roots::{Root, Roots};
+use crate::theme::Theme;
+use crate::workflow;
+
+pub const SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+/// Hidden subcommand the shell scripts call with the words typed so far.
+pub const COMPLETE_COMMAND: &str = "__complete";
+
+/// Printed instead of candidates when the shell should complete file names.
+const FILES: &str = "__files__";
+
+const BASH: &str = r#"# bash completion for toolbox
+_toolbox() {
+    local line="${COMP_LINE:0:COMP_POINT}"
+    local -a words
+    read -ra words <<< "$line"
+    [[ "$line" == *[[:space:]] ]] && words+=("")
+    local cur="${words[${#words[@]}-1]}"
+
+    local IFS=$'\n'
+    local -a candidates
+    candidates=($(toolbox __complete -- "${words[@]:1}" 2>/dev/null))
+    if [[ "${candidates[0]}" == "__files__" ]]; then
+        compopt -o default
+        COMPREPLY=()
+        return
+    fi
+
+    # bash splits NAME=VALUE at the '=', so only the part after it is replaced
+    local prefix="${cur%"${cur##*[=:]}"}"
+    COMPREPLY=()
+    local candidate
+    for candidate in "${candidates[@]}"; do
+        COMPREPLY+=("${candidate#"$prefix"}")
+    done
+    if [[ ${#COMPREPLY[@]} -eq 1 && "${COMPREPLY[0]}" == *= ]]; then
+        compopt -o nospace
+    fi
+}
+complete -F _toolbox toolbox
+"#;
+
+const ZSH: &str = r#"#compdef toolbox
+# zsh completion for toolbox
+_toolbox() {
+    local -a candidates spaced unspaced
+    candidates=("${(@f)$(toolbox __complete -- "${(@)words[2,CURRENT]}" 2>/dev/null)}")
+    if [[ "${candidates[1]}" == "__files__" ]]; then
+        _files
+        return
+    fi
+
+    local candidate
+    for candidate in "${candidates[@]}"; do
+        [[ -z "$candidate" ]] && continue
+        if [[ "$candidate" == *= ]]; then
+            unspaced+=("$candidate")
+        else
+            spaced+=("$candidate")
+        fi
+    done
+    (( ${#spaced} )) && compadd -- "${spaced[@]}"
+    (( ${#unspaced} )) && compadd -S '' -- "${unspaced[@]}"
+}
+
+if [[ "${zsh_eval_context[-1]}" == loadautofunc ]]; then
+    _toolbox "$@"
+else
+    compdef _toolbox toolbox
+fi
+"#;
+
+const FISH: &str = r#"# fish completion for toolbox
+function __toolbox_complete
+    set -l tokens (commandline -opc)
+    set -l current (commandline -ct)
+    set -l candidates (toolbox __complete -- $tokens[2..-1] "$current" 2>/dev/null)
+    if test "$candidates[1]" = __files__
+        __fish_complete_path "$current"
+        return
+    end
+    printf '%s\n' $candidates
+end
+complete -c toolbox -f -a '(__toolbox_complete)'
+"#;
+
+pub fn script(shell: &str) -> &'static str {
+    match shell {
+        "bash" => BASH,
+        "zsh" => ZSH,
+        _ => FISH,
+    }
+}
+
+enum Candidates {
+    Words(Vec<String>),
+    Files,
+}
+
+/// How far the command line has got: the subcommand being typed, the
+/// values given so far, and what the word under the cursor must be.
+struct Line<'a> {
+    command: &'a Command,
+    /// Options of the subcommand and the global options of its parents.
+    options: Vec<&'a Arg>,
+    values: Vec<(&'a str, String)>,
+    positionals: usize,
+    /// The option whose value is being typed.
+    pending: Option<&'a Arg>,
+    current: String,
+}
+
+impl<'a> Line<'a> {
+    fn parse(cli: &'a Command, words: &[String]) -> Self {
+        let (current, typed) = match words.split_last() {
+            Some((current, typed)) => (current.clone(), typed),
+            None => (String::new(), &[][..]),
+        };
+        let mut line = Line {
+            command: cli,
+            options: cli.get_arguments().filter(|arg| !arg.is_positional()).collect(),
+            values: Vec::new(),
+            positionals: 0,
+            pending: None,
+            current,
+        };
+
+        for word in typed {
+            if let Some(arg) = line.pending.take() {
+                line.values.push((arg.get_id().as_str(), word.clone()));
+                continue;
+            }
+
+            if let Some(option) = word.strip_prefix("--").filter(|option| !option.is_empty()) {
+                let (name, inline) = match option.split_once('=') {
+                    Some((name, value)) => (name, Some(value)),
+                    None => (option, None),
+                };
+                if let Some(arg) = line.option(|arg| arg.get_long() == Some(name)) {
+                    match inline {
+                        Some(value) => line.values.push((arg.get_id().as_str(), value.to_string())),
+                        None if arg.get_action().takes_values() => line.pending = Some(arg),
+                        None => {}
+                    }
+                }
+            } else if let Some(short) = word.strip_prefix('-').and_then(|flags| flags.chars().last()) {
+                if let Some(arg) = line.option(|arg| arg.get_short() == Some(short)) {
+                    if arg.get_action().takes_values() && word.len() == 2 {
+                        line.pending = Some(arg);
+                    }
+                }
+            } else if let Some(subcommand) = line
+                .command
+                .get_subcommands()
+                .find(|subcommand| line.positionals == 0 && subcommand.get_name() == word)
+            {
+                line.options.retain(|arg| arg.is_global_set());
+                line.options.extend(subcommand.get_arguments().filter(|arg| !arg.is_positional()));
+                line.command = subcommand;
+            } else {
+                if let Some(arg) = line.command.get_positionals().nth(line.positionals) {
+                    line.values.push((arg.get_id().as_str(), word.clone()));
+                }
+                line.positionals += 1;
+            }
+        }
+
+        line
+    }
+
+    fn option(&self, matches: impl Fn(&Arg) -> bool) -> Option<&'a Arg> {
+        self.options.iter().copied().find(|arg| matches(arg))
+    }
+
+    fn value(&self, id: &str) -> Option<&str> {
+        self.values.iter().rev().find(|(arg, _)| *arg == id).map(|(_, value)| value.as_str())
+    }
+}
+
+/// Print the completions for `words`, the command line after `toolbox`
+/// with the word being completed last. `--path` and `--database` on that
+/// line take precedence over the defaults given.
+pub async fn complete(cli: &Command, words: &[String], roots: Roots, db_path: &Path) -> Result<()> {
+    let line = Line::parse(cli, words);
+    let paths: Vec<Root> = line.values.iter().filter(|(id, _)| *id == "path").map(|(_, path)| Root::parse(path)).collect();
+    let roots = if paths.is_empty() { roots } else { Roots::new(paths)? };
+    let db_path = line.value("database").map(crate::expand_tilde).unwrap_or_else(|| db_path.to_path_buf());
+    let source = Source { roots, db_path, database: None };
+
+    let mut prefix = String::new();
+    let candidates = match line.pending {
+        Some(arg) => option_values(arg, &line, &line.current, source).await?,
+        None if line.current.starts_with("--") && line.current.contains('=') => {
+            let (name, value) = line.current.split_once('=').unwrap_or_default();
+            match line.option(|arg| arg.get_long() == Some(&name[2..])) {
+                Some(arg) => {
+                    prefix = format!("{}=", name);
+                    option_values(arg, &line, value, source).await?
+                }
+                None => Candidates::Words(Vec::new()),
+            }
+        }
+        None if line.current.starts_with('-') => Candidates::Words(
+            line.options
+                .iter()
+                .filter(|arg| !arg.is_hide_set())
+                .filter_map(|arg| arg.get_long().map(|long| format!("--{}", long)))
+                .chain(["--help".to_string()])
+                .collect(),
+        ),
+        None => {
+            let mut words = Vec::new();
+            if line.positionals == 0 {
+                words.extend(
+                    line.command
+                        .get_subcommands()
+                        .filter(|subcommand| !subcommand.is_hide_set())
+                        .map(|subcommand| subcommand.get_name().to_string()),
+                );
+            }
+            if let Some(arg) = line.command.get_positionals().nth(line.positionals) {
+                match option_values(arg, &line, &line.current, source).await? {
+                    Candidates::Words(values) => words.extend(values),
+                    Candidates::Files if words.is_empty() => return print(Candidates::Files, "", ""),
+                    Candidates::Files => {}
+                }
+            }
+            Candidates::Words(words)
+        }
+    };
+
+    print(candidates, &prefix, &line.current)
+}
+
+fn print(candidates: Candidates, prefix: &str, current: &str) -> Result<()> {
+    match candidates {
+        Candidates::Files => println!("{}", FILES),
+        Candidates::Words(words) => {
+            let words: BTreeSet<String> = words.into_iter().map(|word| format!("{}{}", prefix, word)).collect();
+            for word in words.iter().filter(|word| word.starts_with(current)) {
+                println!("{}", word);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Where dynamic values come from. The database is only opened when a
+/// value needs it, and never created.
+struct Source {
+    roots: Roots,
+    db_path: PathBuf,
+    database: Option<Database>,
+}
+
+impl Source {
+    /// The index, opened read-only. Completion never creates or migrates
+    /// it; an index on another schema version offers no candidates.
+    async fn database(&mut self) -> Result<Option<&Database>> {
+        if self.database.is_none() && self.db_path.exists() {
+            let database = Database::open_read_only(&self.db_path)?;
+            if database.schema_version().await? == migrations::latest_version() {
+                self.database = Some(database);
+            }
+        }
+        Ok(self.database.as_ref())
+    }
+
+    /// Indexed scripts and workflows the access policy lets this user see.
+    async fn visible(&mut self) -> Result<(Vec<Script>, Vec<Workflow>)> {
+        let Some(database) = self.database().await? else {
+            return Ok((Vec::new(), Vec::new()));
+        };
+        let (mut scripts, mut workflows) = (database.get_all_scripts().await?, database.get_all_workflows().await?);
+        let access = Policy::load()?;
+        scripts.retain(|script| access.can_see(script));
+        workflows.retain(|workflow| access.can_see_workflow(workflow));
+        Ok((scripts, workflows))
+    }
+}
+
+/// Values for `arg`, given `typed` so far.
+async fn option_values(arg: &Arg, line: &Line<'_>, typed: &str, mut source: Source) -> Result<Candidates> {
+    let possible: Vec<String> = arg.get_possible_values().iter().map(|value| value.get_name().to_string()).collect();
+    if !possible.is_empty() {
+        return Ok(Candidates::Words(possible));
+    }
+
+    let roots = source.roots.clone();
+    let words = match (line.command.get_name(), arg.get_id().as_str()) {
+        (_, "path" | "database" | "config" | "templates" | "output" | "file" | "dir") => return Ok(Candidates::Files),
+        (_, "theme") => Theme::BUILT_IN.iter().map(|theme| theme.to_string()).collect(),
+        (_, "source") => roots.iter().map(|root| root.label.clone()).collect(),
+        ("update", "label") => roots.iter().filter(|root| root.git.is_some()).map(|root| root.label.clone()).collect(),
+        (_, "category") => match source.database().await? {
+            Some(database) => database.get_all_categories().await?,
+            None => Vec::new(),
+        },
+        (_, "tag") => source.visible().await?.0.into_iter().flat_map(|script| script.tags).collect(),
+        ("help", "script") => source.visible().await?.0.into_iter().map(|script| script.name).collect(),
+        ("run", "target") => {
+            let (scripts, workflows) = source.visible().await?;
+            let mut targets: Vec<String> = scripts.into_iter().map(|script| script.name).collect();
+            for workflow in workflows {
+                // Names with spaces do not survive word splitting; the file path does
+                if workflow.name.contains(char::is_whitespace) {
+                    targets.push(roots.relative(&workflow.path).display().to_string());
+                } else {
+                    targets.push(workflow.name);
+                }
+            }
+            targets
+        }
+        ("run", "param") => {
+            let given: Vec<&str> = line
+                .values
+                .iter()
+                .filter(|(id, _)| *id == "param")
+                .filter_map(|(_, value)| value.split_once('=').map(|(name, _)| name))
+                .collect();
+            let Some(target) = line.value("target") else {
+                return Ok(Candidates::Words(Vec::new()));
+            };
+            let Some(database) = source.database().await? else {
+                return Ok(Candidates::Words(Vec::new()));
+            };
+            let parameters = run_parameters(database, &roots, target).await.unwrap_or_default();
+            param_values(&parameters, &given, typed)
+        }
+        _ => Vec::new(),
+    };
+    Ok(Candidates::Words(words))
+}
+
+/// The `--param` names of a workflow or script `toolbox run` was given.
+async fn run_parameters(database: &Database, roots: &Roots, target: &str) -> Result<Vec<(String, ScriptParameter)>> {
+    if let Some(path) = workflow::locate(database, roots, target).await? {
+        return workflow::preset_names(database, roots, &path).await;
+    }
+
+    let scripts = database.get_all_scripts().await?;
+    let parameters = match manpage::find_script(&scripts, roots, target)? {
+        Some(script) => script.parse_parameters()?,
+        None => Vec::new(),
+    };
+    Ok(parameters.into_iter().map(|parameter| (parameter.name.clone(), parameter)).collect())
+}
+
+/// `NAME=` for each parameter not given yet or, once the `=` is typed,
+/// `NAME=VALUE` for each allowed value.
+fn param_values(parameters: &[(String, ScriptParameter)], given: &[&str], typed: &str) -> Vec<String> {
+    let Some((name, _)) = typed.split_once('=') else {
+        return parameters
+            .iter()
+            .filter(|(name, _)| !given.contains(&name.as_str()))
+            .map(|(name, _)| format!("{}=", name))
+            .collect();
+    };
+
+    let Some((_, parameter)) = parameters.iter().find(|(key, _)| key == name) else {
+        return Vec::new();
+    };
+    let values: Vec<String> = match parameter.param_type {
+        ParameterType::Boolean | ParameterType::Checkbox => vec!["true".to_string(), "false".to_string()],
+        _ => parameter.options.iter().flatten().map(|option| option.value.clone()).collect(),
+    };
+    values.into_iter().map(|value| format!("{}={}", name, value)).collect()
+}
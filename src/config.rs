@@ -0,0 +1,126 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// User settings from `~/.config/toolbox/config.toml`. Every section is
+/// optional; a missing file gives the defaults.
+///
+/// ```toml
+/// [keys]
+/// preset = "vim"          # default | vim | emacs
+/// back = ["h", "Left"]    # replace the preset's keys for an action
+/// quit = ["q", "Ctrl+q"]
+///
+/// [theme]
+/// name = "light"          # dark | light | high-contrast | no-colour
+/// file = "~/.config/toolbox/theme.toml"
+///
+/// [display]
+/// ascii = true            # plain ASCII icons; detected from locale/TERM if unset
+///
+/// [legacy]
+/// index = "/opt/toolbox/toolbox_menu.ini"  # build the menu from this instead of scanning
+/// export = true           # rewrite toolbox_menu.ini after every scan
+///
+/// [docs]
+/// templates = "~/.config/toolbox/docs"  # overrides for `toolbox docs generate`
+///
+/// [[roots]]               # toolbox directories, lowest precedence first
+/// label = "system"
+/// path = "/opt/toolbox"
+///
+/// [[roots]]
+/// label = "team"
+/// git = "https://git.example.com/ops/toolbox.git"  # fetched by `toolbox sources update`
+/// rev = "stable"          # branch, tag or commit
+///
+/// [[roots]]
+/// label = "personal"
+/// path = "~/.toolbox"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub keys: KeysConfig,
+    pub theme: ThemeConfig,
+    pub display: DisplayConfig,
+    pub legacy: LegacyConfig,
+    pub docs: DocsConfig,
+    /// Replaced by any `--path` given on the command line.
+    pub roots: Vec<RootConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct KeysConfig {
+    pub preset: Option<String>,
+    /// Per-action overrides, keyed by action name (e.g. `page_down`).
+    #[serde(flatten)]
+    pub bindings: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Built-in theme to start from; overrides `base` in the theme file.
+    pub name: Option<String>,
+    /// Theme file with palette and Z-code customisations.
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// Force (`true`) or disable (`false`) ASCII rendering.
+    pub ascii: Option<bool>,
+}
+
+/// Interplay with the shell toolchain's `toolbox_menu.ini` while both are in use.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LegacyConfig {
+    /// Legacy index to read the menu from, on start and whenever it changes,
+    /// in place of scanning the toolbox directory.
+    pub index: Option<String>,
+    /// Regenerate `toolbox_menu.ini` in the toolbox directory after a scan.
+    pub export: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DocsConfig {
+    /// Directory of templates that replace the built-in ones of the same name.
+    pub templates: Option<String>,
+}
+
+/// A toolbox directory. Scripts in later roots override those at the same
+/// relative path in earlier ones.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RootConfig {
+    /// The directory; for a git root, where it is checked out (default:
+    /// under `~/.cache/toolbox/sources`).
+    pub path: Option<String>,
+    /// Shown on the menu entries from this root; defaults to the directory
+    /// or repository name.
+    pub label: Option<String>,
+    /// Repository to check the root out from.
+    pub git: Option<String>,
+    /// Branch, tag or commit of `git` to check out.
+    pub rev: Option<String>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Invalid config file: {}", path.display()))
+    }
+}
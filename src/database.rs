@@ -1,12 +1,28 @@
-use anyhow::{Context, Result};
-use rusqlite::{params, Connection, Row};
-use std::path::Path;
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row, Transaction};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::task;
 
-use crate::models::Script;
+use crate::migrations::{self, MigrationReport};
+use crate::models::{BatchRun, Elevation, ExecutionOutcome, ExecutionRecord, Script, ScriptUsage, Workflow};
+use crate::system;
 
+const WORKFLOW_COLUMNS: &str = "id, name, path, category, description, icon, order_num, steps, source, overrides";
+
+const SCRIPT_COLUMNS: &str = "id, name, path, category, menu_name, description, detailed_description, \
+    integration, info_url, icon, color, order_num, is_default, separator, tags, author, parameters, \
+    elevation, timeout_secs, resource_limits, dependency_available, source, overrides, revision, checksum, permission_issues, roles, created_at, updated_at";
+
+/// Number of prepared statements kept per connection; covers every query in this module.
+const STATEMENT_CACHE_CAPACITY: usize = 32;
+
+/// Handle to the menu database. A single connection is opened up front and
+/// shared by all queries, so the TUI does not reopen SQLite on every call.
 pub struct Database {
-    db_path: std::path::PathBuf,
+    db_path: PathBuf,
+    conn: Arc<Mutex<Connection>>,
 }
 
 impl Database {
@@ -17,186 +33,235 @@ impl Database {
                 .with_context(|| format!("Failed to create database directory: {}", parent.display()))?;
         }
 
+        let conn = Connection::open(db_path)
+            .with_context(|| format!("Failed to open database: {}", db_path.display()))?;
+
+        // WAL lets readers and the writer proceed concurrently and avoids
+        // rewriting the whole journal on every commit
+        let journal_mode: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+        if !journal_mode.eq_ignore_ascii_case("wal") {
+            eprintln!("Warning: WAL journal mode unavailable for {}, using {}", db_path.display(), journal_mode);
+        }
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+
         Ok(Self {
             db_path: db_path.to_path_buf(),
+            conn: Arc::new(Mutex::new(conn)),
         })
     }
 
-    pub async fn initialize(&self) -> Result<()> {
-        let db_path = self.db_path.clone();
-        
-        task::spawn_blocking(move || -> Result<()> {
-            let conn = Connection::open(&db_path)
-                .with_context(|| format!("Failed to open database: {}", db_path.display()))?;
-
-            conn.execute(
-                r#"
-                CREATE TABLE IF NOT EXISTS scripts (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    name TEXT NOT NULL,
-                    path TEXT NOT NULL UNIQUE,
-                    category TEXT NOT NULL,
-                    menu_name TEXT,
-                    description TEXT,
-                    detailed_description TEXT,
-                    integration TEXT,
-                    info_url TEXT,
-                    icon TEXT,
-                    color TEXT,
-                    order_num INTEGER,
-                    is_default BOOLEAN DEFAULT FALSE,
-                    separator TEXT,
-                    tags TEXT, -- JSON array
-                    author TEXT,
-                    parameters TEXT, -- JSON object
-                    dependency_available BOOLEAN DEFAULT TRUE,
-                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-                )
-                "#,
-                [],
-            )?;
-
-            // Create indexes for better performance
-            conn.execute(
-                "CREATE INDEX IF NOT EXISTS idx_scripts_category ON scripts(category)",
-                [],
-            )?;
+    /// Open an existing database without writing to it: nothing is created
+    /// or migrated. For callers that only look things up, such as shell
+    /// completion, which must not change the database as a side effect.
+    pub fn open_read_only(db_path: &Path) -> Result<Self> {
+        let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        let conn = Connection::open_with_flags(db_path, flags)
+            .with_context(|| format!("Failed to open database: {}", db_path.display()))?;
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
 
-            conn.execute(
-                "CREATE INDEX IF NOT EXISTS idx_scripts_order ON scripts(order_num)",
-                [],
-            )?;
+        Ok(Self {
+            db_path: db_path.to_path_buf(),
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
 
-            conn.execute(
-                "CREATE INDEX IF NOT EXISTS idx_scripts_name ON scripts(name)",
-                [],
-            )?;
+    /// The schema version recorded in the database, as `migrate` sets it.
+    pub async fn schema_version(&self) -> Result<u32> {
+        self.with_connection(|conn| -> Result<u32> {
+            Ok(conn.query_row("PRAGMA user_version", [], |row| row.get(0))?)
+        }).await
+    }
 
-            // Create table for script execution history
-            conn.execute(
-                r#"
-                CREATE TABLE IF NOT EXISTS execution_history (
-                    id INTEGER PRIMARY KEY AUTOINCREMENT,
-                    script_id INTEGER NOT NULL,
-                    executed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                    exit_code INTEGER,
-                    duration_ms INTEGER,
-                    parameters TEXT, -- JSON object
-                    FOREIGN KEY (script_id) REFERENCES scripts (id)
-                )
-                "#,
-                [],
-            )?;
+    /// Run a closure against the shared connection on the blocking thread pool.
+    async fn with_connection<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let conn = Arc::clone(&self.conn);
+
+        task::spawn_blocking(move || -> Result<T> {
+            let mut conn = conn
+                .lock()
+                .map_err(|_| anyhow!("Database connection lock poisoned"))?;
+            f(&mut conn)
+        }).await?
+    }
 
-            // Create table for user preferences
-            conn.execute(
-                r#"
-                CREATE TABLE IF NOT EXISTS user_preferences (
-                    key TEXT PRIMARY KEY,
-                    value TEXT NOT NULL,
-                    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-                )
-                "#,
-                [],
-            )?;
+    pub async fn initialize(&self) -> Result<Option<MigrationReport>> {
+        let db_path = self.db_path.clone();
 
-            Ok(())
-        }).await??;
+        self.with_connection(move |conn| migrations::migrate(conn, &db_path)).await
+    }
 
-        Ok(())
+    /// SQLite's data version changes whenever another connection commits,
+    /// which lets the menu refresh only when the index actually changed.
+    pub async fn data_version(&self) -> Result<i64> {
+        self.with_connection(|conn| -> Result<i64> {
+            Ok(conn.query_row("PRAGMA data_version", [], |row| row.get(0))?)
+        }).await
     }
 
     pub async fn is_empty(&self) -> Result<bool> {
-        let db_path = self.db_path.clone();
-        
-        let count = task::spawn_blocking(move || -> Result<i64> {
-            let conn = Connection::open(&db_path)?;
+        let count = self.with_connection(move |conn| -> Result<i64> {
             let count: i64 = conn.query_row(
                 "SELECT COUNT(*) FROM scripts",
                 [],
                 |row| row.get(0),
             )?;
             Ok(count)
-        }).await??;
+        }).await?;
 
         Ok(count == 0)
     }
 
-    pub async fn update_scripts(&mut self, scripts: Vec<Script>) -> Result<()> {
-        let db_path = self.db_path.clone();
-        
-        task::spawn_blocking(move || -> Result<()> {
-            let mut conn = Connection::open(&db_path)?;
-            let tx = conn.transaction()?;
+    pub async fn update_scripts(&self, scripts: Vec<Script>) -> Result<()> {
+        self.with_connection(move |conn| -> Result<()> {
+            // Rebuilding the table gives every script a new id, which would
+            // orphan `execution_history.script_id`. History finds its scripts
+            // by `script_path`, so the constraint is not enforced here.
+            conn.pragma_update(None, "foreign_keys", false)?;
+            let result = Self::replace_scripts(conn, scripts);
+            conn.pragma_update(None, "foreign_keys", true)?;
+            result
+        }).await
+    }
 
-            // Clear existing scripts
-            tx.execute("DELETE FROM scripts", [])?;
+    fn replace_scripts(conn: &mut Connection, scripts: Vec<Script>) -> Result<()> {
+        let tx = conn.transaction()?;
 
-            // Insert new scripts
-            for script in scripts {
-                let tags_json = serde_json::to_string(&script.tags)?;
-                
-                tx.execute(
-                    r#"
-                    INSERT INTO scripts (
-                        name, path, category, menu_name, description, detailed_description,
-                        integration, info_url, icon, color, order_num, is_default,
-                        separator, tags, author, parameters, dependency_available, created_at, updated_at
-                    ) VALUES (
-                        ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19
-                    )
-                    "#,
-                    params![
-                        script.name,
-                        script.path.to_string_lossy(),
-                        script.category,
-                        script.menu_name,
-                        script.description,
-                        script.detailed_description,
-                        script.integration,
-                        script.info_url,
-                        script.icon,
-                        script.color,
-                        script.order,
-                        script.is_default,
-                        script.separator,
-                        tags_json,
-                        script.author,
-                        script.parameters,
-                        script.dependency_available,
-                        script.created_at.to_rfc3339(),
-                        script.updated_at.to_rfc3339(),
-                    ],
-                )?;
+        // Clear existing scripts
+        tx.execute("DELETE FROM scripts", [])?;
+        tx.execute("DELETE FROM script_bodies_fts", [])?;
+
+        // Insert new scripts
+        for script in scripts {
+            Self::insert_script(&tx, script)?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Remove the scripts indexed at `paths`, then add `scripts`, leaving
+    /// the rest of the index as it is. Applies an incremental rescan.
+    pub async fn update_scripts_at(&self, paths: Vec<PathBuf>, scripts: Vec<Script>) -> Result<()> {
+        self.with_connection(move |conn| -> Result<()> {
+            // Same as update_scripts: history keeps the paths of removed scripts
+            conn.pragma_update(None, "foreign_keys", false)?;
+            let result = Self::replace_scripts_at(conn, paths, scripts);
+            conn.pragma_update(None, "foreign_keys", true)?;
+            result
+        }).await
+    }
+
+    fn replace_scripts_at(conn: &mut Connection, paths: Vec<PathBuf>, scripts: Vec<Script>) -> Result<()> {
+        let tx = conn.transaction()?;
+        {
+            let mut find = tx.prepare_cached("SELECT id FROM scripts WHERE path = ?1")?;
+            let mut delete_body = tx.prepare_cached("DELETE FROM script_bodies_fts WHERE rowid = ?1")?;
+            let mut delete = tx.prepare_cached("DELETE FROM scripts WHERE id = ?1")?;
+            for path in paths.iter().chain(scripts.iter().map(|script| &script.path)) {
+                let id: Option<i64> = find
+                    .query_row([path.to_string_lossy()], |row| row.get(0))
+                    .optional()?;
+                if let Some(id) = id {
+                    delete_body.execute([id])?;
+                    delete.execute([id])?;
+                }
             }
+        }
+        for script in scripts {
+            Self::insert_script(&tx, script)?;
+        }
 
-            tx.commit()?;
-            Ok(())
-        }).await??;
+        tx.commit()?;
+        Ok(())
+    }
 
+    fn insert_script(tx: &Transaction, script: Script) -> Result<()> {
+        let mut insert = tx.prepare_cached(
+            r#"
+            INSERT INTO scripts (
+                name, path, category, menu_name, description, detailed_description,
+                integration, info_url, icon, color, order_num, is_default,
+                separator, tags, author, parameters, elevation, timeout_secs, resource_limits,
+                dependency_available, source, overrides, revision, checksum, permission_issues, roles,
+                created_at, updated_at
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
+                ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28
+            )
+            "#,
+        )?;
+
+        let tags_json = serde_json::to_string(&script.tags)?;
+        let permission_issues_json = serde_json::to_string(&script.permission_issues)?;
+        let roles_json = serde_json::to_string(&script.roles)?;
+        let limits_json = if script.limits.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&script.limits)?)
+        };
+
+        insert.execute(params![
+            script.name,
+            script.path.to_string_lossy(),
+            script.category,
+            script.menu_name,
+            script.description,
+            script.detailed_description,
+            script.integration,
+            script.info_url,
+            script.icon,
+            script.color,
+            script.order,
+            script.is_default,
+            script.separator,
+            tags_json,
+            script.author,
+            script.parameters,
+            script.elevation.as_str(),
+            script.timeout_secs.map(|secs| secs as i64),
+            limits_json,
+            script.dependency_available,
+            script.source,
+            script.overrides,
+            script.revision,
+            script.checksum,
+            permission_issues_json,
+            roles_json,
+            script.created_at.to_rfc3339(),
+            script.updated_at.to_rfc3339(),
+        ])?;
+
+        if let Some(body) = &script.body {
+            let mut insert_body = tx.prepare_cached(
+                "INSERT INTO script_bodies_fts (rowid, body) VALUES (?1, ?2)",
+            )?;
+            insert_body.execute(params![tx.last_insert_rowid(), body])?;
+        }
         Ok(())
     }
 
     pub async fn get_scripts_by_category(&self, category: &str) -> Result<Vec<Script>> {
-        let db_path = self.db_path.clone();
         let category = category.to_string();
         
-        let scripts = task::spawn_blocking(move || -> Result<Vec<Script>> {
-            let conn = Connection::open(&db_path)?;
-            let mut stmt = conn.prepare(
+        let scripts = self.with_connection(move |conn| -> Result<Vec<Script>> {
+            let mut stmt = conn.prepare_cached(&format!(
                 r#"
-                SELECT id, name, path, category, menu_name, description, detailed_description,
-                       integration, info_url, icon, color, order_num, is_default,
-                       separator, tags, author, parameters, created_at, updated_at
+                SELECT {SCRIPT_COLUMNS}
                 FROM scripts 
                 WHERE category = ?1 
                 ORDER BY order_num ASC, name ASC
-                "#,
-            )?;
+                "#
+            ))?;
 
             let script_iter = stmt.query_map([&category], |row| {
-                Ok(row_to_script(row)?)
+                row_to_script(row)
             })?;
 
             let mut scripts = Vec::new();
@@ -205,22 +270,45 @@ impl Database {
             }
 
             Ok(scripts)
-        }).await??;
+        }).await?;
+
+        Ok(scripts)
+    }
+
+    /// All scripts in one query, ordered for grouping by category.
+    pub async fn get_all_scripts(&self) -> Result<Vec<Script>> {
+        let scripts = self.with_connection(move |conn| -> Result<Vec<Script>> {
+            let mut stmt = conn.prepare_cached(&format!(
+                r#"
+                SELECT {SCRIPT_COLUMNS}
+                FROM scripts
+                ORDER BY category ASC, order_num ASC, name ASC
+                "#
+            ))?;
+
+            let script_iter = stmt.query_map([], |row| {
+                row_to_script(row)
+            })?;
+
+            let mut scripts = Vec::new();
+            for script in script_iter {
+                scripts.push(script?);
+            }
+
+            Ok(scripts)
+        }).await?;
 
         Ok(scripts)
     }
 
     pub async fn get_all_categories(&self) -> Result<Vec<String>> {
-        let db_path = self.db_path.clone();
-        
-        let categories = task::spawn_blocking(move || -> Result<Vec<String>> {
-            let conn = Connection::open(&db_path)?;
-            let mut stmt = conn.prepare(
+        let categories = self.with_connection(move |conn| -> Result<Vec<String>> {
+            let mut stmt = conn.prepare_cached(
                 "SELECT DISTINCT category FROM scripts ORDER BY category"
             )?;
 
             let category_iter = stmt.query_map([], |row| {
-                Ok(row.get::<_, String>(0)?)
+                row.get::<_, String>(0)
             })?;
 
             let mut categories = Vec::new();
@@ -229,41 +317,37 @@ impl Database {
             }
 
             Ok(categories)
-        }).await??;
+        }).await?;
 
         Ok(categories)
     }
 
+    /// Full-text search over the FTS5 index, best BM25 match first.
+    ///
+    /// Every word of the query must match (as a prefix) somewhere in the
+    /// name, menu name, descriptions, tags, author or category.
     pub async fn search_scripts(&self, query: &str) -> Result<Vec<Script>> {
-        let db_path = self.db_path.clone();
-        let query = format!("%{}%", query.to_lowercase());
+        let Some(fts_query) = fts_match_expression(query) else {
+            return Ok(Vec::new());
+        };
         
-        let scripts = task::spawn_blocking(move || -> Result<Vec<Script>> {
-            let conn = Connection::open(&db_path)?;
-            let mut stmt = conn.prepare(
+        let scripts = self.with_connection(move |conn| -> Result<Vec<Script>> {
+            let mut stmt = conn.prepare_cached(&format!(
                 r#"
-                SELECT id, name, path, category, menu_name, description, detailed_description,
-                       integration, info_url, icon, color, order_num, is_default,
-                       separator, tags, author, parameters, dependency_available, created_at, updated_at
-                FROM scripts 
-                WHERE LOWER(name) LIKE ?1 
-                   OR LOWER(menu_name) LIKE ?1 
-                   OR LOWER(description) LIKE ?1 
-                   OR LOWER(detailed_description) LIKE ?1
-                   OR LOWER(tags) LIKE ?1
-                ORDER BY 
-                    CASE 
-                        WHEN LOWER(name) LIKE ?1 THEN 1
-                        WHEN LOWER(menu_name) LIKE ?1 THEN 2
-                        WHEN LOWER(description) LIKE ?1 THEN 3
-                        ELSE 4
-                    END,
-                    name ASC
-                "#,
-            )?;
-
-            let script_iter = stmt.query_map([&query], |row| {
-                Ok(row_to_script(row)?)
+                SELECT {SCRIPT_COLUMNS}
+                FROM scripts
+                JOIN (
+                    SELECT rowid AS fts_id,
+                           bm25(scripts_fts, 10.0, 10.0, 5.0, 1.0, 5.0, 2.0, 2.0) AS fts_rank
+                    FROM scripts_fts
+                    WHERE scripts_fts MATCH ?1
+                ) ON fts_id = scripts.id
+                ORDER BY fts_rank ASC, name ASC
+                "#
+            ))?;
+
+            let script_iter = stmt.query_map([&fts_query], |row| {
+                row_to_script(row)
             })?;
 
             let mut scripts = Vec::new();
@@ -272,29 +356,55 @@ impl Database {
             }
 
             Ok(scripts)
-        }).await??;
+        }).await?;
 
         Ok(scripts)
     }
 
+    /// Search indexed script bodies. Returns each matching script's path and
+    /// indexed body, best match first.
+    pub async fn search_bodies(&self, query: &str) -> Result<Vec<(PathBuf, String)>> {
+        let Some(fts_query) = fts_match_expression(query) else {
+            return Ok(Vec::new());
+        };
+
+        self.with_connection(move |conn| -> Result<Vec<(PathBuf, String)>> {
+            let mut stmt = conn.prepare_cached(
+                r#"
+                SELECT scripts.path, script_bodies_fts.body
+                FROM script_bodies_fts
+                JOIN scripts ON scripts.id = script_bodies_fts.rowid
+                WHERE script_bodies_fts MATCH ?1
+                ORDER BY bm25(script_bodies_fts) ASC, scripts.name ASC
+                "#,
+            )?;
+
+            let rows = stmt.query_map([&fts_query], |row| {
+                Ok((PathBuf::from(row.get::<_, String>(0)?), row.get::<_, String>(1)?))
+            })?;
+
+            let mut bodies = Vec::new();
+            for row in rows {
+                bodies.push(row?);
+            }
+            Ok(bodies)
+        }).await
+    }
+
     pub async fn get_script_by_path(&self, path: &str) -> Result<Option<Script>> {
-        let db_path = self.db_path.clone();
         let path = path.to_string();
         
-        let script = task::spawn_blocking(move || -> Result<Option<Script>> {
-            let conn = Connection::open(&db_path)?;
-            let mut stmt = conn.prepare(
+        let script = self.with_connection(move |conn| -> Result<Option<Script>> {
+            let mut stmt = conn.prepare_cached(&format!(
                 r#"
-                SELECT id, name, path, category, menu_name, description, detailed_description,
-                       integration, info_url, icon, color, order_num, is_default,
-                       separator, tags, author, parameters, dependency_available, created_at, updated_at
+                SELECT {SCRIPT_COLUMNS}
                 FROM scripts 
                 WHERE path = ?1
-                "#,
-            )?;
+                "#
+            ))?;
 
             let mut script_iter = stmt.query_map([&path], |row| {
-                Ok(row_to_script(row)?)
+                row_to_script(row)
             })?;
 
             if let Some(script) = script_iter.next() {
@@ -302,26 +412,359 @@ impl Database {
             } else {
                 Ok(None)
             }
-        }).await??;
+        }).await?;
 
         Ok(script)
     }
 
-    pub async fn record_execution(&self, script_id: i64, exit_code: i32, duration_ms: u64, parameters: Option<&str>) -> Result<()> {
-        let db_path = self.db_path.clone();
+    pub async fn update_workflows(&self, workflows: Vec<Workflow>) -> Result<()> {
+        self.with_connection(move |conn| -> Result<()> {
+            let tx = conn.transaction()?;
+
+            tx.execute("DELETE FROM workflows", [])?;
+            {
+                let mut insert = tx.prepare_cached(
+                    r#"
+                    INSERT INTO workflows (name, path, category, description, icon, order_num, steps, source, overrides)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                    "#,
+                )?;
+
+                for workflow in workflows {
+                    insert.execute(params![
+                        workflow.name,
+                        workflow.path.to_string_lossy(),
+                        workflow.category,
+                        workflow.description,
+                        workflow.icon,
+                        workflow.order,
+                        serde_json::to_string(&workflow.steps)?,
+                        workflow.source,
+                        workflow.overrides,
+                    ])?;
+                }
+            }
+
+            tx.commit()?;
+            Ok(())
+        }).await
+    }
+
+    /// All workflows, ordered for grouping by category.
+    pub async fn get_all_workflows(&self) -> Result<Vec<Workflow>> {
+        self.with_connection(move |conn| -> Result<Vec<Workflow>> {
+            let mut stmt = conn.prepare_cached(&format!(
+                r#"
+                SELECT {WORKFLOW_COLUMNS}
+                FROM workflows
+                ORDER BY category ASC, order_num ASC, name ASC
+                "#
+            ))?;
+
+            let workflows = stmt
+                .query_map([], row_to_workflow)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(workflows)
+        }).await
+    }
+
+    pub async fn get_workflows_by_category(&self, category: &str) -> Result<Vec<Workflow>> {
+        let category = category.to_string();
+
+        self.with_connection(move |conn| -> Result<Vec<Workflow>> {
+            let mut stmt = conn.prepare_cached(&format!(
+                r#"
+                SELECT {WORKFLOW_COLUMNS}
+                FROM workflows
+                WHERE category = ?1
+                ORDER BY order_num ASC, name ASC
+                "#
+            ))?;
+
+            let workflows = stmt
+                .query_map([&category], row_to_workflow)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(workflows)
+        }).await
+    }
+
+    /// A workflow by name (ignoring case) or by file path.
+    pub async fn find_workflow(&self, name_or_path: &str) -> Result<Option<Workflow>> {
+        let name_or_path = name_or_path.to_string();
+
+        self.with_connection(move |conn| -> Result<Option<Workflow>> {
+            let mut stmt = conn.prepare_cached(&format!(
+                r#"
+                SELECT {WORKFLOW_COLUMNS}
+                FROM workflows
+                WHERE name = ?1 COLLATE NOCASE OR path = ?1
+                ORDER BY path
+                "#
+            ))?;
+
+            let mut workflows = stmt.query_map([&name_or_path], row_to_workflow)?;
+            Ok(workflows.next().transpose()?)
+        }).await
+    }
+
+    pub async fn record_execution(
+        &self,
+        script: &Script,
+        outcome: &ExecutionOutcome,
+        parameters: Option<&str>,
+        batch_id: Option<i64>,
+    ) -> Result<()> {
+        let script_id = script.id.unwrap_or_default();
+        let script_path = script.path.to_string_lossy().to_string();
+        let revision = script.revision.clone();
+        let outcome = outcome.clone();
         let parameters = parameters.map(|s| s.to_string());
         
-        task::spawn_blocking(move || -> Result<()> {
-            let conn = Connection::open(&db_path)?;
+        self.with_connection(move |conn| -> Result<()> {
             conn.execute(
-                "INSERT INTO execution_history (script_id, exit_code, duration_ms, parameters) VALUES (?1, ?2, ?3, ?4)",
-                params![script_id, exit_code, duration_ms as i64, parameters],
+                r#"
+                INSERT INTO execution_history (
+                    script_id, script_path, exit_code, status, signal, core_dumped, cancelled,
+                    started_at, finished_at, hostname, username, duration_ms, parameters, batch_id,
+                    script_revision
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+                "#,
+                params![
+                    script_id,
+                    script_path,
+                    outcome.exit_code,
+                    outcome.status(),
+                    outcome.signal,
+                    outcome.core_dumped,
+                    outcome.cancelled,
+                    outcome.started_at.to_rfc3339(),
+                    outcome.finished_at.to_rfc3339(),
+                    outcome.hostname,
+                    outcome.user,
+                    outcome.duration_ms() as i64,
+                    parameters,
+                    batch_id,
+                    revision,
+                ],
             )?;
             Ok(())
-        }).await??;
+        }).await?;
 
         Ok(())
     }
+
+    /// Start a batch run; its scripts' executions are recorded against the
+    /// returned id and the totals filled in by `finish_batch_run`.
+    pub async fn start_batch_run(
+        &self,
+        mode: &str,
+        failure_policy: &str,
+        total: usize,
+        workflow: Option<&str>,
+    ) -> Result<i64> {
+        let mode = mode.to_string();
+        let failure_policy = failure_policy.to_string();
+        let workflow = workflow.map(|s| s.to_string());
+
+        self.with_connection(move |conn| -> Result<i64> {
+            conn.execute(
+                r#"
+                INSERT INTO batch_runs (mode, failure_policy, total, workflow, started_at, hostname, username)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                "#,
+                params![
+                    mode,
+                    failure_policy,
+                    total as i64,
+                    workflow,
+                    chrono::Utc::now().to_rfc3339(),
+                    system::hostname(),
+                    system::current_user(),
+                ],
+            )?;
+            Ok(conn.last_insert_rowid())
+        }).await
+    }
+
+    pub async fn finish_batch_run(&self, batch_id: i64, succeeded: usize, failed: usize, skipped: usize) -> Result<()> {
+        self.with_connection(move |conn| -> Result<()> {
+            conn.execute(
+                r#"
+                UPDATE batch_runs
+                SET succeeded = ?2, failed = ?3, skipped = ?4, finished_at = ?5
+                WHERE id = ?1
+                "#,
+                params![
+                    batch_id,
+                    succeeded as i64,
+                    failed as i64,
+                    skipped as i64,
+                    chrono::Utc::now().to_rfc3339(),
+                ],
+            )?;
+            Ok(())
+        }).await
+    }
+
+    /// Run counts per script path, most used first. A run succeeded when
+    /// the script exited 0 on its own (not timed out or cancelled); refused
+    /// runs never started and are not counted.
+    pub async fn get_script_usage(&self) -> Result<Vec<ScriptUsage>> {
+        self.with_connection(move |conn| -> Result<Vec<ScriptUsage>> {
+            let mut stmt = conn.prepare_cached(
+                r#"
+                SELECT script_path,
+                       COUNT(*) AS runs,
+                       SUM(CASE WHEN exit_code = 0 AND status = 'exited' AND NOT COALESCE(cancelled, 0) THEN 1 ELSE 0 END) AS succeeded,
+                       AVG(COALESCE(duration_ms, 0)) AS average_duration_ms,
+                       MAX(COALESCE(started_at, executed_at)) AS last_run
+                FROM execution_history
+                WHERE script_path IS NOT NULL AND COALESCE(status, '') <> 'refused'
+                GROUP BY script_path
+                ORDER BY runs DESC, script_path ASC
+                "#,
+            )?;
+
+            let usage_iter = stmt.query_map([], |row| {
+                Ok(ScriptUsage {
+                    script_path: PathBuf::from(row.get::<_, String>("script_path")?),
+                    runs: row.get::<_, i64>("runs")? as usize,
+                    succeeded: row.get::<_, i64>("succeeded")? as usize,
+                    average_duration_ms: row.get::<_, f64>("average_duration_ms")? as u64,
+                    last_run: parse_timestamp(row.get("last_run")?),
+                })
+            })?;
+
+            let mut usage = Vec::new();
+            for entry in usage_iter {
+                usage.push(entry?);
+            }
+
+            Ok(usage)
+        }).await
+    }
+
+    pub async fn get_execution_history(&self, limit: usize) -> Result<Vec<ExecutionRecord>> {
+
+        let records = self.with_connection(move |conn| -> Result<Vec<ExecutionRecord>> {
+            let mut stmt = conn.prepare_cached(
+                r#"
+                SELECT h.exit_code, h.status, h.signal, h.core_dumped, h.cancelled,
+                       h.started_at, h.finished_at, h.executed_at, h.hostname, h.username,
+                       h.duration_ms, h.batch_id, h.script_revision,
+                       b.mode AS batch_mode, b.workflow AS batch_workflow,
+                       b.failure_policy AS batch_failure_policy,
+                       b.succeeded AS batch_succeeded,
+                       b.failed AS batch_failed, b.skipped AS batch_skipped,
+                       COALESCE(s.menu_name, s.name) AS script_name,
+                       COALESCE(h.script_path, s.path) AS script_path
+                FROM execution_history h
+                LEFT JOIN scripts s ON s.path = h.script_path
+                LEFT JOIN batch_runs b ON b.id = h.batch_id
+                ORDER BY h.id DESC
+                LIMIT ?1
+                "#,
+            )?;
+
+            let record_iter = stmt.query_map([limit as i64], row_to_execution_record)?;
+
+            let mut records = Vec::new();
+            for record in record_iter {
+                records.push(record?);
+            }
+
+            Ok(records)
+        }).await?;
+
+        Ok(records)
+    }
+}
+
+/// Turn free text into an FTS5 expression: each word becomes a quoted
+/// prefix term, so punctuation in the query can never be parsed as syntax.
+fn fts_match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word.to_lowercase()))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+fn parse_timestamp(value: Option<String>) -> Option<chrono::DateTime<chrono::Utc>> {
+    let value = value?;
+    chrono::DateTime::parse_from_rfc3339(&value)
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+        .ok()
+        .or_else(|| {
+            // executed_at uses SQLite's CURRENT_TIMESTAMP format
+            chrono::NaiveDateTime::parse_from_str(&value, "%Y-%m-%d %H:%M:%S")
+                .map(|dt| dt.and_utc())
+                .ok()
+        })
+}
+
+fn row_to_execution_record(row: &Row) -> rusqlite::Result<ExecutionRecord> {
+    let executed_at = parse_timestamp(row.get("executed_at")?).unwrap_or_else(chrono::Utc::now);
+    let started_at = parse_timestamp(row.get("started_at")?).unwrap_or(executed_at);
+    let finished_at = parse_timestamp(row.get("finished_at")?).unwrap_or_else(|| {
+        let duration_ms = row.get::<_, Option<i64>>("duration_ms").ok().flatten().unwrap_or(0);
+        started_at + chrono::Duration::milliseconds(duration_ms)
+    });
+    let status: Option<String> = row.get("status")?;
+
+    Ok(ExecutionRecord {
+        script_name: row
+            .get::<_, Option<String>>("script_name")?
+            .unwrap_or_else(|| "(removed script)".to_string()),
+        script_path: std::path::PathBuf::from(row.get::<_, Option<String>>("script_path")?.unwrap_or_default()),
+        outcome: ExecutionOutcome {
+            exit_code: row.get("exit_code")?,
+            signal: row.get("signal")?,
+            core_dumped: row.get::<_, Option<bool>>("core_dumped")?.unwrap_or(false),
+            timed_out: status.as_deref() == Some("timed_out"),
+            cancelled: row.get::<_, Option<bool>>("cancelled")?.unwrap_or(false),
+            refused: status.as_deref() == Some("refused"),
+            started_at,
+            finished_at,
+            hostname: row.get::<_, Option<String>>("hostname")?.unwrap_or_default(),
+            user: row.get::<_, Option<String>>("username")?.unwrap_or_default(),
+        },
+        revision: row.get("script_revision")?,
+        batch: match row.get::<_, Option<i64>>("batch_id")? {
+            Some(id) => Some(BatchRun {
+                id,
+                mode: row.get::<_, Option<String>>("batch_mode")?.unwrap_or_default(),
+                workflow: row.get("batch_workflow")?,
+                failure_policy: row.get::<_, Option<String>>("batch_failure_policy")?.unwrap_or_default(),
+                succeeded: row.get::<_, Option<i64>>("batch_succeeded")?.unwrap_or(0) as usize,
+                failed: row.get::<_, Option<i64>>("batch_failed")?.unwrap_or(0) as usize,
+                skipped: row.get::<_, Option<i64>>("batch_skipped")?.unwrap_or(0) as usize,
+            }),
+            None => None,
+        },
+    })
+}
+
+fn row_to_workflow(row: &Row) -> rusqlite::Result<Workflow> {
+    let steps_json: String = row.get("steps")?;
+
+    Ok(Workflow {
+        name: row.get("name")?,
+        path: PathBuf::from(row.get::<_, String>("path")?),
+        category: row.get("category")?,
+        description: row.get("description")?,
+        icon: row.get("icon")?,
+        order: row.get("order_num")?,
+        steps: serde_json::from_str(&steps_json).unwrap_or_default(),
+        source: row.get("source")?,
+        overrides: row.get("overrides")?,
+    })
 }
 
 fn row_to_script(row: &Row) -> rusqlite::Result<Script> {
@@ -357,7 +800,23 @@ fn row_to_script(row: &Row) -> rusqlite::Result<Script> {
         tags,
         author: row.get("author")?,
         parameters: row.get("parameters")?,
+        elevation: row
+            .get::<_, Option<String>>("elevation")?
+            .and_then(|value| Elevation::parse(&value))
+            .unwrap_or_default(),
+        timeout_secs: row.get::<_, Option<i64>>("timeout_secs")?.map(|secs| secs as u64),
+        limits: row
+            .get::<_, Option<String>>("resource_limits")?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
         dependency_available: row.get("dependency_available")?,
+        source: row.get("source")?,
+        overrides: row.get("overrides")?,
+        revision: row.get("revision")?,
+        checksum: row.get("checksum")?,
+        permission_issues: serde_json::from_str(&row.get::<_, String>("permission_issues")?).unwrap_or_default(),
+        roles: serde_json::from_str(&row.get::<_, String>("roles")?).unwrap_or_default(),
+        body: None,
         created_at,
         updated_at,
     })
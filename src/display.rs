@@ -1,86 +1,168 @@
 use anyhow::Result;
-use indicatif::{ProgressBar, ProgressStyle};
-use std::path::PathBuf;
-use std::process::{Command, Stdio};
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command as TokioCommand;
-
-use crate::models::Script;
+use std::future::Future;
+use std::io::Write;
+use std::os::unix::process::ExitStatusExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command as TokioCommand};
+use tempfile::NamedTempFile;
+use tokio::sync::{mpsc, watch};
+
+use crate::audit::{AuditEvent, Auditor};
+use crate::glyphs::{self, Glyphs};
+use crate::integrity::{IntegrityPolicy, Snapshot, Verdict};
+use crate::models::{ExecutionOutcome, Script};
+use crate::policy::Policy;
+use crate::params::{ParameterValues, PARAM_ENV_PREFIX};
+use crate::roots::Roots;
+use crate::system::{self, PrivilegeContext};
+
+const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Audited reason for a run refused because the user cannot use sudo.
+pub const NO_SUDO: &str = "requires root privileges and the user cannot use sudo";
+
+/// Environment variable holding the path of the script being run. When it
+/// runs from a private copy, `$0` is the copy.
+const SCRIPT_ENV: &str = "TOOLBOX_SCRIPT";
+
+/// Why a script was stopped before it exited by itself.
+enum Interruption {
+    Timeout,
+    Aborted,
+}
 
+#[derive(Clone)]
 pub struct ScriptExecutor {
-    toolbox_path: PathBuf,
+    roots: Roots,
+    privileges: PrivilegeContext,
+    default_timeout: Option<Duration>,
+    integrity: IntegrityPolicy,
+    access: Policy,
+    audit: Auditor,
+    glyphs: Glyphs,
 }
 
 impl ScriptExecutor {
-    pub fn new(toolbox_path: PathBuf) -> Self {
-        Self { toolbox_path }
+    pub fn new(
+        roots: Roots,
+        privileges: PrivilegeContext,
+        default_timeout: Option<Duration>,
+        integrity: IntegrityPolicy,
+        access: Policy,
+        audit: Auditor,
+        glyphs: Glyphs,
+    ) -> Self {
+        Self { roots, privileges, default_timeout, integrity, access, audit, glyphs }
+    }
+
+    /// Why the access policy does not let this user run `script`, if it
+    /// does not. The refusal is logged.
+    pub fn access_denied(&self, script: &Script) -> Option<String> {
+        (!self.access.can_run(script)).then(|| self.access.deny_run(script))
     }
 
-    pub async fn execute(&self, script: &Script) -> Result<i32> {
-        println!("🚀 Executing: {}", script.display_name());
-        println!("📝 Description: {}", script.display_description());
+    pub async fn execute(&self, script: &Script, parameters: &ParameterValues) -> Result<ExecutionOutcome> {
+        println!("{} Executing: {}", self.glyphs.run, script.display_name());
+        println!("{} Description: {}", self.glyphs.description, script.display_description());
         
         if let Some(info_url) = &script.info_url {
-            println!("ℹ️  Info: {}", info_url);
+            println!("{} Info: {}", self.glyphs.info, info_url);
         }
 
-        println!("📁 Path: {}", script.path.display());
+        println!("{} Path: {}", self.glyphs.path, script.path.display());
         println!("{}", "=".repeat(60));
 
-        // Check if script has parameters
-        if script.has_parameters() {
-            println!("⚙️  This script has parameters. Parameter collection not yet implemented.");
-            println!("   Proceeding with default execution...");
+        if let Some(denied) = self.access_denied(script) {
+            println!("{} {}", self.glyphs.unavailable, denied);
+            return Ok(self.refuse(script, parameters, None, &denied));
+        }
+        let snapshot = match self.snapshot(script, parameters, "") {
+            Ok(snapshot) => snapshot,
+            Err(refused) => {
+                println!("{} Script not executed", self.glyphs.failure);
+                return Ok(refused);
+            }
+        };
+
+        // Elevation pre-flight: authenticate before the script starts, not halfway through
+        if self.needs_sudo(script) {
+            if !self.privileges.can_sudo {
+                println!("{} This script requires root privileges and you are not allowed to use sudo.", self.glyphs.unavailable);
+                return Ok(self.refuse(script, parameters, Some(snapshot.sha256.clone()), NO_SUDO));
+            }
+
+            println!("{} This script requires root privileges - validating sudo credentials...", self.glyphs.sudo);
+            if let Err(e) = system::validate_sudo() {
+                println!("{} {} - script not executed", self.glyphs.failure, e);
+                return Ok(self.refuse(script, parameters, Some(snapshot.sha256.clone()), &e.to_string()));
+            }
+        }
+
+        if !parameters.is_empty() {
+            println!("{} Parameters: {}", self.glyphs.params, parameters.describe());
+        }
+
+        let timeout = self.timeout_for(script);
+        if let Some(timeout) = timeout {
+            println!("{} Timeout: {}s", self.glyphs.timeout, timeout.as_secs());
         }
 
         // Detect script features for enhanced display
-        let script_content = tokio::fs::read_to_string(&script.path).await?;
-        let features = self.detect_script_features(&script_content);
+        let features = self.detect_script_features(&String::from_utf8_lossy(&snapshot.content));
 
-        let exit_code = if features.has_package_manager {
-            self.execute_with_progress_tracking(script, &features).await?
+        let outcome = if features.has_package_manager {
+            self.execute_with_progress_tracking(script, &snapshot, parameters, &features, timeout).await?
         } else if features.has_file_output {
-            self.execute_with_output_capture(script).await?
+            self.execute_with_output_capture(script, &snapshot, parameters, timeout).await?
         } else {
-            self.execute_simple(script).await?
+            self.execute_simple(script, &snapshot, parameters, timeout).await?
         };
+        self.audit.record(&AuditEvent::new(self.access.identity(), script, parameters, &outcome, Some(snapshot.sha256.clone()), self.needs_sudo(script)));
 
         println!("{}", "=".repeat(60));
-        if exit_code == 0 {
-            println!("✅ Script completed successfully");
+        if outcome.success() {
+            println!("{} Script completed successfully", self.glyphs.success);
+        } else if outcome.timed_out {
+            println!(
+                "{} Script timed out after {}s and was terminated",
+                self.glyphs.timeout,
+                timeout.map(|t| t.as_secs()).unwrap_or_default()
+            );
+        } else if outcome.cancelled {
+            println!("{} Script cancelled by user", self.glyphs.cancelled);
         } else {
-            println!("❌ Script failed with exit code: {}", exit_code);
+            println!("{} Script failed: {}", self.glyphs.failure, outcome.describe());
         }
 
-        Ok(exit_code)
+        Ok(outcome)
     }
 
-    async fn execute_simple(&self, script: &Script) -> Result<i32> {
-        let mut cmd = TokioCommand::new("bash");
-        cmd.arg(&script.path);
-        cmd.current_dir(&self.toolbox_path);
+    async fn execute_simple(&self, script: &Script, snapshot: &Snapshot, parameters: &ParameterValues, timeout: Option<Duration>) -> Result<ExecutionOutcome> {
+        let (mut cmd, _launcher) = self.build_command(script, snapshot, parameters)?;
 
-        let mut child = cmd.spawn()?;
-        let status = child.wait().await?;
-
-        Ok(status.code().unwrap_or(-1))
+        let child = cmd.spawn()?;
+        self.supervise(child, self.needs_sudo(script), timeout, async {}, std::future::pending()).await
     }
 
-    async fn execute_with_progress_tracking(&self, script: &Script, _features: &ScriptFeatures) -> Result<i32> {
-        println!("📦 Detected package manager operations - showing progress");
+    async fn execute_with_progress_tracking(&self, script: &Script, snapshot: &Snapshot, parameters: &ParameterValues, _features: &ScriptFeatures, timeout: Option<Duration>) -> Result<ExecutionOutcome> {
+        println!("{} Detected package manager operations - showing progress", self.glyphs.package);
         
-        println!("📦 Package manager operations detected - monitoring progress");
+        println!("{} Package manager operations detected - monitoring progress", self.glyphs.package);
         
-        let mut cmd = TokioCommand::new("bash");
-        cmd.arg(&script.path);
-        cmd.current_dir(&self.toolbox_path);
+        let (mut cmd, _launcher) = self.build_command(script, snapshot, parameters)?;
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
 
         let mut child = cmd.spawn()?;
+        let stdout = child.stdout.take();
         
         // Monitor output for progress indicators
-        if let Some(stdout) = child.stdout.take() {
+        let glyphs = self.glyphs;
+        let output = async move {
+            let Some(stdout) = stdout else { return };
             let reader = BufReader::new(stdout);
             let mut lines = reader.lines();
 
@@ -89,40 +171,43 @@ impl ScriptExecutor {
                 
                 // Show progress messages based on common package manager outputs
                 if line.contains("Reading package lists") {
-                    println!("📋 Reading package lists...");
+                    println!("{} Reading package lists...", glyphs.package_lists);
                 } else if line.contains("Building dependency tree") {
-                    println!("🔗 Building dependency tree...");
+                    println!("{} Building dependency tree...", glyphs.package_deps);
                 } else if line.contains("Downloading") || line.contains("Get:") {
-                    println!("⬇️  Downloading packages...");
+                    println!("{} Downloading packages...", glyphs.package_download);
                 } else if line.contains("Unpacking") {
-                    println!("📦 Unpacking packages...");
+                    println!("{} Unpacking packages...", glyphs.package);
                 } else if line.contains("Setting up") {
-                    println!("⚙️  Setting up packages...");
+                    println!("{} Setting up packages...", glyphs.package_setup);
                 } else if line.contains("Processing triggers") {
-                    println!("🔧 Processing triggers...");
+                    println!("{} Processing triggers...", glyphs.package_triggers);
                 }
             }
-        }
+        };
 
-        let status = child.wait().await?;
-        println!("✅ Package operations completed");
+        let outcome = self.supervise(child, self.needs_sudo(script), timeout, output, std::future::pending()).await?;
+        if !outcome.timed_out && !outcome.cancelled {
+            println!("{} Package operations completed", self.glyphs.success);
+        }
 
-        Ok(status.code().unwrap_or(-1))
+        Ok(outcome)
     }
 
-    async fn execute_with_output_capture(&self, script: &Script) -> Result<i32> {
-        println!("📄 Script may produce file output - enhanced display enabled");
+    async fn execute_with_output_capture(&self, script: &Script, snapshot: &Snapshot, parameters: &ParameterValues, timeout: Option<Duration>) -> Result<ExecutionOutcome> {
+        println!("{} Script may produce file output - enhanced display enabled", self.glyphs.file);
 
-        let mut cmd = TokioCommand::new("bash");
-        cmd.arg(&script.path);
-        cmd.current_dir(&self.toolbox_path);
+        let (mut cmd, _launcher) = self.build_command(script, snapshot, parameters)?;
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
 
         let mut child = cmd.spawn()?;
+        let stdout = child.stdout.take();
         
         // Capture and display output with potential file viewing
-        if let Some(stdout) = child.stdout.take() {
+        let glyphs = self.glyphs;
+        let output = async move {
+            let Some(stdout) = stdout else { return };
             let reader = BufReader::new(stdout);
             let mut lines = reader.lines();
 
@@ -131,14 +216,320 @@ impl ScriptExecutor {
                 
                 // Check for file output patterns
                 if line.contains("cat ") || line.contains("tail ") || line.contains("less ") {
-                    println!("🔍 File viewing detected - enhanced display available");
+                    println!("{} File viewing detected - enhanced display available", glyphs.search);
                     // Future: Implement enhanced file viewing with search
                 }
             }
+        };
+
+        self.supervise(child, self.needs_sudo(script), timeout, output, std::future::pending()).await
+    }
+
+    /// Run several scripts at once. They cannot share the terminal, so their
+    /// output is prefixed with the script name and stdin is closed. With
+    /// `stop_on_error` the first failure terminates the scripts still running.
+    /// Outcomes are returned in the order of `runs`.
+    pub async fn execute_parallel(&self, runs: Vec<(Script, ParameterValues)>, stop_on_error: bool) -> Result<Vec<ExecutionOutcome>> {
+        let (abort_tx, abort_rx) = watch::channel(false);
+        let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+        let label_width = runs
+            .iter()
+            .map(|(script, _)| glyphs::display_width(script.display_name()))
+            .max()
+            .unwrap_or(0);
+
+        let count = runs.len();
+        for (index, (script, parameters)) in runs.into_iter().enumerate() {
+            let executor = self.clone();
+            let mut abort_rx = abort_rx.clone();
+            let done_tx = done_tx.clone();
+            let label = format!("[{}]", glyphs::pad(script.display_name(), label_width));
+
+            tokio::spawn(async move {
+                let abort = async move {
+                    // A dropped sender means the batch is over, not stopped
+                    if abort_rx.wait_for(|&stop| stop).await.is_err() {
+                        std::future::pending::<()>().await;
+                    }
+                };
+                let outcome = match executor.execute_captured(&script, &parameters, &label, abort).await {
+                    Ok(outcome) => outcome,
+                    Err(e) => {
+                        println!("{} {} {}", label, executor.glyphs.failure, e);
+                        Self::not_executed()
+                    }
+                };
+                let _ = done_tx.send((index, outcome));
+            });
+        }
+        drop(done_tx);
+
+        let mut outcomes = vec![None; count];
+        while let Some((index, outcome)) = done_rx.recv().await {
+            if stop_on_error && !outcome.success() {
+                let _ = abort_tx.send(true);
+            }
+            outcomes[index] = Some(outcome);
+        }
+
+        Ok(outcomes.into_iter().map(|outcome| outcome.unwrap_or_else(Self::not_executed)).collect())
+    }
+
+    async fn execute_captured(&self, script: &Script, parameters: &ParameterValues, label: &str, abort: impl Future<Output = ()>) -> Result<ExecutionOutcome> {
+        if let Some(denied) = self.access_denied(script) {
+            println!("{} {} {}", label, self.glyphs.unavailable, denied);
+            return Ok(self.refuse(script, parameters, None, &denied));
+        }
+        let snapshot = match self.snapshot(script, parameters, &format!("{} ", label)) {
+            Ok(snapshot) => snapshot,
+            Err(refused) => {
+                println!("{} {} Script not executed", label, self.glyphs.failure);
+                return Ok(refused);
+            }
+        };
+        if self.needs_sudo(script) && !self.privileges.can_sudo {
+            println!("{} {} This script requires root privileges and you are not allowed to use sudo.", label, self.glyphs.unavailable);
+            return Ok(self.refuse(script, parameters, Some(snapshot.sha256.clone()), NO_SUDO));
         }
 
-        let status = child.wait().await?;
-        Ok(status.code().unwrap_or(-1))
+        let (mut cmd, _launcher) = self.build_command(script, &snapshot, parameters)?;
+        cmd.stdin(Stdio::null());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+
+        let mut child = cmd.spawn()?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let output = async move {
+            tokio::join!(print_prefixed(stdout, label), print_prefixed(stderr, label));
+        };
+
+        let outcome = self.supervise(child, self.needs_sudo(script), self.timeout_for(script), output, abort).await?;
+        self.audit.record(&AuditEvent::new(self.access.identity(), script, parameters, &outcome, Some(snapshot.sha256.clone()), self.needs_sudo(script)));
+        let icon = if outcome.success() { self.glyphs.success } else { self.glyphs.failure };
+        println!("{} {} {}", label, icon, outcome.describe());
+        Ok(outcome)
+    }
+
+    /// Wait for a script (and its output handling) to finish, enforcing the
+    /// timeout: SIGTERM first, then SIGKILL once the grace period has passed.
+    /// A script holding the terminal gets Ctrl+C directly; otherwise it
+    /// reaches us and is passed on to the script's process group. Either
+    /// way the run is recorded as cancelled. When `abort` completes the
+    /// script is terminated and recorded as cancelled too.
+    async fn supervise(
+        &self,
+        mut child: Child,
+        elevated: bool,
+        timeout: Option<Duration>,
+        output: impl Future<Output = ()>,
+        abort: impl Future<Output = ()>,
+    ) -> Result<ExecutionOutcome> {
+        let started_at = chrono::Utc::now();
+        let pid = child.id();
+        let mut cancelled = false;
+
+        let finished = {
+            let run = async {
+                output.await;
+                child.wait().await
+            };
+            let deadline = async {
+                match timeout {
+                    Some(timeout) => tokio::time::sleep(timeout).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::pin!(run);
+            tokio::pin!(deadline);
+            tokio::pin!(abort);
+
+            loop {
+                tokio::select! {
+                    status = &mut run => break Ok(status?),
+                    _ = &mut deadline => break Err(Interruption::Timeout),
+                    _ = &mut abort => break Err(Interruption::Aborted),
+                    _ = tokio::signal::ctrl_c(), if !cancelled => {
+                        cancelled = true;
+                        if let Some(pid) = pid {
+                            system::signal_script(pid, libc::SIGINT, elevated);
+                        }
+                    }
+                }
+            }
+        };
+
+        let (status, timed_out) = match finished {
+            Ok(status) => (Some(status), false),
+            Err(Interruption::Timeout) => (self.terminate(&mut child, elevated, "Timeout reached").await?, true),
+            Err(Interruption::Aborted) => {
+                cancelled = true;
+                (self.terminate(&mut child, elevated, "Batch stopped").await?, false)
+            }
+        };
+        if let Some(pid) = pid {
+            system::restore_terminal(pid);
+        }
+        cancelled |= status.and_then(|s| s.signal()) == Some(libc::SIGINT);
+
+        Ok(ExecutionOutcome {
+            exit_code: status.and_then(|s| s.code()),
+            signal: status.and_then(|s| s.signal()),
+            core_dumped: status.map(|s| s.core_dumped()).unwrap_or(false),
+            timed_out,
+            cancelled,
+            refused: false,
+            started_at,
+            finished_at: chrono::Utc::now(),
+            hostname: system::hostname(),
+            user: system::current_user(),
+        })
+    }
+
+    /// Read `script` for running and check it, printing what the checks
+    /// found, each line after `prefix`. When it must not run, the refusal
+    /// is audited and its outcome returned as the error.
+    fn snapshot(&self, script: &Script, parameters: &ParameterValues, prefix: &str) -> std::result::Result<Snapshot, ExecutionOutcome> {
+        let snapshot = match Snapshot::take(&script.path, self.integrity.runs_private_copy()) {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                println!("{}{} {:#}", prefix, self.glyphs.unavailable, e);
+                return Err(self.refuse(script, parameters, None, &format!("{:#}", e)));
+            }
+        };
+        let verdict = self.integrity.check(script, &snapshot);
+        let reason = verdict.refusals.join("; ");
+        if self.report_integrity(verdict, prefix) {
+            Ok(snapshot)
+        } else {
+            Err(self.refuse(script, parameters, Some(snapshot.sha256.clone()), &reason))
+        }
+    }
+
+    /// Print what the integrity check found, each line after `prefix`, and
+    /// whether the script may run.
+    fn report_integrity(&self, verdict: Verdict, prefix: &str) -> bool {
+        for warning in &verdict.warnings {
+            println!("{}{} Warning: {}", prefix, self.glyphs.insecure, warning);
+        }
+        for refusal in &verdict.refusals {
+            println!("{}{} {}", prefix, self.glyphs.unavailable, refusal);
+        }
+        verdict.allows_run()
+    }
+
+    /// Record that `script` was refused before it started, with why, and
+    /// return the outcome for it.
+    pub fn refuse(&self, script: &Script, parameters: &ParameterValues, sha256: Option<String>, reason: &str) -> ExecutionOutcome {
+        let outcome = Self::not_executed();
+        let mut event = AuditEvent::new(self.access.identity(), script, parameters, &outcome, sha256, self.needs_sudo(script));
+        event.reason = Some(reason.to_string());
+        self.audit.record(&event);
+        outcome
+    }
+
+    /// Outcome for a run that was refused before the script was started.
+    fn not_executed() -> ExecutionOutcome {
+        let now = chrono::Utc::now();
+        ExecutionOutcome {
+            exit_code: None,
+            signal: None,
+            core_dumped: false,
+            timed_out: false,
+            cancelled: false,
+            refused: true,
+            started_at: now,
+            finished_at: now,
+            hostname: system::hostname(),
+            user: system::current_user(),
+        }
+    }
+
+    async fn terminate(&self, child: &mut Child, elevated: bool, reason: &str) -> Result<Option<ExitStatus>> {
+        let Some(pid) = child.id() else {
+            return Ok(child.try_wait()?); // Already reaped
+        };
+
+        println!("\n{} {} - sending SIGTERM", self.glyphs.timeout, reason);
+        system::signal_script(pid, libc::SIGTERM, elevated);
+
+        match tokio::time::timeout(TERMINATE_GRACE_PERIOD, child.wait()).await {
+            Ok(status) => Ok(Some(status?)),
+            Err(_) => {
+                println!("{} Script ignored SIGTERM for {}s - sending SIGKILL", self.glyphs.timeout, TERMINATE_GRACE_PERIOD.as_secs());
+                system::signal_script(pid, libc::SIGKILL, elevated);
+                child.kill().await?;
+                Ok(Some(child.wait().await?))
+            }
+        }
+    }
+
+    fn timeout_for(&self, script: &Script) -> Option<Duration> {
+        script
+            .timeout_secs
+            .map(Duration::from_secs)
+            .or(self.default_timeout)
+            .filter(|timeout| !timeout.is_zero())
+    }
+
+    pub fn needs_sudo(&self, script: &Script) -> bool {
+        script.requires_elevation() && !self.privileges.is_root
+    }
+
+    /// Build the command that runs a script from its `snapshot`, the
+    /// content that was checked, going through sudo when the script
+    /// requires elevation. Parameters are passed to scripts as
+    /// `TOOLBOX_PARAM_*` environment variables and the script's own path as
+    /// `TOOLBOX_SCRIPT`. sudo would strip them, and any command line is
+    /// readable by every user, so they are written to a private launcher
+    /// that exports them and then runs the script. The launcher is returned to
+    /// be kept until the script has exited.
+    fn build_command(&self, script: &Script, snapshot: &Snapshot, parameters: &ParameterValues) -> Result<(TokioCommand, NamedTempFile)> {
+        let mut variables = parameters.env();
+        variables.push((SCRIPT_ENV.to_string(), script.path.to_string_lossy().into_owned()));
+        let mut cmd = if self.needs_sudo(script) {
+            variables.splice(0..0, Self::parameter_env());
+            let mut cmd = TokioCommand::new("sudo");
+            cmd.args(["--", "bash"]);
+            cmd
+        } else {
+            TokioCommand::new("bash")
+        };
+        let launcher = launcher(variables, snapshot.path())?;
+        cmd.arg(launcher.path());
+
+        // Scripts run from the root they were found in, as in a single-root toolbox
+        let root = self.roots.root_of(&script.path).unwrap_or(self.roots.base());
+        cmd.current_dir(&root.path);
+
+        // Its own process group lets a timeout reach everything the script
+        // started; in the foreground, the group is handed the terminal
+        cmd.process_group(0);
+        if system::owns_terminal() {
+            // SAFETY: take_terminal only makes async-signal-safe calls.
+            unsafe {
+                cmd.pre_exec(|| {
+                    system::take_terminal();
+                    Ok(())
+                });
+            }
+        }
+
+        if !script.limits.is_empty() {
+            let limits = script.limits;
+            // SAFETY: the closure only calls setrlimit, which is async-signal-safe.
+            unsafe {
+                cmd.pre_exec(move || system::apply_resource_limits(&limits));
+            }
+        }
+
+        Ok((cmd, launcher))
+    }
+
+    fn parameter_env() -> Vec<(String, String)> {
+        std::env::vars()
+            .filter(|(key, _)| key.starts_with(PARAM_ENV_PREFIX))
+            .collect()
     }
 
     fn detect_script_features(&self, content: &str) -> ScriptFeatures {
@@ -184,11 +575,12 @@ impl ScriptExecutor {
         features
     }
 
+    #[allow(dead_code)]
     pub async fn show_file_with_search(&self, file_path: &str, search_term: Option<&str>) -> Result<()> {
-        println!("📄 Displaying file: {}", file_path);
+        println!("{} Displaying file: {}", self.glyphs.file, file_path);
         
         if let Some(term) = search_term {
-            println!("🔍 Searching for: {}", term);
+            println!("{} Searching for: {}", self.glyphs.search, term);
             
             // Use grep to highlight matches
             let output = Command::new("grep")
@@ -222,11 +614,12 @@ impl ScriptExecutor {
         Ok(())
     }
 
+    #[allow(dead_code)]
     pub async fn tail_with_search(&self, file_path: &str, search_term: Option<&str>) -> Result<()> {
-        println!("📄 Tailing file: {}", file_path);
+        println!("{} Tailing file: {}", self.glyphs.file, file_path);
         
         if let Some(term) = search_term {
-            println!("🔍 Filtering for: {}", term);
+            println!("{} Filtering for: {}", self.glyphs.search, term);
             
             // Use tail with grep
             let mut tail_cmd = Command::new("tail");
@@ -238,7 +631,6 @@ impl ScriptExecutor {
                 .arg(term)
                 .stdin(Stdio::piped());
 
-            let tail_child = tail_cmd.stdout(Stdio::piped()).spawn()?;
             let mut tail_child = tail_cmd.stdout(Stdio::piped()).spawn()?;
             let mut grep_child = grep_cmd.stdin(tail_child.stdout.take().unwrap()).spawn()?;
 
@@ -255,6 +647,32 @@ impl ScriptExecutor {
     }
 }
 
+/// A bash script only this user can read that exports `variables` and
+/// then runs `script` in its place.
+fn launcher(variables: impl IntoIterator<Item = (String, String)>, script: &Path) -> Result<NamedTempFile> {
+    let quote = |value: &str| format!("'{}'", value.replace('\'', r"'\''"));
+    let mut file = tempfile::Builder::new().prefix("toolbox-launch-").tempfile()?;
+    for (name, value) in variables {
+        // Names come from our own environment too; only shell identifiers are safe here
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            continue;
+        }
+        writeln!(file, "export {}={}", name, quote(&value))?;
+    }
+    writeln!(file, "exec bash {}", quote(&script.to_string_lossy()))?;
+    file.flush()?;
+    Ok(file)
+}
+
+/// Print each line of a script's output stream behind `label`.
+async fn print_prefixed(stream: Option<impl AsyncRead + Unpin>, label: &str) {
+    let Some(stream) = stream else { return };
+    let mut lines = BufReader::new(stream).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        println!("{} {}", label, line);
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ScriptFeatures {
     pub has_package_manager: bool,
@@ -266,12 +684,14 @@ pub struct ScriptFeatures {
     pub has_network_ops: bool,
 }
 
+#[allow(dead_code)]
 pub struct FileViewer {
     current_file: Option<PathBuf>,
     search_term: Option<String>,
     line_number: usize,
 }
 
+#[allow(dead_code)]
 impl FileViewer {
     pub fn new() -> Self {
         Self {
@@ -281,8 +701,8 @@ impl FileViewer {
         }
     }
 
-    pub async fn view_file(&mut self, path: &PathBuf) -> Result<()> {
-        self.current_file = Some(path.clone());
+    pub async fn view_file(&mut self, path: &Path) -> Result<()> {
+        self.current_file = Some(path.to_path_buf());
         self.display_file().await
     }
 
@@ -0,0 +1,362 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::database::Database;
+use crate::models::{Elevation, ParameterType, Script, ScriptParameter, ScriptUsage};
+use crate::policy::Policy;
+use crate::roots::{describe_source, Roots};
+use crate::template::Template;
+
+/// Templates, by the file name they are looked up under in a custom
+/// templates directory. Any that a directory does not have are taken from
+/// the built-in set.
+pub const TEMPLATES: [(&str, &str); 4] = [
+    ("index.md", include_str!("../templates/docs/index.md")),
+    ("script_index.md", include_str!("../templates/docs/script_index.md")),
+    ("category.md", include_str!("../templates/docs/category.md")),
+    ("statistics.md", include_str!("../templates/docs/statistics.md")),
+];
+
+const NO_DESCRIPTION: &str = "No description available";
+
+/// Render the documentation tree into `output_dir`: `README.md`,
+/// `SCRIPT_INDEX.md`, `STATISTICS.md` and a page per category, covering
+/// the scripts `access` lets this user see. Returns the files written.
+pub async fn generate(
+    database: &Database,
+    roots: &Roots,
+    access: &Policy,
+    output_dir: &Path,
+    templates_dir: Option<&Path>,
+) -> Result<Vec<PathBuf>> {
+    let templates = load_templates(templates_dir)?;
+    let mut scripts = database.get_all_scripts().await?;
+    scripts.retain(|script| access.can_see(script));
+    let usage: HashMap<PathBuf, ScriptUsage> = database
+        .get_script_usage()
+        .await?
+        .into_iter()
+        .filter(|usage| scripts.iter().any(|script| script.path == usage.script_path))
+        .map(|usage| (usage.script_path.clone(), usage))
+        .collect();
+
+    let data = build_data(&scripts, &usage, roots);
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create {}", output_dir.display()))?;
+
+    let mut written = Vec::new();
+    let mut write = |file: &str, content: String| -> Result<()> {
+        let path = output_dir.join(file);
+        fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+        written.push(path);
+        Ok(())
+    };
+
+    write("README.md", templates["index.md"].render(&data))?;
+    write("SCRIPT_INDEX.md", templates["script_index.md"].render(&data))?;
+    write("STATISTICS.md", templates["statistics.md"].render(&data))?;
+    for category in data["categories"].as_array().into_iter().flatten() {
+        let mut page = data.clone();
+        page["category"] = category.clone();
+        write(category["file"].as_str().unwrap_or_default(), templates["category.md"].render(&page))?;
+    }
+
+    Ok(written)
+}
+
+/// Copy the built-in templates into `dir` as a starting point for
+/// customising them. Existing files are left alone; returns those written.
+pub fn write_templates(dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let mut written = Vec::new();
+    for (name, source) in TEMPLATES {
+        let path = dir.join(name);
+        if path.exists() {
+            continue;
+        }
+        fs::write(&path, source).with_context(|| format!("Failed to write {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn load_templates(templates_dir: Option<&Path>) -> Result<HashMap<&'static str, Template>> {
+    let mut templates = HashMap::new();
+    for (name, builtin) in TEMPLATES {
+        let custom = templates_dir.map(|dir| dir.join(name)).filter(|path| path.exists());
+        let template = match custom {
+            Some(path) => {
+                let source = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read template {}", path.display()))?;
+                Template::parse(&path.display().to_string(), &source)?
+            }
+            None => Template::parse(name, builtin)?,
+        };
+        templates.insert(name, template);
+    }
+    Ok(templates)
+}
+
+fn build_data(scripts: &[Script], usage: &HashMap<PathBuf, ScriptUsage>, roots: &Roots) -> Value {
+    let mut by_category: BTreeMap<&str, Vec<&Script>> = BTreeMap::new();
+    for script in scripts {
+        by_category.entry(script.category.as_str()).or_default().push(script);
+    }
+
+    let categories: Vec<Value> = by_category
+        .iter()
+        .map(|(category, scripts)| {
+            let total_size: u64 = scripts.iter().map(|script| file_size(&script.path)).sum();
+            json!({
+                "name": cell(category),
+                "file": category_file(category),
+                "path": category.replace("::", "/"),
+                "count": scripts.len(),
+                "with_parameters": scripts.iter().filter(|script| script.has_parameters()).count(),
+                "average_size": format_size(total_size / scripts.len().max(1) as u64),
+                "scripts": scripts
+                    .iter()
+                    .map(|script| script_data(script, usage.get(&script.path), roots))
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+
+    let mut alphabetical: Vec<&Script> = scripts.iter().collect();
+    alphabetical.sort_by_key(|script| script.display_name().to_lowercase());
+
+    let mut most_run: Vec<(&Script, &ScriptUsage)> = scripts
+        .iter()
+        .filter_map(|script| usage.get(&script.path).map(|usage| (script, usage)))
+        .collect();
+    most_run.sort_by(|(a, a_usage), (b, b_usage)| {
+        b_usage.runs.cmp(&a_usage.runs).then_with(|| a.display_name().cmp(b.display_name()))
+    });
+
+    json!({
+        "generated_at": chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        "toolbox_path": roots.base().path.display().to_string(),
+        "roots": roots
+            .iter()
+            .map(|root| json!({ "label": root.label, "path": root.path.display().to_string() }))
+            .collect::<Vec<_>>(),
+        "layered": roots.is_layered(),
+        "totals": {
+            "scripts": scripts.len(),
+            "categories": by_category.len(),
+            "with_parameters": scripts.iter().filter(|script| script.has_parameters()).count(),
+            "with_info": scripts.iter().filter(|script| script.info_url.is_some()).count(),
+            "with_authors": scripts.iter().filter(|script| script.author.is_some()).count(),
+            "requiring_root": scripts.iter().filter(|script| script.requires_elevation()).count(),
+            "size": format_size(scripts.iter().map(|script| file_size(&script.path)).sum()),
+            "runs": usage.values().map(|usage| usage.runs).sum::<usize>(),
+            "never_run": scripts.len() - most_run.len(),
+        },
+        "categories": categories,
+        "scripts": alphabetical
+            .iter()
+            .map(|script| script_data(script, usage.get(&script.path), roots))
+            .collect::<Vec<_>>(),
+        "colors": color_distribution(scripts),
+        "authors": ranked(scripts.iter().filter_map(|script| script.author.as_deref())),
+        "tags": ranked(scripts.iter().flat_map(|script| script.tags.iter().map(String::as_str))),
+        "most_run": most_run
+            .iter()
+            .map(|(script, usage)| {
+                let mut entry = usage_data(usage);
+                entry["name"] = json!(cell(script.display_name()));
+                entry["category_file"] = json!(category_file(&script.category));
+                entry["anchor"] = json!(anchor(script, roots));
+                entry
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn script_data(script: &Script, usage: Option<&ScriptUsage>, roots: &Roots) -> Value {
+    let parameters = script.parse_parameters().unwrap_or_default();
+    let sudo = match script.elevation {
+        Elevation::Required => Some("Runs as root (`sudo`)"),
+        Elevation::Optional => Some("Can use root privileges when available"),
+        Elevation::Never => None,
+    };
+    let timeout = script.timeout_secs.map(|secs| format!("{}s", secs));
+    let limits = describe_limits(script);
+
+    json!({
+        "name": script.display_name(),
+        "key": script.name,
+        "filename": script.path.file_name().map(|name| name.to_string_lossy().to_string()),
+        "path": roots.relative(&script.path).display().to_string(),
+        "source": describe_source(script.source.as_deref(), script.overrides.as_deref()).filter(|_| roots.is_layered()),
+        "anchor": anchor(script, roots),
+        "category": script.category,
+        "category_file": category_file(&script.category),
+        "icon": script.display_icon(),
+        "description": script.description.as_deref().unwrap_or(NO_DESCRIPTION),
+        "detailed_description": script.detailed_description,
+        "info_url": script.info_url,
+        "author": script.author.as_deref().map(cell),
+        "tags": script.tags.iter().map(|tag| format!("`{}`", tag)).collect::<Vec<_>>().join(", "),
+        "color": script.color,
+        "color_label": script.color.as_deref().map(color_label),
+        "integration": script.integration,
+        "dependency_available": script.dependency_available,
+        "sudo": sudo,
+        "timeout": timeout,
+        "limits": limits,
+        "has_requirements": script.integration.is_some() || sudo.is_some() || timeout.is_some() || limits.is_some(),
+        "parameters": parameters.iter().map(parameter_data).collect::<Vec<_>>(),
+        "usage": usage.map(usage_data),
+    })
+}
+
+fn parameter_data(parameter: &ScriptParameter) -> Value {
+    let default = match (&parameter.default_value, parameter.param_type) {
+        (Some(_), ParameterType::Password) => "(hidden)".to_string(),
+        (Some(default), _) => format!("`{}`", default),
+        (None, _) => String::new(),
+    };
+
+    json!({
+        "name": parameter.name,
+        "label": cell(&parameter.label),
+        "type": serde_json::to_value(parameter.param_type).unwrap_or_default(),
+        "required": if parameter.required { "yes" } else { "no" },
+        "default": cell(&default),
+        "constraints": cell(&constraints(parameter)),
+        "description": cell(parameter.description.as_deref().unwrap_or_default()),
+    })
+}
+
+/// Options, ranges, lengths and patterns, in one line for the table.
+fn constraints(parameter: &ScriptParameter) -> String {
+    let mut constraints = Vec::new();
+    if let Some(options) = parameter.options.as_deref().filter(|options| !options.is_empty()) {
+        let values: Vec<String> = options.iter().map(|option| format!("`{}`", option.value)).collect();
+        constraints.push(format!("one of {}", values.join(", ")));
+    }
+    constraints.extend(parameter.constraints());
+    constraints.join("; ")
+}
+
+fn usage_data(usage: &ScriptUsage) -> Value {
+    json!({
+        "runs": usage.runs,
+        "succeeded": usage.succeeded,
+        "failed": usage.failed(),
+        "success_rate": format!("{:.0}%", usage.success_rate()),
+        "average_duration": format!("{:.1}s", usage.average_duration_ms as f64 / 1000.0),
+        "last_run": usage
+            .last_run
+            .map(|last_run| last_run.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()),
+    })
+}
+
+pub fn describe_limits(script: &Script) -> Option<String> {
+    let limits = script.limits;
+    let mut parts = Vec::new();
+    if let Some(cpu) = limits.cpu_secs {
+        parts.push(format!("{}s CPU", cpu));
+    }
+    if let Some(memory) = limits.memory_bytes {
+        parts.push(format!("{} memory", format_size(memory)));
+    }
+    if let Some(files) = limits.open_files {
+        parts.push(format!("{} open files", files));
+    }
+    Some(parts.join(", ")).filter(|parts| !parts.is_empty())
+}
+
+fn color_distribution(scripts: &[Script]) -> Vec<Value> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for script in scripts {
+        *counts.entry(script.color.as_deref().unwrap_or_default()).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .map(|(code, count)| {
+            let (label, purpose) = if code.is_empty() {
+                ("⚪ Default".to_string(), "Standard scripts")
+            } else {
+                let purpose = match code {
+                    "Z1" => "Dangerous operations",
+                    "Z2" => "Safe operations",
+                    "Z3" => "Caution required",
+                    "Z4" => "Information/utilities",
+                    _ => "Custom colour code",
+                };
+                (color_label(code), purpose)
+            };
+            json!({ "code": code, "label": label, "count": count, "purpose": purpose })
+        })
+        .collect()
+}
+
+fn color_label(code: &str) -> String {
+    match code {
+        "Z1" => "🔴 Red (Z1)".to_string(),
+        "Z2" => "🟢 Green (Z2)".to_string(),
+        "Z3" => "🟡 Yellow (Z3)".to_string(),
+        "Z4" => "🔵 Blue (Z4)".to_string(),
+        _ => code.to_string(),
+    }
+}
+
+/// Count occurrences, most frequent first.
+fn ranked<'a>(values: impl Iterator<Item = &'a str>) -> Vec<Value> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    counts
+        .into_iter()
+        .map(|(name, count)| json!({ "name": cell(name), "count": count }))
+        .collect()
+}
+
+/// `LinuxTools::SystemUtilities` is documented in `LinuxTools__SystemUtilities.md`,
+/// as `generate_documentation.sh` names it.
+pub fn category_file(category: &str) -> String {
+    format!("{}.md", category.replace("::", "__").replace(['/', ' '], "_"))
+}
+
+/// Stable link target for a script, from its path in the toolbox.
+fn anchor(script: &Script, roots: &Roots) -> String {
+    let path = roots.relative(&script.path);
+    path.to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect()
+}
+
+/// Keep a value from breaking out of its Markdown table cell.
+fn cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0)
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
@@ -0,0 +1,222 @@
+use ratatui::symbols::border;
+use unicode_width::UnicodeWidthChar;
+
+/// Icons and line-drawing characters used by the menu and the executor.
+///
+/// Serial consoles, iDRAC/iLO consoles and old PuTTY builds cannot show
+/// emoji, so an ASCII set replaces every icon with a short text tag.
+/// Emoji written with a variation selector (U+FE0F) are drawn two columns
+/// wide but measured as one, so those entries carry a trailing space.
+#[derive(Debug, Clone, Copy)]
+pub struct Glyphs {
+    pub ascii: bool,
+    pub app: &'static str,
+    pub search: &'static str,
+    pub script: &'static str,
+    pub category: &'static str,
+    pub workflow: &'static str,
+    pub back: &'static str,
+    pub home: &'static str,
+    pub exit: &'static str,
+    pub sudo: &'static str,
+    pub unavailable: &'static str,
+    pub insecure: &'static str,
+    pub run: &'static str,
+    pub description: &'static str,
+    pub info: &'static str,
+    pub path: &'static str,
+    pub params: &'static str,
+    pub success: &'static str,
+    pub failure: &'static str,
+    pub timeout: &'static str,
+    pub cancelled: &'static str,
+    pub skipped: &'static str,
+    pub mark: &'static str,
+    pub package: &'static str,
+    pub package_lists: &'static str,
+    pub package_deps: &'static str,
+    pub package_download: &'static str,
+    pub package_setup: &'static str,
+    pub package_triggers: &'static str,
+    pub file: &'static str,
+    pub selector: &'static str,
+    pub rule: &'static str,
+    pub gutter: &'static str,
+    pub swatch: &'static str,
+    pub arrow: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+    pub left: &'static str,
+    pub right: &'static str,
+    pub borders: border::Set,
+}
+
+const ASCII_BORDERS: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+impl Glyphs {
+    pub const UNICODE: Glyphs = Glyphs {
+        ascii: false,
+        app: "🛡️",
+        search: "🔍",
+        script: "📝",
+        category: "📁",
+        workflow: "🔁",
+        back: "⬅️ ",
+        home: "🏠",
+        exit: "❌",
+        sudo: "🔐",
+        unavailable: "🚫",
+        insecure: "⚠️ ",
+        run: "🚀",
+        description: "📝",
+        info: "ℹ️ ",
+        path: "📁",
+        params: "⚙️ ",
+        success: "✅",
+        failure: "❌",
+        timeout: "⏱️ ",
+        cancelled: "🛑",
+        skipped: "⏭️ ",
+        mark: "●",
+        package: "📦",
+        package_lists: "📋",
+        package_deps: "🔗",
+        package_download: "⬇️ ",
+        package_setup: "⚙️ ",
+        package_triggers: "🔧",
+        file: "📄",
+        selector: "► ",
+        rule: "────",
+        gutter: "│",
+        swatch: "■",
+        arrow: "→",
+        up: "↑",
+        down: "↓",
+        left: "←",
+        right: "→",
+        borders: border::PLAIN,
+    };
+
+    pub const ASCII: Glyphs = Glyphs {
+        ascii: true,
+        app: "[TB]",
+        search: "[?]",
+        script: "[S]",
+        category: "[D]",
+        workflow: "[W]",
+        back: "[<]",
+        home: "[H]",
+        exit: "[X]",
+        sudo: "[SUDO]",
+        unavailable: "[N/A]",
+        insecure: "[!]",
+        run: "[RUN]",
+        description: "[i]",
+        info: "[i]",
+        path: "[PATH]",
+        params: "[CFG]",
+        success: "[OK]",
+        failure: "[FAIL]",
+        timeout: "[TIME]",
+        cancelled: "[STOP]",
+        skipped: "[SKIP]",
+        mark: "*",
+        package: "[PKG]",
+        package_lists: "[PKG]",
+        package_deps: "[PKG]",
+        package_download: "[PKG]",
+        package_setup: "[PKG]",
+        package_triggers: "[PKG]",
+        file: "[FILE]",
+        selector: "> ",
+        rule: "----",
+        gutter: "|",
+        swatch: "#",
+        arrow: "->",
+        up: "Up",
+        down: "Down",
+        left: "Left",
+        right: "Right",
+        borders: ASCII_BORDERS,
+    };
+
+    /// Pick the glyph set. `force` comes from `--ascii` or the config file;
+    /// otherwise ASCII is used when the locale is not UTF-8 or the terminal
+    /// is one known to lack the glyphs (the Linux console, VT100-style
+    /// serial terminals).
+    pub fn detect(force: Option<bool>) -> Self {
+        let ascii = force.unwrap_or_else(|| !locale_is_utf8() || limited_terminal());
+        if ascii {
+            Self::ASCII
+        } else {
+            Self::UNICODE
+        }
+    }
+
+    /// A script or category icon from the metadata, replaced by `fallback`
+    /// in ASCII mode unless it is already plain ASCII.
+    pub fn icon<'a>(&self, icon: &'a str, fallback: &'a str) -> &'a str {
+        if self.ascii && !icon.is_ascii() {
+            fallback
+        } else {
+            icon
+        }
+    }
+}
+
+/// Terminal columns taken by `text`. Unlike a plain char count this counts
+/// wide (CJK, emoji) characters as two columns, including emoji that are
+/// only made wide by a following variation selector.
+pub fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let char_width = c.width().unwrap_or(0);
+        if chars.peek() == Some(&'\u{FE0F}') && char_width == 1 {
+            width += 2;
+        } else {
+            width += char_width;
+        }
+    }
+
+    width
+}
+
+/// Pad `text` with spaces to `width` terminal columns.
+pub fn pad(text: &str, width: usize) -> String {
+    let padding = width.saturating_sub(display_width(text));
+    format!("{}{}", text, " ".repeat(padding))
+}
+
+fn locale_is_utf8() -> bool {
+    // The first of these that is set decides, as in setlocale(3)
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    locale.contains("utf-8") || locale.contains("utf8")
+}
+
+fn limited_terminal() -> bool {
+    match std::env::var("TERM") {
+        Ok(term) => {
+            matches!(term.as_str(), "linux" | "dumb" | "ansi" | "cons25")
+                || term.starts_with("vt1")
+                || term.starts_with("vt2")
+        }
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,422 @@
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+
+use serde::Deserialize;
+
+use crate::models::Script;
+
+/// The integrity policy. Like the access policy, it is kept outside the
+/// users' own configuration so that they cannot relax it.
+pub const INTEGRITY_FILE: &str = "/etc/toolbox/integrity.toml";
+
+/// Namespace toolbox scripts are signed in with `ssh-keygen -Y sign -n`.
+pub const SSH_NAMESPACE: &str = "toolbox";
+
+/// What to do with a script whose content differs from the checksum the
+/// last scan recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnChange {
+    #[default]
+    Warn,
+    Refuse,
+}
+
+/// Tool that makes and checks the detached signature next to each script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureScheme {
+    /// `ssh-keygen -Y`, checked against `allowed_signers` files; `<script>.sig`.
+    Ssh,
+    /// `minisign`, checked against public key files; `<script>.minisig`.
+    Minisign,
+}
+
+impl SignatureScheme {
+    /// Where the signature of `script` is expected.
+    pub fn signature_path(&self, script: &Path) -> PathBuf {
+        let extension = match self {
+            SignatureScheme::Ssh => ".sig",
+            SignatureScheme::Minisign => ".minisig",
+        };
+        let mut path = OsString::from(script.as_os_str());
+        path.push(extension);
+        PathBuf::from(path)
+    }
+}
+
+/// The integrity policy file:
+///
+/// ```toml
+/// on_change = "refuse"    # warn (default) | refuse to run scripts changed since the last scan
+/// signatures = "ssh"      # require <script>.sig (ssh) or <script>.minisig (minisign)
+/// trusted_keys = ["/etc/toolbox/allowed_signers"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct IntegrityConfig {
+    /// `warn` (default) or `refuse` when a script no longer matches the
+    /// checksum recorded by the last scan.
+    on_change: Option<String>,
+    /// Require a detached signature by a trusted key: `ssh` or `minisign`.
+    signatures: Option<String>,
+    /// `allowed_signers` files for `ssh`, public key files for `minisign`.
+    trusted_keys: Vec<String>,
+}
+
+/// The checks a script must pass before it runs, from `INTEGRITY_FILE`.
+#[derive(Debug, Clone, Default)]
+pub struct IntegrityPolicy {
+    pub on_change: OnChange,
+    /// Required signature scheme; signatures are not checked when unset.
+    pub signatures: Option<SignatureScheme>,
+    pub trusted_keys: Vec<PathBuf>,
+}
+
+/// A script's content, read once before it runs. With `private`, the bytes
+/// are also copied into a directory only this user can enter and the
+/// script is run from there, so a change to the file after it was checked
+/// is not run.
+pub struct Snapshot {
+    pub content: Vec<u8>,
+    pub sha256: String,
+    path: PathBuf,
+    _dir: Option<TempDir>,
+}
+
+impl Snapshot {
+    pub fn take(path: &Path, private: bool) -> Result<Self> {
+        let content = fs::read(path).with_context(|| format!("Failed to read script: {}", path.display()))?;
+        let sha256 = sha256(&content);
+        if !private {
+            return Ok(Self { content, sha256, path: path.to_path_buf(), _dir: None });
+        }
+
+        let dir = tempfile::Builder::new().prefix("toolbox-script-").tempdir()?;
+        // Same file name, so the script's messages about itself still read right
+        let copy = dir.path().join(path.file_name().unwrap_or("script".as_ref()));
+        fs::write(&copy, &content).with_context(|| format!("Failed to copy script: {}", path.display()))?;
+        Ok(Self { content, sha256, path: copy, _dir: Some(dir) })
+    }
+
+    /// The file to run: the private copy if there is one, otherwise the script.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Outcome of checking a script: problems worth mentioning, and those that
+/// stop it from running.
+#[derive(Debug, Clone, Default)]
+pub struct Verdict {
+    pub warnings: Vec<String>,
+    pub refusals: Vec<String>,
+}
+
+impl Verdict {
+    pub fn allows_run(&self) -> bool {
+        self.refusals.is_empty()
+    }
+}
+
+impl IntegrityPolicy {
+    /// The policy in `INTEGRITY_FILE`; changed scripts only warn without it.
+    pub fn load() -> Result<Self> {
+        let path = Path::new(INTEGRITY_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read integrity policy: {}", path.display()))?;
+        let config = toml::from_str(&content).with_context(|| format!("Invalid integrity policy: {}", path.display()))?;
+        Self::from_config(&config)
+    }
+
+    fn from_config(config: &IntegrityConfig) -> Result<Self> {
+        let on_change = match config.on_change.as_deref() {
+            None | Some("warn") => OnChange::Warn,
+            Some("refuse") => OnChange::Refuse,
+            Some(other) => bail!("Unknown on_change '{}': expected warn or refuse", other),
+        };
+        let signatures = match config.signatures.as_deref() {
+            None => None,
+            Some("ssh") => Some(SignatureScheme::Ssh),
+            Some("minisign") => Some(SignatureScheme::Minisign),
+            Some(other) => bail!("Unknown signatures '{}': expected ssh or minisign", other),
+        };
+        let trusted_keys: Vec<PathBuf> = config.trusted_keys.iter().map(PathBuf::from).collect();
+        // `~` or a relative path would name a file of whoever runs toolbox, who could add their own key
+        if let Some(key) = trusted_keys.iter().find(|key| !key.is_absolute()) {
+            bail!("Trusted key {} must be an absolute path", key.display());
+        }
+        if signatures.is_some() && trusted_keys.is_empty() {
+            bail!("signatures is set but trusted_keys lists no keys");
+        }
+
+        Ok(Self { on_change, signatures, trusted_keys })
+    }
+
+    /// Whether a script must run from a private copy of the bytes that were
+    /// checked: when a change or a missing signature would refuse it.
+    pub fn runs_private_copy(&self) -> bool {
+        self.on_change == OnChange::Refuse || self.signatures.is_some()
+    }
+
+    /// Check `snapshot`, the content of `script` about to run, against the
+    /// checksum its scan recorded and, when required, its signature; and
+    /// the script's file permissions.
+    pub fn check(&self, script: &Script, snapshot: &Snapshot) -> Verdict {
+        let path = script.path.display();
+        let mut verdict = Verdict::default();
+
+        for issue in permission_issues(&script.path) {
+            verdict.warnings.push(format!("{} is {}", path, issue));
+        }
+
+        let changed = match &script.checksum {
+            Some(trusted) if *trusted == snapshot.sha256 => None,
+            Some(_) => Some(format!("{} has changed since it was last scanned", path)),
+            // Entries from the legacy index or an imported catalogue were never hashed
+            None => Some(format!("{} has no checksum from a scan", path)).filter(|_| self.on_change == OnChange::Refuse),
+        };
+        if let Some(changed) = changed {
+            match self.on_change {
+                OnChange::Warn => verdict.warnings.push(changed),
+                OnChange::Refuse => verdict.refusals.push(format!("{} - run toolbox --scan to trust it", changed)),
+            }
+        }
+
+        if let Some(scheme) = self.signatures {
+            if let Err(e) = self.verify_signature(scheme, &script.path, snapshot) {
+                verdict.refusals.push(format!("{:#}", e));
+            }
+        }
+
+        verdict
+    }
+
+    /// Verify the signature next to `path` against the content in `snapshot`.
+    fn verify_signature(&self, scheme: SignatureScheme, path: &Path, snapshot: &Snapshot) -> Result<()> {
+        let signature = scheme.signature_path(path);
+        if !signature.exists() {
+            bail!("{} is not signed: {} is missing", path.display(), signature.display());
+        }
+
+        for key in &self.trusted_keys {
+            let verified = match scheme {
+                SignatureScheme::Ssh => verify_ssh(key, snapshot.path(), &signature)?,
+                SignatureScheme::Minisign => verify_minisign(key, snapshot.path(), &signature)?,
+            };
+            if verified {
+                return Ok(());
+            }
+        }
+        bail!("{} is not signed by a trusted key", path.display())
+    }
+}
+
+/// Whether `signature` is a valid signature of `path` by a principal in the
+/// `allowed_signers` file.
+fn verify_ssh(allowed_signers: &Path, path: &Path, signature: &Path) -> Result<bool> {
+    let principals = Command::new("ssh-keygen")
+        .args(["-Y", "find-principals", "-s"])
+        .arg(signature)
+        .arg("-f")
+        .arg(allowed_signers)
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to run ssh-keygen; is OpenSSH installed?")?;
+    if !principals.status.success() {
+        return Ok(false);
+    }
+
+    for principal in String::from_utf8_lossy(&principals.stdout).lines() {
+        let status = Command::new("ssh-keygen")
+            .args(["-Y", "verify", "-n", SSH_NAMESPACE, "-I", principal, "-s"])
+            .arg(signature)
+            .arg("-f")
+            .arg(allowed_signers)
+            .stdin(File::open(path).with_context(|| format!("Failed to read script: {}", path.display()))?)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        if status.success() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Whether `signature` is a valid signature of `path` by the minisign
+/// public key in `public_key`.
+fn verify_minisign(public_key: &Path, path: &Path, signature: &Path) -> Result<bool> {
+    let status = Command::new("minisign")
+        .args(["-V", "-q", "-p"])
+        .arg(public_key)
+        .arg("-m")
+        .arg(path)
+        .arg("-x")
+        .arg(signature)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to run minisign; is it installed?")?;
+    Ok(status.success())
+}
+
+/// Why the file at `path` could be changed by someone other than root:
+/// it is world-writable, or owned by another user.
+pub fn permission_issues(path: &Path) -> Vec<String> {
+    let Ok(metadata) = fs::metadata(path) else {
+        return Vec::new();
+    };
+
+    let mut issues = Vec::new();
+    if metadata.mode() & 0o002 != 0 {
+        issues.push("world-writable".to_string());
+    }
+    if metadata.uid() != 0 {
+        issues.push("not owned by root".to_string());
+    }
+    issues
+}
+
+/// SHA-256 of `data`, as lowercase hex.
+pub fn sha256(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const CONTENT: &str = "#!/bin/bash\necho checked\n";
+
+    /// A script written to `dir`, indexed with `checksum`.
+    fn script(dir: &Path, checksum: Option<&str>) -> Script {
+        let path = dir.join("check.sh");
+        fs::write(&path, CONTENT).unwrap();
+        let mut script = Script::new("check".to_string(), path, "TopLevel".to_string());
+        script.checksum = checksum.map(str::to_string);
+        script
+    }
+
+    fn policy(on_change: OnChange, signatures: Option<SignatureScheme>) -> IntegrityPolicy {
+        IntegrityPolicy { on_change, signatures, trusted_keys: vec![PathBuf::from("/etc/toolbox/allowed_signers")] }
+    }
+
+    /// The verdict, leaving out the file permission warnings, which depend
+    /// on who runs the tests.
+    fn check(policy: &IntegrityPolicy, script: &Script) -> Verdict {
+        let mut verdict = policy.check(script, &Snapshot::take(&script.path, policy.runs_private_copy()).unwrap());
+        verdict.warnings.retain(|warning| !permission_issues(&script.path).iter().any(|issue| warning.ends_with(issue)));
+        verdict
+    }
+
+    #[test]
+    fn a_matching_checksum_passes() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = script(dir.path(), Some(&sha256(CONTENT.as_bytes())));
+        for on_change in [OnChange::Warn, OnChange::Refuse] {
+            let verdict = check(&policy(on_change, None), &script);
+            assert!(verdict.warnings.is_empty() && verdict.refusals.is_empty(), "{:?}", verdict);
+        }
+    }
+
+    #[test]
+    fn a_changed_script_warns_or_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = script(dir.path(), Some(&sha256(b"#!/bin/bash\necho trusted\n")));
+
+        let verdict = check(&policy(OnChange::Warn, None), &script);
+        assert!(verdict.allows_run());
+        assert_eq!(verdict.warnings, [format!("{} has changed since it was last scanned", script.path.display())]);
+
+        let verdict = check(&policy(OnChange::Refuse, None), &script);
+        assert!(!verdict.allows_run());
+        assert!(verdict.refusals[0].contains("has changed since it was last scanned"), "{:?}", verdict);
+    }
+
+    #[test]
+    fn a_missing_checksum_is_refused_only_under_refuse() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = script(dir.path(), None);
+
+        let verdict = check(&policy(OnChange::Warn, None), &script);
+        assert!(verdict.warnings.is_empty() && verdict.refusals.is_empty(), "{:?}", verdict);
+
+        let verdict = check(&policy(OnChange::Refuse, None), &script);
+        assert!(verdict.refusals[0].contains("has no checksum from a scan"), "{:?}", verdict);
+    }
+
+    #[test]
+    fn a_missing_signature_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = script(dir.path(), Some(&sha256(CONTENT.as_bytes())));
+        for (scheme, extension) in [(SignatureScheme::Ssh, "sig"), (SignatureScheme::Minisign, "minisig")] {
+            let verdict = check(&policy(OnChange::Warn, Some(scheme)), &script);
+            assert_eq!(
+                verdict.refusals,
+                [format!("{0} is not signed: {0}.{1} is missing", script.path.display(), extension)]
+            );
+        }
+    }
+
+    #[test]
+    fn only_a_strict_policy_runs_a_private_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = script(dir.path(), None);
+
+        let snapshot = Snapshot::take(&script.path, policy(OnChange::Warn, None).runs_private_copy()).unwrap();
+        assert_eq!(snapshot.path(), script.path);
+
+        for strict in [policy(OnChange::Refuse, None), policy(OnChange::Warn, Some(SignatureScheme::Ssh))] {
+            let snapshot = Snapshot::take(&script.path, strict.runs_private_copy()).unwrap();
+            assert_ne!(snapshot.path(), script.path);
+            assert_eq!(snapshot.path().file_name(), script.path.file_name());
+            assert_eq!(fs::read(snapshot.path()).unwrap(), CONTENT.as_bytes());
+            assert_eq!(snapshot.sha256, sha256(CONTENT.as_bytes()));
+        }
+    }
+
+    #[test]
+    fn trusted_keys_must_be_absolute() {
+        let config = |keys: &[&str]| IntegrityConfig {
+            signatures: Some("ssh".to_string()),
+            trusted_keys: keys.iter().map(|key| key.to_string()).collect(),
+            ..IntegrityConfig::default()
+        };
+        assert!(IntegrityPolicy::from_config(&config(&["/etc/toolbox/allowed_signers"])).is_ok());
+        assert!(IntegrityPolicy::from_config(&config(&["~/.ssh/allowed_signers"])).is_err());
+        assert!(IntegrityPolicy::from_config(&config(&[])).is_err());
+    }
+
+    // Test vectors from FIPS 180-4 and the NIST SHA examples
+    #[test]
+    fn sha256_matches_the_standard_test_vectors() {
+        let vectors: [(&[u8], &str); 4] = [
+            (b"", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            (b"abc", "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            (
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+            ),
+            (
+                b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu",
+                "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1",
+            ),
+        ];
+        for (data, digest) in vectors {
+            assert_eq!(sha256(data), digest, "{}", String::from_utf8_lossy(data));
+        }
+        assert_eq!(
+            sha256(&vec![b'a'; 1_000_000]),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+    }
+}
@@ -0,0 +1,462 @@
+use anyhow::{anyhow, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::config::KeysConfig;
+use crate::glyphs::{self, Glyphs};
+
+/// Everything a key can be bound to in the menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    Select,
+    Back,
+    Home,
+    Search,
+    Help,
+    History,
+    Quit,
+    Cancel,
+    Complete,
+    ToggleBodies,
+    Mark,
+    RunMarked,
+}
+
+impl Action {
+    pub const ALL: [Action; 18] = [
+        Action::Up,
+        Action::Down,
+        Action::PageUp,
+        Action::PageDown,
+        Action::First,
+        Action::Last,
+        Action::Select,
+        Action::Back,
+        Action::Home,
+        Action::Search,
+        Action::Help,
+        Action::History,
+        Action::Quit,
+        Action::Cancel,
+        Action::Complete,
+        Action::ToggleBodies,
+        Action::Mark,
+        Action::RunMarked,
+    ];
+
+    /// Name used in the `[keys]` section of the config file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::First => "first",
+            Action::Last => "last",
+            Action::Select => "select",
+            Action::Back => "back",
+            Action::Home => "home",
+            Action::Search => "search",
+            Action::Help => "help",
+            Action::History => "history",
+            Action::Quit => "quit",
+            Action::Cancel => "cancel",
+            Action::Complete => "complete",
+            Action::ToggleBodies => "toggle_bodies",
+            Action::Mark => "mark",
+            Action::RunMarked => "run_marked",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Up => "Move selection up",
+            Action::Down => "Move selection down",
+            Action::PageUp => "Move 10 items up",
+            Action::PageDown => "Move 10 items down",
+            Action::First => "Go to first item",
+            Action::Last => "Go to last item",
+            Action::Select => "Execute selected item",
+            Action::Back => "Go back to previous menu",
+            Action::Home => "Go to home menu",
+            Action::Search => "Enter search mode",
+            Action::Help => "Show this help",
+            Action::History => "Show execution history",
+            Action::Quit => "Quit application",
+            Action::Cancel => "Exit current mode/quit",
+            Action::Complete => "Complete the current word (again to cycle)",
+            Action::ToggleBodies => "Also search script bodies",
+            Action::Mark => "Mark/unmark a script for a batch run",
+            Action::RunMarked => "Run the marked scripts as a batch",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// A key with its modifiers, written in the config as e.g. `x`, `Ctrl+q`,
+/// `Alt+v`, `PageDown` or `F2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = value.trim();
+
+        // A lone "+" is a key, not a separator
+        while let Some((prefix, rest)) = key.split_once('+').filter(|(_, rest)| !rest.is_empty()) {
+            modifiers |= match prefix.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return None,
+            };
+            key = rest;
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "space" => KeyCode::Char(' '),
+            lower => {
+                if let Some(number) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    KeyCode::F(number)
+                } else {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => KeyCode::Char(c),
+                        _ => return None,
+                    }
+                }
+            }
+        };
+
+        Some(Self { code, modifiers })
+    }
+
+    /// Character keys already carry their case, so Shift is ignored for
+    /// them; with Ctrl or Alt held the case is ignored too.
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        match (self.code, key.code) {
+            (KeyCode::Char(bound), KeyCode::Char(pressed)) => {
+                let bound_mods = self.modifiers - KeyModifiers::SHIFT;
+                let pressed_mods = key.modifiers - KeyModifiers::SHIFT;
+                if bound_mods != pressed_mods {
+                    return false;
+                }
+                if bound_mods.is_empty() {
+                    bound == pressed
+                } else {
+                    bound.eq_ignore_ascii_case(&pressed)
+                }
+            }
+            (bound, pressed) => bound == pressed && self.modifiers == key.modifiers,
+        }
+    }
+
+    /// How the key is shown in hints, e.g. `Ctrl+d` or an arrow glyph.
+    pub fn label(&self, glyphs: &Glyphs) -> String {
+        let mut label = String::new();
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            label.push_str("Ctrl+");
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            label.push_str("Alt+");
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            label.push_str("Shift+");
+        }
+
+        match self.code {
+            KeyCode::Up => label.push_str(glyphs.up),
+            KeyCode::Down => label.push_str(glyphs.down),
+            KeyCode::Left => label.push_str(glyphs.left),
+            KeyCode::Right => label.push_str(glyphs.right),
+            KeyCode::PageUp => label.push_str("PgUp"),
+            KeyCode::PageDown => label.push_str("PgDn"),
+            KeyCode::Home => label.push_str("Home"),
+            KeyCode::End => label.push_str("End"),
+            KeyCode::Enter => label.push_str("Enter"),
+            KeyCode::Esc => label.push_str("Esc"),
+            KeyCode::Tab => label.push_str("Tab"),
+            KeyCode::Backspace => label.push_str("Backspace"),
+            KeyCode::Delete => label.push_str("Del"),
+            KeyCode::F(number) => label.push_str(&format!("F{}", number)),
+            KeyCode::Char(' ') => label.push_str("Space"),
+            KeyCode::Char(c) => label.push(c),
+            _ => label.push('?'),
+        }
+        label
+    }
+}
+
+/// The active key bindings: a preset with any overrides from the config.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    preset: String,
+    bindings: Vec<(Action, Vec<KeyBinding>)>,
+}
+
+impl Keymap {
+    pub const PRESETS: [&'static str; 3] = ["default", "vim", "emacs"];
+
+    pub fn from_config(config: &KeysConfig) -> Result<Self> {
+        let preset = config.preset.as_deref().unwrap_or("default");
+        let mut keymap = Self::preset(preset).ok_or_else(|| {
+            anyhow!("Unknown key preset '{}'; expected one of: {}", preset, Self::PRESETS.join(", "))
+        })?;
+
+        for (name, keys) in &config.bindings {
+            let action = Action::parse(name).ok_or_else(|| {
+                let names: Vec<&str> = Action::ALL.iter().map(Action::name).collect();
+                anyhow!("Unknown action '{}' in [keys]; expected one of: {}", name, names.join(", "))
+            })?;
+            let keys = keys
+                .iter()
+                .map(|key| {
+                    KeyBinding::parse(key)
+                        .ok_or_else(|| anyhow!("Invalid key '{}' for action '{}' in [keys]", key, name))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            keymap.bind(action, keys);
+        }
+
+        Ok(keymap)
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        let table: &[(Action, &[&str])] = match name {
+            "default" => &[
+                (Action::Up, &["Up", "k"]),
+                (Action::Down, &["Down", "j"]),
+                (Action::PageUp, &["PageUp"]),
+                (Action::PageDown, &["PageDown"]),
+                (Action::First, &["Home"]),
+                (Action::Last, &["End"]),
+                (Action::Select, &["Enter"]),
+                (Action::Back, &["x", "X"]),
+                (Action::Home, &["h", "H"]),
+                (Action::Search, &["s", "S", "/"]),
+                (Action::Help, &["F1", "?"]),
+                (Action::History, &["F2"]),
+                (Action::Quit, &["Ctrl+q"]),
+                (Action::Cancel, &["Esc"]),
+                (Action::Complete, &["Tab"]),
+                (Action::ToggleBodies, &["Ctrl+b"]),
+                (Action::Mark, &["Space", "Ctrl+Space"]),
+                (Action::RunMarked, &["F5", "Ctrl+r"]),
+            ],
+            "vim" => &[
+                (Action::Up, &["k", "Up"]),
+                (Action::Down, &["j", "Down"]),
+                (Action::PageUp, &["Ctrl+u", "PageUp"]),
+                (Action::PageDown, &["Ctrl+d", "PageDown"]),
+                (Action::First, &["g", "Home"]),
+                (Action::Last, &["G", "End"]),
+                (Action::Select, &["Enter", "l", "Right"]),
+                (Action::Back, &["h", "Left"]),
+                (Action::Home, &["H"]),
+                (Action::Search, &["/"]),
+                (Action::Help, &["?", "F1"]),
+                (Action::History, &["F2"]),
+                (Action::Quit, &["q", "Ctrl+q"]),
+                (Action::Cancel, &["Esc"]),
+                (Action::Complete, &["Tab"]),
+                (Action::ToggleBodies, &["Ctrl+b"]),
+                (Action::Mark, &["Space", "Ctrl+Space"]),
+                (Action::RunMarked, &["F5", "Ctrl+r"]),
+            ],
+            "emacs" => &[
+                (Action::Up, &["Ctrl+p", "Up"]),
+                (Action::Down, &["Ctrl+n", "Down"]),
+                (Action::PageUp, &["Alt+v", "PageUp"]),
+                (Action::PageDown, &["Ctrl+v", "PageDown"]),
+                (Action::First, &["Alt+<", "Home"]),
+                (Action::Last, &["Alt+>", "End"]),
+                (Action::Select, &["Enter", "Ctrl+m"]),
+                (Action::Back, &["Ctrl+b", "Left"]),
+                (Action::Home, &["Alt+h"]),
+                (Action::Search, &["Ctrl+s"]),
+                (Action::Help, &["F1"]),
+                (Action::History, &["F2"]),
+                (Action::Quit, &["Ctrl+x", "Ctrl+q"]),
+                (Action::Cancel, &["Esc", "Ctrl+g"]),
+                (Action::Complete, &["Tab"]),
+                (Action::ToggleBodies, &["Alt+b"]),
+                (Action::Mark, &["Ctrl+Space", "Space"]),
+                (Action::RunMarked, &["F5", "Alt+r"]),
+            ],
+            _ => return None,
+        };
+
+        let bindings = table
+            .iter()
+            .map(|(action, keys)| {
+                let keys = keys.iter().filter_map(|key| KeyBinding::parse(key)).collect();
+                (*action, keys)
+            })
+            .collect();
+
+        Some(Self {
+            preset: name.to_string(),
+            bindings,
+        })
+    }
+
+    /// Replace the keys for `action`, unbinding them from any other action.
+    fn bind(&mut self, action: Action, keys: Vec<KeyBinding>) {
+        for (_, bound) in self.bindings.iter_mut() {
+            bound.retain(|key| !keys.contains(key));
+        }
+        match self.bindings.iter_mut().find(|(bound_action, _)| *bound_action == action) {
+            Some((_, bound)) => *bound = keys,
+            None => self.bindings.push((action, keys)),
+        }
+    }
+
+    pub fn preset_name(&self) -> &str {
+        &self.preset
+    }
+
+    pub fn action_for(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|binding| binding.matches(key)))
+            .map(|(action, _)| *action)
+    }
+
+    /// All keys for an action, e.g. `↑/k`.
+    pub fn keys_for(&self, action: Action, glyphs: &Glyphs) -> String {
+        self.bindings
+            .iter()
+            .find(|(bound_action, _)| *bound_action == action)
+            .map(|(_, keys)| keys.iter().map(|key| key.label(glyphs)).collect::<Vec<_>>().join("/"))
+            .filter(|keys| !keys.is_empty())
+            .unwrap_or_else(|| "unbound".to_string())
+    }
+
+    /// The first key for an action, for compact hints in the footer.
+    pub fn primary_key(&self, action: Action, glyphs: &Glyphs) -> String {
+        self.bindings
+            .iter()
+            .find(|(bound_action, _)| *bound_action == action)
+            .and_then(|(_, keys)| keys.first())
+            .map(|key| key.label(glyphs))
+            .unwrap_or_else(|| "-".to_string())
+    }
+
+    /// Help dialog line for an action: its keys and what it does.
+    pub fn help_line(&self, action: Action, glyphs: &Glyphs) -> String {
+        let keys = self.keys_for(action, glyphs);
+        format!("  {} - {}", glyphs::pad(&keys, 14), action.description())
+    }
+}
+
+/// Whether a key should be typed into the search query rather than treated
+/// as a shortcut: plain characters, with or without Shift.
+pub fn is_text_input(key: &KeyEvent) -> bool {
+    matches!(key.code, KeyCode::Char(_))
+        && !key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding {
+        KeyBinding { code, modifiers }
+    }
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parses_plain_and_named_keys() {
+        assert_eq!(KeyBinding::parse("x"), Some(binding(KeyCode::Char('x'), KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("G"), Some(binding(KeyCode::Char('G'), KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse(" PageDown "), Some(binding(KeyCode::PageDown, KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("pgup"), Some(binding(KeyCode::PageUp, KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("Return"), Some(binding(KeyCode::Enter, KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("space"), Some(binding(KeyCode::Char(' '), KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("F2"), Some(binding(KeyCode::F(2), KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("f"), Some(binding(KeyCode::Char('f'), KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("F"), Some(binding(KeyCode::Char('F'), KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn parses_modifiers_in_any_case() {
+        assert_eq!(KeyBinding::parse("Ctrl+d"), Some(binding(KeyCode::Char('d'), KeyModifiers::CONTROL)));
+        assert_eq!(KeyBinding::parse("control+D"), Some(binding(KeyCode::Char('D'), KeyModifiers::CONTROL)));
+        assert_eq!(KeyBinding::parse("META+v"), Some(binding(KeyCode::Char('v'), KeyModifiers::ALT)));
+        assert_eq!(
+            KeyBinding::parse("Ctrl+Alt+Delete"),
+            Some(binding(KeyCode::Delete, KeyModifiers::CONTROL | KeyModifiers::ALT))
+        );
+        assert_eq!(KeyBinding::parse("Shift+Tab"), Some(binding(KeyCode::Tab, KeyModifiers::SHIFT)));
+    }
+
+    #[test]
+    fn plus_is_a_key_when_nothing_follows_it() {
+        assert_eq!(KeyBinding::parse("+"), Some(binding(KeyCode::Char('+'), KeyModifiers::NONE)));
+        assert_eq!(KeyBinding::parse("Ctrl++"), Some(binding(KeyCode::Char('+'), KeyModifiers::CONTROL)));
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_modifiers() {
+        assert_eq!(KeyBinding::parse(""), None);
+        assert_eq!(KeyBinding::parse("Ctrl+"), None);
+        assert_eq!(KeyBinding::parse("Hyper+x"), None);
+        assert_eq!(KeyBinding::parse("xy"), None);
+        assert_eq!(KeyBinding::parse("Fx"), None);
+    }
+
+    #[test]
+    fn character_keys_ignore_shift_and_case_under_ctrl() {
+        let upper_g = KeyBinding::parse("G").unwrap();
+        assert!(upper_g.matches(&press(KeyCode::Char('G'), KeyModifiers::SHIFT)));
+        assert!(!upper_g.matches(&press(KeyCode::Char('g'), KeyModifiers::NONE)));
+
+        let ctrl_d = KeyBinding::parse("Ctrl+d").unwrap();
+        assert!(ctrl_d.matches(&press(KeyCode::Char('D'), KeyModifiers::CONTROL | KeyModifiers::SHIFT)));
+        assert!(!ctrl_d.matches(&press(KeyCode::Char('d'), KeyModifiers::NONE)));
+        assert!(!ctrl_d.matches(&press(KeyCode::Char('d'), KeyModifiers::ALT)));
+    }
+
+    #[test]
+    fn other_keys_need_the_exact_modifiers() {
+        let shift_tab = KeyBinding::parse("Shift+Tab").unwrap();
+        assert!(shift_tab.matches(&press(KeyCode::Tab, KeyModifiers::SHIFT)));
+        assert!(!shift_tab.matches(&press(KeyCode::Tab, KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn labels_parse_back_to_the_same_binding() {
+        for value in ["x", "Ctrl+d", "Alt+v", "PageDown", "F12", "Space", "Ctrl+Alt+Del", "Enter"] {
+            let parsed = KeyBinding::parse(value).unwrap();
+            let label = parsed.label(&Glyphs::ASCII);
+            assert_eq!(KeyBinding::parse(&label), Some(parsed), "{} -> {}", value, label);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::database::Database;
+use crate::models::Script;
+
+/// Index written by `toolbox_scan.sh` and read by the shell launcher.
+pub const LEGACY_INDEX_FILE: &str = "toolbox_menu.ini";
+
+/// How often the menu checks whether the legacy index was rewritten.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+// Values `toolbox_scan.sh` writes for tags a script does not set
+const DEFAULT_ICON: &str = "📝";
+const DEFAULT_COLOR: &str = "Z2";
+const DEFAULT_ORDER: i32 = 999999;
+
+/// The scripts listed in a legacy index, and the lines that could not be read.
+pub struct LegacyIndex {
+    pub scripts: Vec<Script>,
+    pub warnings: Vec<String>,
+}
+
+/// Read a `toolbox_menu.ini`: `[Category]` sections holding
+/// `name=path|icon|color|order|default|separator` entries, where every
+/// field after the path may be missing. Malformed lines are skipped.
+pub fn read_index(path: &Path) -> Result<LegacyIndex> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read legacy index: {}", path.display()))?;
+
+    let mut scripts = Vec::new();
+    let mut warnings = Vec::new();
+    let mut category = "TopLevel".to_string();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(section) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            category = section.trim().to_string();
+            continue;
+        }
+
+        let Some((name, value)) = line.split_once('=') else {
+            warnings.push(format!("{}:{}: expected name=path, got '{}'", path.display(), index + 1, line));
+            continue;
+        };
+        let mut fields = value.split('|').map(str::trim);
+        let script_path = fields.next().unwrap_or_default();
+        if name.trim().is_empty() || script_path.is_empty() {
+            warnings.push(format!("{}:{}: entry without a name or path", path.display(), index + 1));
+            continue;
+        }
+
+        let mut script = Script::new(name.trim().to_string(), PathBuf::from(script_path), category.clone());
+        let mut field = || fields.next().filter(|value| !value.is_empty()).map(str::to_string);
+        script.icon = field();
+        script.color = field();
+        script.order = field().and_then(|order| order.parse().ok()).filter(|&order| order != DEFAULT_ORDER);
+        script.is_default = field().is_some_and(|default| default == "true");
+        script.separator = field();
+
+        scripts.push(script);
+    }
+
+    Ok(LegacyIndex { scripts, warnings })
+}
+
+/// Check that a script can be written to the index and read back as it is.
+/// The format has no quoting, so a `=` in a name, a `|` in a field, a `]`
+/// in a category or a line break anywhere would shift or split the entry.
+fn check_entry(script: &Script) -> Result<()> {
+    let breaks_line = |value: &str| value.contains(['\n', '\r']);
+    let fields = [
+        ("path", script.path.to_string_lossy().into_owned()),
+        ("icon", script.icon.clone().unwrap_or_default()),
+        ("color", script.color.clone().unwrap_or_default()),
+        ("separator", script.separator.clone().unwrap_or_default()),
+    ];
+
+    if breaks_line(&script.name) || script.name.contains('=') || script.name.starts_with([';', '#', '[']) {
+        bail!("Cannot write '{}' to the legacy index: its name cannot be stored there", script.name.escape_debug());
+    }
+    if breaks_line(&script.category) || script.category.contains(']') {
+        bail!("Cannot write '{}' to the legacy index: its category cannot be stored there", script.name);
+    }
+    if let Some((field, _)) = fields.iter().find(|(_, value)| breaks_line(value) || value.contains('|')) {
+        bail!("Cannot write '{}' to the legacy index: its {} contains '|' or a line break", script.name, field);
+    }
+    Ok(())
+}
+
+/// Write the scripts in the format `toolbox_scan.sh` produces, TopLevel
+/// first, so the shell launcher sees the same menu. The file is replaced
+/// in one step so a launcher never reads it half-written. Nothing is
+/// written if any script cannot be stored (see [`check_entry`]).
+pub fn write_index(scripts: &[Script], path: &Path) -> Result<()> {
+    for script in scripts {
+        check_entry(script)?;
+    }
+
+    let mut categories: Vec<&str> = scripts.iter().map(|script| script.category.as_str()).collect();
+    categories.sort_by_key(|category| (*category != "TopLevel", *category));
+    categories.dedup();
+
+    let mut content = String::from("; Auto-generated toolbox menu index with metadata\n");
+    for category in categories {
+        content.push_str(&format!("[{}]\n", category));
+
+        let mut entries: Vec<&Script> = scripts.iter().filter(|script| script.category == category).collect();
+        entries.sort_by_key(|script| (script.order.unwrap_or(DEFAULT_ORDER), script.name.as_str()));
+        for script in entries {
+            content.push_str(&format!(
+                "{}={}|{}|{}|{}|{}|{}\n",
+                script.name,
+                script.path.display(),
+                script.icon.as_deref().unwrap_or(DEFAULT_ICON),
+                script.color.as_deref().unwrap_or(DEFAULT_COLOR),
+                script.order.unwrap_or(DEFAULT_ORDER),
+                script.is_default,
+                script.separator.as_deref().unwrap_or_default(),
+            ));
+        }
+        content.push('\n');
+    }
+
+    let temp_path = path.with_extension("ini.tmp");
+    fs::write(&temp_path, content).with_context(|| format!("Failed to write {}", temp_path.display()))?;
+    fs::rename(&temp_path, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// Re-import the legacy index whenever it changes while the menu is open.
+/// The import goes through its own connection so the menu notices it the
+/// same way it notices a rescan from another process.
+pub fn watch(db_path: PathBuf, index_path: PathBuf) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let Ok(database) = Database::new(&db_path) else {
+            return;
+        };
+        let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+        let mut last_modified: Option<SystemTime> = modified(&index_path);
+
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+
+            let current = modified(&index_path);
+            if current.is_none() || current == last_modified {
+                continue;
+            }
+            last_modified = current;
+
+            // A failed read (e.g. the file is mid-rewrite) keeps the current
+            // menu; the next change tries again
+            if let Ok(index) = read_index(&index_path) {
+                let _ = database.update_scripts(index.scripts).await;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(name: &str, path: &str, category: &str) -> Script {
+        Script::new(name.to_string(), PathBuf::from(path), category.to_string())
+    }
+
+    fn round_trip(scripts: &[Script]) -> LegacyIndex {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LEGACY_INDEX_FILE);
+        write_index(scripts, &path).unwrap();
+        read_index(&path).unwrap()
+    }
+
+    #[test]
+    fn read_accepts_missing_fields_and_skips_malformed_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LEGACY_INDEX_FILE);
+        fs::write(
+            &path,
+            "; comment\nbare=/opt/bare.sh\n[Net]\nping=/opt/ping.sh|🌐||5\nno equals sign\n=/opt/nameless.sh\nempty=\n",
+        )
+        .unwrap();
+
+        let index = read_index(&path).unwrap();
+        assert_eq!(index.scripts.len(), 2);
+        assert_eq!(index.scripts[0].category, "TopLevel");
+        assert_eq!(index.scripts[0].icon, None);
+        let ping = &index.scripts[1];
+        assert_eq!((ping.name.as_str(), ping.category.as_str()), ("ping", "Net"));
+        assert_eq!(ping.icon.as_deref(), Some("🌐"));
+        assert_eq!(ping.color, None);
+        assert_eq!(ping.order, Some(5));
+        assert_eq!(index.warnings.len(), 3);
+        assert!(index.warnings[0].ends_with(":5: expected name=path, got 'no equals sign'"));
+    }
+
+    #[test]
+    fn written_index_reads_back_the_same_scripts() {
+        let mut backup = script("Backup", "/opt/tools/backup.sh", "Storage");
+        backup.icon = Some("💾".to_string());
+        backup.color = Some("Z3".to_string());
+        backup.order = Some(10);
+        backup.is_default = true;
+        backup.separator = Some("Backup & Recovery".to_string());
+        let scripts = vec![
+            backup,
+            script("Zeta", "/opt/tools/zeta.sh", "Storage"),
+            script("Hello world", "/opt/tools/hello world.sh", "TopLevel"),
+        ];
+
+        let index = round_trip(&scripts);
+        assert!(index.warnings.is_empty());
+        let names: Vec<&str> = index.scripts.iter().map(|script| script.name.as_str()).collect();
+        assert_eq!(names, ["Hello world", "Backup", "Zeta"]);
+        for read in &index.scripts {
+            let written = scripts.iter().find(|script| script.name == read.name).unwrap();
+            assert_eq!(read.path, written.path);
+            assert_eq!(read.category, written.category);
+            assert_eq!(read.icon.as_deref().unwrap_or(DEFAULT_ICON), written.icon.as_deref().unwrap_or(DEFAULT_ICON));
+            assert_eq!(read.color.as_deref().unwrap_or(DEFAULT_COLOR), written.color.as_deref().unwrap_or(DEFAULT_COLOR));
+            assert_eq!(read.order, written.order);
+            assert_eq!(read.is_default, written.is_default);
+            assert_eq!(read.separator, written.separator);
+        }
+    }
+
+    #[test]
+    fn entries_that_would_not_read_back_are_refused() {
+        let mut with_pipe = script("Pipe", "/opt/tools/a.sh", "Tools");
+        with_pipe.separator = Some("a|b".to_string());
+        let refused = [
+            script("a=b", "/opt/tools/a.sh", "Tools"),
+            script("two\nlines", "/opt/tools/a.sh", "Tools"),
+            script("; comment", "/opt/tools/a.sh", "Tools"),
+            script("Pipe", "/opt/tools/a|b.sh", "Tools"),
+            script("Bracket", "/opt/tools/a.sh", "Tools]x"),
+            script("Newline", "/opt/tools/a.sh", "Tools\n[Other"),
+            with_pipe,
+        ];
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LEGACY_INDEX_FILE);
+        for entry in refused {
+            let ok = script("Fine", "/opt/tools/fine.sh", "Tools");
+            assert!(write_index(&[ok, entry], &path).is_err());
+            assert!(!path.exists());
+        }
+    }
+}
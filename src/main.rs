@@ -1,28 +1,374 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{Arg, Command};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 mod database;
+mod migrations;
 mod menu;
 mod scanner;
+mod scan_config;
 mod ui;
 mod models;
 mod search;
+mod query;
 mod display;
+mod system;
+mod config;
+mod keymap;
+mod theme;
+mod glyphs;
+mod params;
+mod batch;
+mod workflow;
+mod legacy;
+mod catalogue;
+mod template;
+mod docs;
+mod manpage;
+mod completion;
+mod roots;
+mod sources;
+mod integrity;
+mod policy;
+mod audit;
 
+use audit::Auditor;
+use config::Config;
 use database::Database;
+use display::{ScriptExecutor, NO_SUDO};
+use keymap::Keymap;
 use menu::MenuSystem;
+use models::Script;
+use roots::{GitSource, Root, Roots};
 use scanner::ScriptScanner;
+use system::PrivilegeContext;
+use theme::Theme;
+use glyphs::Glyphs;
+use integrity::IntegrityPolicy;
+use policy::Policy;
 
 const TOOLBOX_DIR: &str = "/opt/toolbox";
 const DEFAULT_DB_PATH: &str = "~/.config/toolbox/menu.db";
+const DEFAULT_CONFIG_PATH: &str = "~/.config/toolbox/config.toml";
+const DEFAULT_THEME_PATH: &str = "~/.config/toolbox/theme.toml";
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let matches = Command::new("toolbox")
+    let mut command = cli();
+    let matches = command.get_matches_mut();
+
+    let paths = matches.get_many::<String>("path").into_iter().flatten();
+    let db_path = expand_tilde(matches.get_one::<String>("database").unwrap());
+    let config_path = expand_tilde(matches.get_one::<String>("config").unwrap());
+
+    // Completion runs on every <Tab>, so it must not touch the configuration
+    // or print anything but candidates
+    if let Some((completion::COMPLETE_COMMAND, sub_matches)) = matches.subcommand() {
+        let words: Vec<String> = sub_matches.get_many::<String>("words").into_iter().flatten().cloned().collect();
+        if let Ok(roots) = toolbox_roots(paths, &Config::load(&config_path).unwrap_or_default()) {
+            let _ = completion::complete(&command, &words, roots, &db_path).await;
+        }
+        return Ok(());
+    }
+    if let Some(("completions", sub_matches)) = matches.subcommand() {
+        print!("{}", completion::script(sub_matches.get_one::<String>("shell").unwrap()));
+        return Ok(());
+    }
+
+    let config = Config::load(&config_path)?;
+    let roots = toolbox_roots(paths, &config)?;
+    let keymap = Keymap::from_config(&config.keys)?;
+    let theme_name = matches.get_one::<String>("theme").or(config.theme.name.as_ref());
+    let theme_file = expand_tilde(config.theme.file.as_deref().unwrap_or(DEFAULT_THEME_PATH));
+    if config.theme.file.is_some() && !theme_file.exists() {
+        anyhow::bail!("Theme file not found: {}", theme_file.display());
+    }
+    let theme = Theme::load(theme_name.map(String::as_str), Some(&theme_file))?;
+    let glyphs = Glyphs::detect(if matches.get_flag("ascii") { Some(true) } else { config.display.ascii });
+    let debug = matches.get_flag("debug");
+    let default_timeout = Some(Duration::from_secs(*matches.get_one::<u64>("timeout").unwrap()))
+        .filter(|timeout| !timeout.is_zero());
+
+    // Initialize database
+    let database = Database::new(&db_path)?;
+    if let Some(report) = database.initialize().await? {
+        if let Some(backup_path) = report.backup_path {
+            println!(
+                "{} Migrating database schema v{} {} v{} (backup: {})",
+                glyphs.package,
+                report.from_version,
+                glyphs.arrow,
+                report.to_version,
+                backup_path.display()
+            );
+        }
+    }
+
+    // Everything below shows scripts only as far as the policy lets this user see them
+    let access = Policy::load()?;
+
+    if let Some(("sources", sub_matches)) = matches.subcommand() {
+        return match sub_matches.subcommand().unwrap() {
+            ("update", update_matches) => {
+                let labels: Vec<&String> = update_matches.get_many::<String>("label").into_iter().flatten().collect();
+                update_sources(&database, &roots, &labels, &config, &glyphs).await
+            }
+            _ => {
+                print_sources(&roots, &glyphs);
+                Ok(())
+            }
+        };
+    }
+
+    if let Some(("history", sub_matches)) = matches.subcommand() {
+        let limit = *sub_matches.get_one::<usize>("limit").unwrap();
+        return print_history(&database, limit, &glyphs).await;
+    }
+
+    if let Some(("export", sub_matches)) = matches.subcommand() {
+        let format = catalogue::Format::parse(sub_matches.get_one::<String>("format").unwrap()).unwrap();
+        let scripts = visible_scripts(&database, &access).await?;
+        let json = catalogue::export(&scripts, &roots, format)?;
+        return match sub_matches.get_one::<String>("output") {
+            Some(output) => {
+                let output = expand_tilde(output);
+                std::fs::write(&output, json).with_context(|| format!("Failed to write {}", output.display()))?;
+                println!("{} Wrote {} scripts to {}", glyphs.success, scripts.len(), output.display());
+                Ok(())
+            }
+            None => {
+                print!("{}", json);
+                Ok(())
+            }
+        };
+    }
+
+    if let Some(("import", sub_matches)) = matches.subcommand() {
+        let file = expand_tilde(sub_matches.get_one::<String>("file").unwrap());
+        let imported = catalogue::import(&file, &roots)?;
+        let count = imported.scripts.len();
+        database.update_scripts(imported.scripts).await?;
+        println!(
+            "{} Imported {} scripts from {} (catalogue v{})",
+            glyphs.success,
+            count,
+            file.display(),
+            imported.version
+        );
+        if imported.missing_parameters > 0 {
+            println!(
+                "{} {} scripts have parameters the catalogue does not define - run toolbox --scan to index them",
+                glyphs.params, imported.missing_parameters
+            );
+        }
+        return Ok(());
+    }
+
+    if let Some(("help", sub_matches)) = matches.subcommand() {
+        let Some(query) = sub_matches.get_one::<String>("script") else {
+            command.print_long_help()?;
+            return Ok(());
+        };
+        let scripts = visible_scripts(&database, &access).await?;
+        let Some(script) = manpage::find_script(&scripts, &roots, query)? else {
+            anyhow::bail!("No script named '{}' - scripts are indexed by toolbox --scan", query);
+        };
+        let (_, width) = console::Term::stdout().size();
+        print!("{}", manpage::render_text(script, &roots, width as usize));
+        return Ok(());
+    }
+
+    if let Some(("docs", sub_matches)) = matches.subcommand() {
+        return match sub_matches.subcommand().unwrap() {
+            ("generate", generate_matches) => {
+                let dir = expand_tilde(generate_matches.get_one::<String>("dir").unwrap());
+                let templates = generate_matches
+                    .get_one::<String>("templates")
+                    .or(config.docs.templates.as_ref())
+                    .map(|templates| expand_tilde(templates));
+                if let Some(templates) = templates.as_ref().filter(|templates| !templates.is_dir()) {
+                    anyhow::bail!("Templates directory not found: {}", templates.display());
+                }
+                let written = docs::generate(&database, &roots, &access, &dir, templates.as_deref()).await?;
+                println!("{} Generated {} files in {}", glyphs.success, written.len(), dir.display());
+                Ok(())
+            }
+            ("man", man_matches) => {
+                let dir = expand_tilde(man_matches.get_one::<String>("dir").unwrap());
+                let scripts = visible_scripts(&database, &access).await?;
+                let written = manpage::write_pages(&scripts, &roots, &dir)?;
+                println!("{} Wrote {} man pages to {}", glyphs.success, written.len(), dir.display());
+                Ok(())
+            }
+            (_, templates_matches) => {
+                let dir = expand_tilde(templates_matches.get_one::<String>("dir").unwrap());
+                let written = docs::write_templates(&dir)?;
+                for path in &written {
+                    println!("{} {}", glyphs.file, path.display());
+                }
+                println!(
+                    "{} Wrote {} of {} templates to {} (existing files were kept)",
+                    glyphs.success,
+                    written.len(),
+                    docs::TEMPLATES.len(),
+                    dir.display()
+                );
+                Ok(())
+            }
+        };
+    }
+
+    if let Some(("ini", sub_matches)) = matches.subcommand() {
+        let (action, action_matches) = sub_matches.subcommand().unwrap();
+        let file = action_matches
+            .get_one::<String>("file")
+            .map(|file| expand_tilde(file))
+            .unwrap_or_else(|| roots.base().path.join(legacy::LEGACY_INDEX_FILE));
+        return match action {
+            "import" => {
+                let count = import_legacy_index(&database, &file).await?;
+                println!("{} Imported {} scripts from {}", glyphs.success, count, file.display());
+                Ok(())
+            }
+            _ => {
+                let scripts = visible_scripts(&database, &access).await?;
+                legacy::write_index(&scripts, &file)?;
+                println!("{} Wrote {} scripts to {}", glyphs.success, scripts.len(), file.display());
+                Ok(())
+            }
+        };
+    }
+
+    let legacy_index = config.legacy.index.as_deref().map(expand_tilde);
+    if let Some(index) = &legacy_index {
+        // The legacy index replaces the directory scan as the menu source
+        let count = import_legacy_index(&database, index).await?;
+        if matches.get_flag("scan") {
+            println!("{} Imported {} scripts from {}", glyphs.success, count, index.display());
+        }
+    } else if matches.get_flag("scan") || database.is_empty().await? {
+        // Scan if requested or if database is empty
+        if roots.is_layered() {
+            let labels: Vec<&str> = roots.iter().map(|root| root.label.as_str()).collect();
+            println!("{} Scanning toolbox roots {}...", glyphs.search, labels.join(", "));
+        } else {
+            println!("{} Scanning toolbox directory...", glyphs.search);
+        }
+        let scanner = ScriptScanner::new(roots.clone());
+        let scripts = scanner.scan().await?;
+        let workflows = scanner.scan_workflows().await?;
+        
+        println!(
+            "{} Updating database with {} scripts and {} workflows...",
+            glyphs.description,
+            scripts.len(),
+            workflows.len()
+        );
+        database.update_scripts(scripts).await?;
+        database.update_workflows(workflows).await?;
+        println!("{} Database updated successfully!", glyphs.success);
+
+        if config.legacy.export {
+            let index = roots.base().path.join(legacy::LEGACY_INDEX_FILE);
+            legacy::write_index(&database.get_all_scripts().await?, &index)?;
+            println!("{} Legacy index written to {}", glyphs.success, index.display());
+        }
+    }
+
+    if let Some(("list", sub_matches)) = matches.subcommand() {
+        let filter = ListFilter {
+            category: sub_matches.get_one::<String>("category").map(String::as_str),
+            tag: sub_matches.get_one::<String>("tag").map(String::as_str),
+            source: sub_matches.get_one::<String>("source").map(String::as_str),
+        };
+        return print_list(&database, filter, &roots, &access, &glyphs).await;
+    }
+
+    let privileges = PrivilegeContext::detect();
+    let integrity = IntegrityPolicy::load()?;
+    let audit = Auditor::load()?;
+
+    if let Some(("run", sub_matches)) = matches.subcommand() {
+        let target = sub_matches.get_one::<String>("target").unwrap();
+        let mut presets = Vec::new();
+        for param in sub_matches.get_many::<String>("param").into_iter().flatten() {
+            let Some((name, value)) = param.split_once('=') else {
+                anyhow::bail!("Invalid --param '{}': expected NAME=VALUE", param);
+            };
+            presets.push((name.trim().to_string(), value.to_string()));
+        }
+
+        let executor = ScriptExecutor::new(
+            roots.clone(),
+            privileges,
+            default_timeout,
+            integrity.clone(),
+            access.clone(),
+            audit.clone(),
+            glyphs,
+        );
+        if let Some(path) = workflow::locate(&database, &roots, target).await? {
+            return match workflow::run(&executor, &database, &roots, &path, &presets, &glyphs).await? {
+                Some(true) => Ok(()),
+                Some(false) => anyhow::bail!("Workflow failed"),
+                None => anyhow::bail!("Workflow cancelled"),
+            };
+        }
+
+        let scripts = database.get_all_scripts().await?;
+        let Some(script) = manpage::find_script(&scripts, &roots, target)? else {
+            anyhow::bail!("No workflow or script named '{}' - both are indexed by toolbox --scan", target);
+        };
+        if let Some(denied) = executor.access_denied(script) {
+            executor.refuse(script, &params::ParameterValues::default(), None, &denied);
+            anyhow::bail!(denied);
+        }
+        if script.requires_elevation() && !privileges.can_elevate() {
+            executor.refuse(script, &params::ParameterValues::default(), None, NO_SUDO);
+            anyhow::bail!("{} requires root privileges and you cannot use sudo", script.display_name());
+        }
+        let Some(parameters) = params::collect_with_presets(script, &presets, &glyphs)? else {
+            anyhow::bail!("Cancelled - {} was not run", script.display_name());
+        };
+        let outcome = executor.execute(script, &parameters).await?;
+        database
+            .record_execution(script, &outcome, parameters.to_history_json().as_deref(), None)
+            .await?;
+        if !outcome.success() {
+            anyhow::bail!("{} failed: {}", script.display_name(), outcome.describe());
+        }
+        return Ok(());
+    }
+
+    // Start the menu system, following changes to the legacy index
+    let _watcher = legacy_index.map(|index| legacy::watch(db_path.clone(), index));
+    let mut menu_system = MenuSystem::new(
+        database,
+        roots,
+        privileges,
+        default_timeout,
+        integrity,
+        access,
+        audit,
+        keymap,
+        theme,
+        glyphs,
+        debug,
+    );
+    menu_system.run().await?;
+
+    Ok(())
+}
+
+/// The command-line interface, also walked by shell completion.
+fn cli() -> Command {
+    Command::new("toolbox")
         .version("1.0.0")
         .author("Toolbox Team")
         .about("Advanced CLI menu system for toolbox scripts")
+        // `toolbox help SCRIPT` shows a script's manual page instead
+        .disable_help_subcommand(true)
         .arg(
             Arg::new("scan")
                 .long("scan")
@@ -34,9 +380,10 @@ async fn main() -> Result<()> {
             Arg::new("path")
                 .long("path")
                 .short('p')
-                .value_name("PATH")
-                .help("Toolbox directory path")
-                .default_value(TOOLBOX_DIR),
+                .value_name("[LABEL=]PATH")
+                .help("Toolbox directory; repeat to layer roots, lowest precedence first [default: /opt/toolbox]")
+                .global(true)
+                .action(clap::ArgAction::Append),
         )
         .arg(
             Arg::new("database")
@@ -44,46 +391,504 @@ async fn main() -> Result<()> {
                 .short('d')
                 .value_name("DB_PATH")
                 .help("Database file path")
+                .global(true)
                 .default_value(DEFAULT_DB_PATH),
         )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .short('c')
+                .value_name("CONFIG_PATH")
+                .help("Configuration file path")
+                .global(true)
+                .default_value(DEFAULT_CONFIG_PATH),
+        )
+        .arg(
+            Arg::new("theme")
+                .long("theme")
+                .value_name("THEME")
+                .help("Colour theme: dark, light, high-contrast or no-colour"),
+        )
+        .arg(
+            Arg::new("ascii")
+                .long("ascii")
+                .help("Use plain ASCII instead of emoji and line-drawing characters")
+                .global(true)
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("timeout")
+                .long("timeout")
+                .value_name("SECONDS")
+                .help("Default script timeout in seconds when a script has no #MTIMEOUT (0 = none)")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("0"),
+        )
         .arg(
             Arg::new("debug")
                 .long("debug")
                 .help("Enable debug mode")
+                .global(true)
                 .action(clap::ArgAction::SetTrue),
         )
-        .get_matches();
+        .subcommand(
+            Command::new("run")
+                .about("Run a workflow or a script")
+                .arg(
+                    Arg::new("target")
+                        .value_name("WORKFLOW|SCRIPT")
+                        .help("Workflow name or .workflow.toml file, or script name or path")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("param")
+                        .long("param")
+                        .value_name("NAME=VALUE")
+                        .help("Set a parameter; for a workflow step's script parameter use STEP.NAME=VALUE")
+                        .action(clap::ArgAction::Append),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Write the script catalogue from the database")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("json (versioned catalogue) or json-v1 (scripts_metadata.json schema)")
+                        .value_parser(["json", "json-v1"])
+                        .default_value("json"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("File to write instead of standard output"),
+                ),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Replace the indexed scripts with those of a catalogue written by export or extract_script_metadata.sh")
+                .arg(Arg::new("file").value_name("FILE").help("Catalogue to read").required(true)),
+        )
+        .subcommand(
+            Command::new("help")
+                .about("Show the manual page of a script, or this help")
+                .arg(
+                    Arg::new("script")
+                        .value_name("SCRIPT")
+                        .help("Script name, menu name or path relative to the toolbox"),
+                ),
+        )
+        .subcommand(
+            Command::new("docs")
+                .about("Generate Markdown documentation from the database")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("generate")
+                        .about("Write README.md, SCRIPT_INDEX.md, STATISTICS.md and a page per category")
+                        .arg(Arg::new("dir").value_name("DIR").help("Output directory").required(true))
+                        .arg(
+                            Arg::new("templates")
+                                .long("templates")
+                                .value_name("DIR")
+                                .help("Directory of templates overriding the built-in ones"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("man")
+                        .about("Write a roff man page per script (toolbox-<script>.1) for installation")
+                        .arg(Arg::new("dir").value_name("DIR").help("Output directory, e.g. /usr/local/share/man/man1").required(true)),
+                )
+                .subcommand(
+                    Command::new("templates")
+                        .about("Copy the built-in templates into a directory for customising")
+                        .arg(Arg::new("dir").value_name("DIR").help("Directory to write them to").required(true)),
+                ),
+        )
+        .subcommand(
+            Command::new("ini")
+                .about("Import or export the legacy toolbox_menu.ini index")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("import")
+                        .about("Replace the indexed scripts with the entries of an INI index")
+                        .arg(Arg::new("file").value_name("FILE").help("Index to read (default: <path>/toolbox_menu.ini)")),
+                )
+                .subcommand(
+                    Command::new("export")
+                        .about("Write the indexed scripts as an INI index")
+                        .arg(Arg::new("file").value_name("FILE").help("Index to write (default: <path>/toolbox_menu.ini)")),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List the indexed scripts and workflows")
+                .arg(
+                    Arg::new("category")
+                        .long("category")
+                        .value_name("CATEGORY")
+                        .help("Only this category and the categories below it"),
+                )
+                .arg(Arg::new("tag").long("tag").value_name("TAG").help("Only scripts with this tag"))
+                .arg(
+                    Arg::new("source")
+                        .long("source")
+                        .value_name("LABEL")
+                        .help("Only entries from the toolbox root with this label"),
+                ),
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("Print a shell completion script")
+                .after_help(
+                    "Load it from your shell's start-up file, e.g.\n  \
+                     bash: source <(toolbox completions bash)\n  \
+                     zsh:  source <(toolbox completions zsh)\n  \
+                     fish: toolbox completions fish | source",
+                )
+                .arg(
+                    Arg::new("shell")
+                        .value_name("SHELL")
+                        .value_parser(completion::SHELLS)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new(completion::COMPLETE_COMMAND)
+                .hide(true)
+                .arg(
+                    Arg::new("words")
+                        .value_name("WORDS")
+                        .num_args(0..)
+                        .trailing_var_arg(true)
+                        .allow_hyphen_values(true),
+                ),
+        )
+        .subcommand(
+            Command::new("sources")
+                .about("Show the toolbox roots, or update those checked out from git")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("List the toolbox roots and the revisions checked out"))
+                .subcommand(
+                    Command::new("update")
+                        .about("Fetch git roots, check out their configured revision and rescan what changed")
+                        .arg(
+                            Arg::new("label")
+                                .value_name("LABEL")
+                                .num_args(0..)
+                                .help("Roots to update (default: every git root)"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("Show recent script executions")
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .short('n')
+                        .value_name("COUNT")
+                        .help("Number of runs to show")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("20"),
+                ),
+        )
+}
 
-    let toolbox_path = PathBuf::from(matches.get_one::<String>("path").unwrap());
-    let db_path = expand_tilde(matches.get_one::<String>("database").unwrap());
-    let debug = matches.get_flag("debug");
+/// The roots given with `--path`, else those in the configuration, else
+/// the system-wide toolbox directory.
+fn toolbox_roots<'a>(paths: impl Iterator<Item = &'a String>, config: &Config) -> Result<Roots> {
+    let mut roots: Vec<Root> = paths.map(|path| Root::parse(path)).collect();
+    if roots.is_empty() {
+        for root in &config.roots {
+            let path = root.path.as_deref().map(expand_tilde);
+            roots.push(match (&root.git, path) {
+                (Some(url), path) => {
+                    let git = GitSource { url: url.clone(), rev: root.rev.clone() };
+                    Root::from_git(root.label.as_deref(), path, git)
+                }
+                (None, _) if root.rev.is_some() => anyhow::bail!("Toolbox root: `rev` needs a `git` repository"),
+                (None, Some(path)) => Root::new(root.label.as_deref(), path),
+                (None, None) => anyhow::bail!("Toolbox root: set `path`, `git` or both"),
+            });
+        }
+    }
+    if roots.is_empty() {
+        roots.push(Root::new(None, PathBuf::from(TOOLBOX_DIR)));
+    }
+    Roots::new(roots)
+}
 
-    // Initialize database
-    let mut database = Database::new(&db_path)?;
-    database.initialize().await?;
+fn print_sources(roots: &Roots, glyphs: &Glyphs) {
+    for root in roots.iter() {
+        println!("{} {}  {}", glyphs.category, root.label, root.path.display());
+        if let Some(git) = &root.git {
+            let checked_out = match sources::revision(&root.path).filter(|_| root.path.join(".git").exists()) {
+                Some(revision) => format!("at {}", sources::short(&revision)),
+                None => "not fetched yet".to_string(),
+            };
+            println!(
+                "    {} {} {} ({})",
+                glyphs.package,
+                git.url,
+                git.rev.as_deref().map(|rev| format!("@ {}", rev)).unwrap_or_default(),
+                checked_out
+            );
+        }
+    }
+}
 
-    // Scan if requested or if database is empty
-    if matches.get_flag("scan") || database.is_empty().await? {
-        println!("🔍 Scanning toolbox directory...");
-        let scanner = ScriptScanner::new(toolbox_path.clone());
-        let scripts = scanner.scan().await?;
-        
-        println!("📝 Updating database with {} scripts...", scripts.len());
-        database.update_scripts(scripts).await?;
-        println!("✅ Database updated successfully!");
+/// Update the git roots named by `labels` (all of them when empty), then
+/// rescan the scripts the updates touched.
+async fn update_sources(database: &Database, roots: &Roots, labels: &[&String], config: &Config, glyphs: &Glyphs) -> Result<()> {
+    for label in labels {
+        match roots.find(label) {
+            Some(root) if root.git.is_none() => anyhow::bail!("Toolbox root '{}' is not a git root", label),
+            Some(_) => {}
+            None => anyhow::bail!("No toolbox root labelled '{}'", label),
+        }
+    }
+    let git_roots: Vec<(&Root, &GitSource)> = roots
+        .iter()
+        .filter(|root| labels.is_empty() || labels.contains(&&root.label))
+        .filter_map(|root| root.git.as_ref().map(|git| (root, git)))
+        .collect();
+    if git_roots.is_empty() {
+        anyhow::bail!("No git roots configured - set `git` on a [[roots]] entry in {}", DEFAULT_CONFIG_PATH);
     }
 
-    // Start the menu system
-    let mut menu_system = MenuSystem::new(database, toolbox_path, debug);
-    menu_system.run().await?;
+    let scanner = ScriptScanner::new(roots.clone());
+    for (root, git) in git_roots {
+        println!("{} Updating {} from {}...", glyphs.package, root.label, git.url);
+        let update = sources::update(root, git)?;
+        match &update.from {
+            None => println!(
+                "{} {} checked out at {} ({} scripts and workflows)",
+                glyphs.success,
+                root.label,
+                sources::short(&update.to),
+                update.added.len()
+            ),
+            Some(_) if update.is_unchanged() => {
+                println!("{} {} is up to date at {}", glyphs.success, root.label, sources::short(&update.to))
+            }
+            Some(from) => {
+                println!(
+                    "{} {} {} {} {}: {} added, {} changed, {} removed",
+                    glyphs.success,
+                    root.label,
+                    sources::short(from),
+                    glyphs.arrow,
+                    sources::short(&update.to),
+                    update.added.len(),
+                    update.changed.len(),
+                    update.removed.len()
+                );
+                for (mark, paths) in [("+", &update.added), ("~", &update.changed), ("-", &update.removed)] {
+                    for path in paths {
+                        println!("    {} {}", mark, path.display());
+                    }
+                }
+            }
+        }
+
+        // Index each root as soon as it is checked out: the next update sees
+        // no change to this one, so a later root failing must not leave it
+        // unindexed
+        let touched: Vec<PathBuf> = update.paths().cloned().collect();
+        reindex(database, &scanner, &touched, roots, config, glyphs).await?;
+    }
+    Ok(())
+}
+
+/// Bring the index up to date with the scripts and workflows at `touched`
+/// (relative to the roots), or with every root while nothing is indexed.
+async fn reindex(
+    database: &Database,
+    scanner: &ScriptScanner,
+    touched: &[PathBuf],
+    roots: &Roots,
+    config: &Config,
+    glyphs: &Glyphs,
+) -> Result<()> {
+    if database.is_empty().await? {
+        // Nothing indexed yet, so every root needs a full scan
+        database.update_scripts(scanner.scan().await?).await?;
+    } else if !touched.is_empty() {
+        let (scripts, replaced) = scanner.scan_paths(touched).await?;
+        println!("{} Rescanned {} scripts", glyphs.search, scripts.len());
+        database.update_scripts_at(replaced, scripts).await?;
+    } else {
+        return Ok(());
+    }
+    // Workflows are few, so they are always rescanned in full
+    database.update_workflows(scanner.scan_workflows().await?).await?;
+    if config.legacy.export {
+        let index = roots.base().path.join(legacy::LEGACY_INDEX_FILE);
+        legacy::write_index(&database.get_all_scripts().await?, &index)?;
+    }
+    println!("{} Database updated successfully!", glyphs.success);
+    Ok(())
+}
+
+/// The indexed scripts `access` lets this user see.
+async fn visible_scripts(database: &Database, access: &Policy) -> Result<Vec<Script>> {
+    let mut scripts = database.get_all_scripts().await?;
+    scripts.retain(|script| access.can_see(script));
+    Ok(scripts)
+}
+
+/// Replace the indexed scripts with those of a legacy index; returns how many were imported.
+async fn import_legacy_index(database: &Database, file: &Path) -> Result<usize> {
+    let imported = legacy::read_index(file)?;
+    for warning in &imported.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    let count = imported.scripts.len();
+    database.update_scripts(imported.scripts).await?;
+    Ok(count)
+}
+
+/// Filters for `toolbox list`; `None` matches everything.
+struct ListFilter<'a> {
+    category: Option<&'a str>,
+    tag: Option<&'a str>,
+    source: Option<&'a str>,
+}
+
+async fn print_list(database: &Database, filter: ListFilter<'_>, roots: &Roots, access: &Policy, glyphs: &Glyphs) -> Result<()> {
+    let ListFilter { category, tag, source } = filter;
+    // A category filter also matches the categories nested below it
+    let in_category = |name: &str| {
+        category.is_none_or(|category| {
+            let (name, category) = (name.to_lowercase(), category.to_lowercase());
+            name == category || name.starts_with(&format!("{}::", category))
+        })
+    };
+
+    let from_source = |label: Option<&str>| source.is_none_or(|source| label == Some(source));
+    // With several roots, say which one each entry comes from
+    let describe = |description: Option<String>, label: Option<&str>, overrides: Option<&str>| {
+        let description = description.unwrap_or_default();
+        match roots::describe_source(label, overrides).filter(|_| roots.is_layered()) {
+            Some(layer) => format!("{} [{}]", description, layer).trim_start().to_string(),
+            None => description,
+        }
+    };
+
+    let mut rows: Vec<(&str, String, String, String)> = Vec::new();
+    for script in database.get_all_scripts().await? {
+        if !access.can_see(&script)
+            || !in_category(&script.category)
+            || !from_source(script.source.as_deref())
+            || tag.is_some_and(|tag| !script.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+        {
+            continue;
+        }
+        let description = describe(script.description.clone(), script.source.as_deref(), script.overrides.as_deref());
+        rows.push((glyphs.script, script.category.clone(), script.name.clone(), description));
+    }
+    // Workflows have no tags, so a tag filter leaves them out
+    if tag.is_none() {
+        for workflow in database.get_all_workflows().await? {
+            if access.can_see_workflow(&workflow)
+                && in_category(&workflow.category)
+                && from_source(workflow.source.as_deref())
+            {
+                let description =
+                    describe(workflow.description.clone(), workflow.source.as_deref(), workflow.overrides.as_deref());
+                rows.push((glyphs.workflow, workflow.category, workflow.name, description));
+            }
+        }
+    }
+    if rows.is_empty() {
+        println!("No scripts match.");
+        return Ok(());
+    }
+    rows.sort_by(|a, b| (&a.1, &a.2).cmp(&(&b.1, &b.2)));
+
+    let category_width = rows.iter().map(|row| row.1.chars().count()).max().unwrap_or(0);
+    let name_width = rows.iter().map(|row| row.2.chars().count()).max().unwrap_or(0);
+    let icon_width = glyphs::display_width(glyphs.script).max(glyphs::display_width(glyphs.workflow));
+    for (icon, category, name, description) in rows {
+        println!(
+            "{}{} {:<category_width$}  {:<name_width$}  {}",
+            icon,
+            " ".repeat(icon_width - glyphs::display_width(icon)),
+            category,
+            name,
+            description
+        );
+    }
+    Ok(())
+}
+
+async fn print_history(database: &Database, limit: usize, glyphs: &Glyphs) -> Result<()> {
+    let records = database.get_execution_history(limit).await?;
+    if records.is_empty() {
+        println!("No scripts have been executed yet.");
+        return Ok(());
+    }
+
+    println!(
+        "  {:<19}  {:<28}  {:>9}  {:<24}  SCRIPT",
+        "STARTED", "OUTCOME", "DURATION", "USER@HOST"
+    );
+    // Icons differ in width (and are text tags in ASCII mode), so pad by
+    // display width rather than by character count
+    let icon_width = [glyphs.success, glyphs.timeout, glyphs.cancelled, glyphs.failure]
+        .iter()
+        .map(|icon| glyphs::display_width(icon))
+        .max()
+        .unwrap_or(0);
+
+    // Runs from the same batch are listed together under a summary line
+    let mut current_batch = None;
+    for record in records {
+        let batch_id = record.batch.as_ref().map(|batch| batch.id);
+        if batch_id != current_batch {
+            if let Some(batch) = &record.batch {
+                println!("{} {} {}", glyphs.gutter, glyphs.package, batch.describe());
+            }
+            current_batch = batch_id;
+        }
+        let gutter = if batch_id.is_some() { glyphs.gutter } else { " " };
+
+        let outcome = &record.outcome;
+        let icon = if outcome.success() {
+            glyphs.success
+        } else if outcome.timed_out {
+            glyphs.timeout
+        } else if outcome.cancelled {
+            glyphs.cancelled
+        } else {
+            glyphs.failure
+        };
+        println!(
+            "{} {:<19}  {} {}  {:>8.1}s  {:<24}  {} ({})",
+            gutter,
+            outcome.started_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"),
+            glyphs::pad(icon, icon_width),
+            glyphs::pad(&outcome.describe(), 27usize.saturating_sub(icon_width)),
+            outcome.duration_ms() as f64 / 1000.0,
+            format!("{}@{}", outcome.user, outcome.hostname),
+            record.script_name,
+            match &record.revision {
+                Some(revision) => format!("{} @ {}", record.script_path.display(), sources::short(revision)),
+                None => record.script_path.display().to_string(),
+            },
+        );
+    }
 
     Ok(())
 }
 
 fn expand_tilde(path: &str) -> PathBuf {
-    if path.starts_with("~/") {
+    if let Some(rest) = path.strip_prefix("~/") {
         if let Some(home) = dirs::home_dir() {
-            return home.join(&path[2..]);
+            return home.join(rest);
         }
     }
     PathBuf::from(path)
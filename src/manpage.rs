@@ -0,0 +1,500 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::docs;
+use crate::models::{Elevation, ParameterType, Script, ScriptParameter};
+use crate::params;
+use crate::roots::Roots;
+
+/// Installed pages are named `toolbox-<script>.1` so they never shadow the
+/// page of the command a script wraps (`htop.sh` next to `htop(1)`).
+pub const PAGE_PREFIX: &str = "toolbox-";
+pub const MAN_SECTION: &str = "1";
+
+/// Left margin of section bodies on the terminal, as `man` uses.
+const INDENT: usize = 7;
+const MAX_WIDTH: usize = 100;
+
+/// A man page, independent of how it is rendered.
+struct Page {
+    name: String,
+    summary: String,
+    synopsis: Vec<SynopsisItem>,
+    sections: Vec<Section>,
+}
+
+struct SynopsisItem {
+    parameter: String,
+    value: String,
+    required: bool,
+}
+
+struct Section {
+    heading: &'static str,
+    blocks: Vec<Block>,
+}
+
+enum Block {
+    Paragraph(String),
+    /// A tagged paragraph: `tag` on its own line, `lines` indented below.
+    Item { tag: String, lines: Vec<String> },
+}
+
+/// Find the script `query` names: its file name or `#MN` name (ignoring
+/// case), or its path, absolute or relative to a toolbox root. A name
+/// shared by several scripts is an error listing their paths.
+pub fn find_script<'a>(scripts: &'a [Script], roots: &Roots, query: &str) -> Result<Option<&'a Script>> {
+    let by_path = scripts.iter().find(|script| {
+        script.path == Path::new(query) || roots.iter().any(|root| script.path == root.path.join(query))
+    });
+    if by_path.is_some() {
+        return Ok(by_path);
+    }
+
+    let query_stem = query.strip_suffix(".sh").unwrap_or(query);
+    let matches: Vec<&Script> = scripts
+        .iter()
+        .filter(|script| {
+            script.name.eq_ignore_ascii_case(query_stem)
+                || script.menu_name.as_deref().is_some_and(|name| name.eq_ignore_ascii_case(query))
+        })
+        .collect();
+
+    match matches.as_slice() {
+        [script] => Ok(Some(script)),
+        [] => Ok(None),
+        _ => {
+            let paths: Vec<String> = matches
+                .iter()
+                .map(|script| format!("  {}", roots.relative(&script.path).display()))
+                .collect();
+            bail!("'{}' matches {} scripts; name one by path:\n{}", query, matches.len(), paths.join("\n"))
+        }
+    }
+}
+
+/// The page as text for the terminal, wrapped to `width` columns.
+pub fn render_text(script: &Script, roots: &Roots, width: usize) -> String {
+    let page = build_page(script, roots, page_name(&script.name));
+    let width = width.clamp(40, MAX_WIDTH);
+    let mut output = String::new();
+
+    let title = format!("{}{}({})", PAGE_PREFIX, page.name, MAN_SECTION).to_uppercase();
+    let middle = "Toolbox Scripts";
+    let gap = width.saturating_sub(title.len() * 2 + middle.len()) / 2;
+    output.push_str(&format!("{}{}{}{}{}\n\n", title, " ".repeat(gap), middle, " ".repeat(gap), title));
+
+    push_heading(&mut output, "NAME");
+    push_wrapped(&mut output, &format!("{} - {}", page.name, page.summary), INDENT, width);
+    output.push('\n');
+
+    push_heading(&mut output, "SYNOPSIS");
+    let synopsis: Vec<String> = page
+        .synopsis
+        .iter()
+        .map(|item| {
+            let argument = format!("{}={}", item.parameter, item.value);
+            if item.required { argument } else { format!("[{}]", argument) }
+        })
+        .collect();
+    let mut line = page.name.clone();
+    if !synopsis.is_empty() {
+        line.push(' ');
+        line.push_str(&synopsis.join(" "));
+    }
+    push_wrapped(&mut output, &line, INDENT, width);
+
+    for section in &page.sections {
+        output.push('\n');
+        push_heading(&mut output, section.heading);
+        for (i, block) in section.blocks.iter().enumerate() {
+            if i > 0 {
+                output.push('\n');
+            }
+            match block {
+                Block::Paragraph(text) => push_wrapped(&mut output, text, INDENT, width),
+                Block::Item { tag, lines } => {
+                    output.push_str(&format!("{}{}\n", " ".repeat(INDENT), tag));
+                    for line in lines {
+                        push_wrapped(&mut output, line, INDENT + 4, width);
+                    }
+                }
+            }
+        }
+    }
+
+    output
+}
+
+/// The page as roff source for `man`, titled `name`.
+pub fn render_roff(script: &Script, roots: &Roots, name: String) -> String {
+    let page = build_page(script, roots, name);
+    let mut output = String::new();
+
+    output.push_str(&format!(
+        ".TH \"{}{}\" \"{}\" \"{}\" \"toolbox\" \"Toolbox Scripts\"\n",
+        PAGE_PREFIX.to_uppercase(),
+        roff_escape(&page.name.to_uppercase()),
+        MAN_SECTION,
+        chrono::Local::now().format("%Y-%m-%d")
+    ));
+    output.push_str(".SH NAME\n");
+    output.push_str(&format!("{}{} \\- {}\n", PAGE_PREFIX, roff_escape(&page.name), roff_escape(&page.summary)));
+
+    output.push_str(".SH SYNOPSIS\n");
+    output.push_str(&format!(".B {}\n", roff_escape(&page.name)));
+    for item in &page.synopsis {
+        let argument = format!("\\fB{}\\fR=\\fI{}\\fR", roff_escape(&item.parameter), roff_escape(&item.value));
+        if item.required {
+            output.push_str(&format!("{}\n", argument));
+        } else {
+            output.push_str(&format!("[{}]\n", argument));
+        }
+    }
+
+    for section in &page.sections {
+        output.push_str(&format!(".SH {}\n", section.heading));
+        for block in &section.blocks {
+            match block {
+                Block::Paragraph(text) => output.push_str(&format!(".PP\n{}\n", roff_line(text))),
+                Block::Item { tag, lines } => {
+                    output.push_str(&format!(".TP\n.B {}\n", roff_escape(tag)));
+                    for (i, line) in lines.iter().enumerate() {
+                        if i > 0 {
+                            output.push_str(".br\n");
+                        }
+                        output.push_str(&format!("{}\n", roff_line(line)));
+                    }
+                }
+            }
+        }
+    }
+
+    output
+}
+
+/// Write a roff page per script into `dir`; returns the files written.
+/// Nothing is written if two scripts would get the same page.
+pub fn write_pages(scripts: &[Script], roots: &Roots, dir: &Path) -> Result<Vec<PathBuf>> {
+    let names = page_names(scripts, roots)?;
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let mut written = Vec::new();
+    for (script, name) in scripts.iter().zip(names) {
+        let path = dir.join(format!("{}{}.{}", PAGE_PREFIX, name, MAN_SECTION));
+        fs::write(&path, render_roff(script, roots, name))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn build_page(script: &Script, roots: &Roots, name: String) -> Page {
+    let relative_path = roots.relative(&script.path);
+    let mut sections = Vec::new();
+
+    // Definitions that do not parse are reported on the page rather than hidden
+    let (parameters, parameter_error) = match script.parse_parameters() {
+        Ok(parameters) => (parameters, None),
+        Err(e) => (Vec::new(), Some(e.to_string())),
+    };
+
+    let mut description = vec![Block::Paragraph(
+        script
+            .detailed_description
+            .clone()
+            .or_else(|| script.description.clone())
+            .unwrap_or_else(|| "No description available.".to_string()),
+    )];
+    let location = match (script.source.as_deref(), script.overrides.as_deref()) {
+        (Some(source), Some(lower)) if roots.is_layered() => format!(
+            "{} in the {} root, where it overrides the one in {}",
+            relative_path.display(),
+            source,
+            lower
+        ),
+        (Some(source), None) if roots.is_layered() => format!("{} in the {} root", relative_path.display(), source),
+        _ => relative_path.display().to_string(),
+    };
+    description.push(Block::Paragraph(format!(
+        "Run from the toolbox menu (category {}). The script is {}.",
+        script.category, location
+    )));
+    sections.push(Section { heading: "DESCRIPTION", blocks: description });
+
+    if !parameters.is_empty() || parameter_error.is_some() {
+        let mut blocks = vec![Block::Paragraph(
+            "The menu asks for each option before the script runs and passes it in the environment variable shown."
+                .to_string(),
+        )];
+        blocks.extend(parameters.iter().map(option_block));
+        if let Some(error) = parameter_error {
+            blocks.push(Block::Paragraph(format!("The parameter definitions in the script are invalid: {}", error)));
+        }
+        sections.push(Section { heading: "OPTIONS", blocks });
+    }
+
+    let mut requirements = Vec::new();
+    if let Some(integration) = &script.integration {
+        let availability = if script.dependency_available { "" } else { " (not available on this host)" };
+        requirements.push(Block::Paragraph(format!("Depends on {}{}.", integration, availability)));
+    }
+    match script.elevation {
+        Elevation::Required => requirements.push(Block::Paragraph("Runs as root, through sudo when needed.".to_string())),
+        Elevation::Optional => {
+            requirements.push(Block::Paragraph("Uses root privileges when they are available.".to_string()))
+        }
+        Elevation::Never => {}
+    }
+    if let Some(timeout) = script.timeout_secs {
+        requirements.push(Block::Paragraph(format!("Stopped after {} seconds.", timeout)));
+    }
+    if let Some(limits) = docs::describe_limits(script) {
+        requirements.push(Block::Paragraph(format!("Resource limits: {}.", limits)));
+    }
+    if script.is_dangerous() {
+        requirements.push(Block::Paragraph("Marked as a dangerous operation (Z1).".to_string()));
+    }
+    if !requirements.is_empty() {
+        sections.push(Section { heading: "REQUIREMENTS", blocks: requirements });
+    }
+
+    if let Some(author) = &script.author {
+        sections.push(Section { heading: "AUTHOR", blocks: vec![Block::Paragraph(author.clone())] });
+    }
+    if !script.tags.is_empty() {
+        sections.push(Section { heading: "TAGS", blocks: vec![Block::Paragraph(script.tags.join(", "))] });
+    }
+    if let Some(info_url) = &script.info_url {
+        sections.push(Section { heading: "SEE ALSO", blocks: vec![Block::Paragraph(info_url.clone())] });
+    }
+
+    Page {
+        name,
+        summary: match (&script.menu_name, &script.description) {
+            (Some(menu_name), Some(description)) => format!("{}: {}", menu_name, description),
+            (Some(menu_name), None) => menu_name.clone(),
+            (None, description) => description.clone().unwrap_or_else(|| script.name.clone()),
+        },
+        synopsis: parameters.iter().map(synopsis_item).collect(),
+        sections,
+    }
+}
+
+fn synopsis_item(parameter: &ScriptParameter) -> SynopsisItem {
+    let options = parameter.options.as_deref().unwrap_or_default();
+    let value = match parameter.param_type {
+        _ if !options.is_empty() => {
+            options.iter().map(|option| option.value.as_str()).collect::<Vec<_>>().join("|")
+        }
+        ParameterType::Boolean | ParameterType::Checkbox => "true|false".to_string(),
+        param_type => value_name(param_type).to_string(),
+    };
+    SynopsisItem { parameter: parameter.name.clone(), value, required: parameter.required }
+}
+
+fn option_block(parameter: &ScriptParameter) -> Block {
+    let mut lines = Vec::new();
+    let mut heading = parameter.label.clone();
+    if let Some(description) = &parameter.description {
+        heading = format!("{}. {}", heading.trim_end_matches('.'), description);
+    }
+    lines.push(heading);
+
+    let kind = serde_json::to_value(parameter.param_type)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default();
+    lines.push(format!(
+        "Type: {}{}. Environment: {}.",
+        kind,
+        if parameter.required { ", required" } else { "" },
+        params::env_name(&parameter.name)
+    ));
+
+    for option in parameter.options.iter().flatten() {
+        if option.label.is_empty() || option.label == option.value {
+            lines.push(format!("  {}", option.value));
+        } else {
+            lines.push(format!("  {} - {}", option.value, option.label));
+        }
+    }
+    match &parameter.default_value {
+        Some(_) if parameter.param_type == ParameterType::Password => lines.push("Default: (hidden)".to_string()),
+        Some(default) => lines.push(format!("Default: {}", default)),
+        None => {}
+    }
+    let constraints = parameter.constraints();
+    if !constraints.is_empty() {
+        lines.push(format!("Valid values: {}.", constraints.join("; ")));
+    }
+
+    Block::Item {
+        tag: format!("{}={}", parameter.name, value_name(parameter.param_type)),
+        lines,
+    }
+}
+
+fn value_name(param_type: ParameterType) -> &'static str {
+    match param_type {
+        ParameterType::Number => "NUMBER",
+        ParameterType::Password => "PASSWORD",
+        ParameterType::File => "FILE",
+        ParameterType::Directory => "DIRECTORY",
+        ParameterType::Boolean | ParameterType::Checkbox => "BOOL",
+        ParameterType::Select | ParameterType::Radio => "CHOICE",
+        ParameterType::Text => "TEXT",
+    }
+}
+
+/// Page names for `scripts`, in order. A script whose name gives the same
+/// page as another's is told apart by its category (`net-ping`), then by
+/// its root as well (`team-net-ping`); scripts still sharing a page after
+/// that are an error listing their paths.
+fn page_names(scripts: &[Script], roots: &Roots) -> Result<Vec<String>> {
+    const MOST_QUALIFIED: usize = 2;
+    let qualified = |script: &Script, level: usize| {
+        let mut parts = Vec::new();
+        if level >= 2 {
+            parts.push(roots.label_of(&script.path).unwrap_or_default());
+        }
+        if level >= 1 {
+            parts.push(script.category.clone());
+        }
+        parts.push(script.name.clone());
+        parts.iter().map(|part| page_name(part)).collect::<Vec<_>>().join("-")
+    };
+
+    let mut levels = vec![0; scripts.len()];
+    loop {
+        let names: Vec<String> = scripts.iter().zip(&levels).map(|(script, &level)| qualified(script, level)).collect();
+        let mut raised = false;
+        for (first, name) in names.iter().enumerate() {
+            let shared: Vec<usize> = (first..names.len()).filter(|&i| names[i] == *name).collect();
+            if shared.len() < 2 || names[..first].contains(name) {
+                continue;
+            }
+            if shared.iter().all(|&i| levels[i] == MOST_QUALIFIED) {
+                let paths: Vec<String> = shared.iter().map(|&i| format!("  {}", scripts[i].path.display())).collect();
+                bail!("These scripts would share the man page {}{}:\n{}", PAGE_PREFIX, name, paths.join("\n"));
+            }
+            for i in shared {
+                if levels[i] < MOST_QUALIFIED {
+                    levels[i] += 1;
+                    raised = true;
+                }
+            }
+        }
+        if !raised {
+            return Ok(names);
+        }
+    }
+}
+
+/// Names become page names: lowercase, with anything a shell or `man`
+/// would trip over replaced.
+fn page_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' { c.to_ascii_lowercase() } else { '_' })
+        .collect()
+}
+
+fn push_heading(output: &mut String, heading: &str) {
+    output.push_str(&console::style(heading).bold().to_string());
+    output.push('\n');
+}
+
+/// Word-wrap `text` at `width` columns with `indent` spaces before each line.
+/// Lines that start with spaces keep them, so option lists stay indented.
+fn push_wrapped(output: &mut String, text: &str, indent: usize, width: usize) {
+    let leading = text.len() - text.trim_start().len();
+    let prefix = " ".repeat(indent + leading);
+    let available = width.saturating_sub(indent + leading).max(20);
+
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > available {
+            output.push_str(&format!("{}{}\n", prefix, line));
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    output.push_str(&format!("{}{}\n", prefix, line));
+}
+
+/// Escape text for use inside a roff line.
+fn roff_escape(text: &str) -> String {
+    text.replace('\\', "\\e").replace('-', "\\-")
+}
+
+/// Escape a whole roff text line, which must not start with a control character.
+fn roff_line(text: &str) -> String {
+    let escaped = roff_escape(text.trim());
+    if escaped.starts_with('.') || escaped.starts_with('\'') {
+        format!("\\&{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roots::Root;
+
+    fn roots() -> Roots {
+        Roots::new(vec![
+            Root::new(Some("system"), PathBuf::from("/opt/toolbox")),
+            Root::new(Some("team"), PathBuf::from("/srv/team")),
+        ])
+        .unwrap()
+    }
+
+    fn script(path: &str, category: &str) -> Script {
+        let path = PathBuf::from(path);
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        Script::new(name, path, category.to_string())
+    }
+
+    #[test]
+    fn unique_names_are_used_as_they_are() {
+        let scripts = [script("/opt/toolbox/Net/Ping Host.sh", "Net"), script("/opt/toolbox/disk.sh", "TopLevel")];
+        assert_eq!(page_names(&scripts, &roots()).unwrap(), ["ping_host", "disk"]);
+    }
+
+    #[test]
+    fn shared_names_are_qualified_by_category_then_root() {
+        let scripts = [
+            script("/opt/toolbox/Net/check.sh", "Net"),
+            script("/opt/toolbox/Disk/check.sh", "Disk"),
+            script("/srv/team/Disk/Check.sh", "Disk"),
+            script("/srv/team/Disk/status.sh", "Disk"),
+        ];
+        assert_eq!(
+            page_names(&scripts, &roots()).unwrap(),
+            ["net-check", "system-disk-check", "team-disk-check", "status"]
+        );
+    }
+
+    #[test]
+    fn names_that_cannot_be_told_apart_are_an_error() {
+        let scripts = [script("/opt/toolbox/Net/a b.sh", "Net"), script("/opt/toolbox/Net/a_b.sh", "Net")];
+        let error = page_names(&scripts, &roots()).unwrap_err().to_string();
+        assert!(error.starts_with("These scripts would share the man page toolbox-system-net-a_b:"));
+        assert!(error.contains("/opt/toolbox/Net/a b.sh") && error.contains("/opt/toolbox/Net/a_b.sh"));
+    }
+
+    #[test]
+    fn write_pages_names_files_and_titles_alike() {
+        let dir = tempfile::tempdir().unwrap();
+        let scripts = [script("/opt/toolbox/Net/check.sh", "Net"), script("/opt/toolbox/Disk/check.sh", "Disk")];
+        let written = write_pages(&scripts, &roots(), dir.path()).unwrap();
+        assert_eq!(written, [dir.path().join("toolbox-net-check.1"), dir.path().join("toolbox-disk-check.1")]);
+        let page = fs::read_to_string(&written[1]).unwrap();
+        assert!(page.starts_with(r#".TH "TOOLBOX-DISK\-CHECK" "1""#));
+    }
+}
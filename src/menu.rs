@@ -1,56 +1,133 @@
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
 use crate::database::Database;
-use crate::models::{MenuCategory, MenuItem, MenuState, Script};
+use crate::models::{MenuCategory, MenuItem, MenuState, Script, Workflow};
+use crate::query::{self, FilterKind, SearchQuery};
+use crate::roots::Roots;
 use crate::search::SearchEngine;
-use crate::ui::MenuUI;
-use crate::display::ScriptExecutor;
+use crate::ui::{MenuUI, MouseTarget};
+use crate::display::{ScriptExecutor, NO_SUDO};
+use crate::audit::Auditor;
+use crate::integrity::IntegrityPolicy;
+use crate::policy::Policy;
+use crate::keymap::{self, Action, Keymap};
+use crate::system::PrivilegeContext;
+use crate::theme::Theme;
+use crate::glyphs::Glyphs;
+use crate::{batch, params, workflow};
+
+const HISTORY_DIALOG_LIMIT: usize = 50;
+/// How long to wait for input before checking whether the database changed.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Two clicks on the same entry within this interval run it.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+/// Entries moved per mouse wheel step.
+const WHEEL_SCROLL_ITEMS: i32 = 3;
 
 pub struct MenuSystem {
     database: Database,
-    toolbox_path: PathBuf,
+    roots: Roots,
     state: MenuState,
     ui: MenuUI,
     search_engine: SearchEngine,
     executor: ScriptExecutor,
+    /// Decides which scripts this user sees; the executor enforces running.
+    access: Policy,
+    keymap: Keymap,
+    glyphs: Glyphs,
+    menu_dirty: bool,
+    needs_redraw: bool,
+    data_version: i64,
+    /// Query text before the word that Tab completion replaces.
+    completion_prefix: String,
+    /// Entry and time of the last click, for detecting double-clicks.
+    last_click: Option<(usize, Instant)>,
+    /// Set by `--debug`, which nothing reports on yet.
+    #[allow(dead_code)]
     debug: bool,
 }
 
 impl MenuSystem {
-    pub fn new(database: Database, toolbox_path: PathBuf, debug: bool) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        database: Database,
+        roots: Roots,
+        privileges: PrivilegeContext,
+        default_timeout: Option<Duration>,
+        integrity: IntegrityPolicy,
+        access: Policy,
+        audit: Auditor,
+        keymap: Keymap,
+        theme: Theme,
+        glyphs: Glyphs,
+        debug: bool,
+    ) -> Self {
+        let state = MenuState {
+            can_elevate: privileges.can_elevate(),
+            show_sources: roots.is_layered(),
+            ..MenuState::default()
+        };
+
         Self {
             database,
-            toolbox_path: toolbox_path.clone(),
-            state: MenuState::default(),
-            ui: MenuUI::new(),
+            roots: roots.clone(),
+            state,
+            ui: MenuUI::new(keymap.clone(), theme, glyphs),
             search_engine: SearchEngine::new(),
-            executor: ScriptExecutor::new(toolbox_path),
+            executor: ScriptExecutor::new(roots, privileges, default_timeout, integrity, access.clone(), audit, glyphs),
+            access,
+            keymap,
+            glyphs,
+            menu_dirty: true,
+            needs_redraw: true,
+            data_version: 0,
+            completion_prefix: String::new(),
+            last_click: None,
             debug,
         }
     }
 
     pub async fn run(&mut self) -> Result<()> {
         self.ui.initialize()?;
+        self.data_version = self.database.data_version().await?;
 
         loop {
-            // Update menu items based on current state
-            if !self.state.search_mode {
+            // Rebuild the menu only when navigation or the database changed it
+            if self.menu_dirty && !self.state.search_mode {
                 self.update_menu_items().await?;
+                self.menu_dirty = false;
+                self.needs_redraw = true;
             }
 
             // Render the current menu
-            self.ui.render(&self.state)?;
+            if self.needs_redraw {
+                self.ui.render(&self.state)?;
+                self.needs_redraw = false;
+            }
 
             // Handle user input
-            if let Ok(true) = event::poll(Duration::from_millis(100)) {
-                if let Ok(Event::Key(key_event)) = event::read() {
-                    if self.handle_key_event(key_event).await? {
-                        break; // Exit requested
+            if event::poll(IDLE_POLL_INTERVAL)? {
+                match event::read()? {
+                    Event::Key(key_event) => {
+                        self.needs_redraw = true;
+                        if self.handle_key_event(key_event).await? {
+                            break; // Exit requested
+                        }
+                    }
+                    Event::Mouse(mouse_event) => {
+                        let exit = self.handle_mouse_event(mouse_event).await?;
+                        if exit {
+                            break; // Exit requested
+                        }
                     }
+                    Event::Resize(_, _) => self.needs_redraw = true,
+                    _ => {}
                 }
+            } else {
+                self.check_for_database_changes().await?;
             }
         }
 
@@ -58,30 +135,71 @@ impl MenuSystem {
         Ok(())
     }
 
+    /// Pick up rescans made by another toolbox process while the menu is open.
+    async fn check_for_database_changes(&mut self) -> Result<()> {
+        let data_version = self.database.data_version().await?;
+        if data_version != self.data_version {
+            self.data_version = data_version;
+            self.menu_dirty = true;
+            if self.state.search_mode {
+                self.update_search_results().await?;
+                self.needs_redraw = true;
+            }
+        }
+        Ok(())
+    }
+
     async fn handle_key_event(&mut self, key_event: KeyEvent) -> Result<bool> {
-        match key_event.code {
+        self.state.status_message = None;
+
+        // While searching, plain characters are always part of the query
+        if self.state.search_mode {
+            if let KeyCode::Char(c) = key_event.code {
+                if keymap::is_text_input(&key_event) {
+                    self.state.search_query.push(c);
+                    self.update_search_results().await?;
+                    return Ok(false);
+                }
+            }
+            if key_event.code == KeyCode::Backspace && key_event.modifiers.is_empty() {
+                self.state.search_query.pop();
+                self.update_search_results().await?;
+                return Ok(false);
+            }
+        }
+
+        let Some(action) = self.keymap.action_for(&key_event) else {
+            // Number selection (1-9, 0)
+            if let KeyCode::Char(c) = key_event.code {
+                if c.is_ascii_digit() && !self.state.search_mode {
+                    let num = if c == '0' { 10 } else { c.to_digit(10).unwrap() as usize };
+                    self.select_by_number(num - 1).await?;
+                }
+            }
+            return Ok(false);
+        };
+
+        match action {
             // Navigation shortcuts
-            KeyCode::Char('x') | KeyCode::Char('X') => {
+            Action::Back => {
                 if self.state.search_mode {
                     self.exit_search_mode().await?;
                 } else {
                     self.go_back().await?;
                 }
             }
-            KeyCode::Char('h') | KeyCode::Char('H') => {
+            Action::Home => {
                 self.go_home().await?;
             }
-            KeyCode::Char('s') | KeyCode::Char('S') => {
+            Action::Search => {
                 if !self.state.search_mode {
                     self.enter_search_mode().await?;
                 }
             }
-            KeyCode::Char('q') | KeyCode::Char('Q') => {
-                if key_event.modifiers.contains(KeyModifiers::CONTROL) {
-                    return Ok(true); // Exit
-                }
+            Action::Quit => {
+                return Ok(true); // Exit
             }
-            KeyCode::Esc => {
+            Action::Cancel => {
                 if self.state.search_mode {
                     self.exit_search_mode().await?;
                 } else {
@@ -90,93 +208,165 @@ impl MenuSystem {
             }
 
             // Menu navigation
-            KeyCode::Up | KeyCode::Char('k') => {
+            Action::Up => {
                 self.move_selection(-1);
             }
-            KeyCode::Down | KeyCode::Char('j') => {
+            Action::Down => {
                 self.move_selection(1);
             }
-            KeyCode::PageUp => {
+            Action::PageUp => {
                 self.move_selection(-10);
             }
-            KeyCode::PageDown => {
+            Action::PageDown => {
                 self.move_selection(10);
             }
-            KeyCode::Home => {
+            Action::First => {
                 self.state.selected_index = 0;
             }
-            KeyCode::End => {
-                if self.state.search_mode {
-                    if !self.state.filtered_items.is_empty() {
-                        self.state.selected_index = self.state.filtered_items.len() - 1;
-                    }
-                } else {
-                    // Will be set by update_menu_items
-                }
+            Action::Last => {
+                self.state.selected_index = self.state.filtered_items.len().saturating_sub(1);
             }
 
             // Selection and execution
-            KeyCode::Enter => {
+            Action::Select => {
                 self.execute_selected_item().await?;
             }
 
-            // Number selection (1-9, 0)
-            KeyCode::Char(c) if c.is_ascii_digit() => {
-                let num = if c == '0' { 10 } else { c.to_digit(10).unwrap() as usize };
-                if num > 0 {
-                    self.select_by_number(num - 1).await?;
-                }
+            // Batch runs
+            Action::Mark => {
+                self.toggle_mark();
+            }
+            Action::RunMarked => {
+                self.run_marked().await?;
             }
 
-            // Search mode input
-            KeyCode::Char(c) if self.state.search_mode => {
-                self.state.search_query.push(c);
-                self.update_search_results().await?;
+            // Search mode
+            Action::Complete => {
+                if self.state.search_mode {
+                    self.accept_suggestion().await?;
+                }
             }
-            KeyCode::Backspace if self.state.search_mode => {
-                self.state.search_query.pop();
-                self.update_search_results().await?;
+            Action::ToggleBodies => {
+                // Toggle searching script bodies with the free text
+                if self.state.search_mode {
+                    self.state.search_bodies = !self.state.search_bodies;
+                    self.update_search_results().await?;
+                }
             }
 
             // Help
-            KeyCode::F(1) | KeyCode::Char('?') => {
+            Action::Help => {
                 self.show_help().await?;
             }
 
-            _ => {}
+            // Execution history
+            Action::History => {
+                self.show_history().await?;
+            }
         }
 
         Ok(false)
     }
 
+    async fn handle_mouse_event(&mut self, mouse_event: MouseEvent) -> Result<bool> {
+        let target = self.ui.mouse_target(mouse_event.column, mouse_event.row);
+
+        match mouse_event.kind {
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let direction = if mouse_event.kind == MouseEventKind::ScrollUp { -1 } else { 1 };
+                if target == Some(MouseTarget::DetailPanel) {
+                    self.ui.scroll_detail(direction);
+                } else {
+                    self.move_selection(direction * WHEEL_SCROLL_ITEMS);
+                }
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.state.status_message = None;
+                match target {
+                    Some(MouseTarget::Item(index)) => {
+                        if let Some(MenuItem::Separator(_)) = self.state.filtered_items.get(index) {
+                            return Ok(false);
+                        }
+
+                        let double_click = matches!(
+                            self.last_click,
+                            Some((last_index, at)) if last_index == index && at.elapsed() <= DOUBLE_CLICK_INTERVAL
+                        );
+                        self.state.selected_index = index;
+
+                        if double_click {
+                            self.last_click = None;
+                            if let Some(MenuItem::Exit) = self.state.filtered_items.get(index) {
+                                return Ok(true);
+                            }
+                            self.execute_selected_item().await?;
+                        } else {
+                            self.last_click = Some((index, Instant::now()));
+                        }
+                    }
+                    Some(MouseTarget::Breadcrumb(depth)) => {
+                        self.jump_to_breadcrumb(depth).await?;
+                    }
+                    _ => return Ok(false),
+                }
+            }
+            // Pointer movement and other buttons do not change anything
+            _ => return Ok(false),
+        }
+
+        self.needs_redraw = true;
+        Ok(false)
+    }
+
     async fn update_menu_items(&mut self) -> Result<()> {
         if self.state.current_category == "root" {
-            // Show main categories
-            let categories = self.database.get_all_categories().await?;
+            // Show main categories, fetched in a single query and grouped here
             let mut items = Vec::new();
+            let mut categories: Vec<MenuCategory> = Vec::new();
+            let mut top_level = Vec::new();
 
-            // Add TopLevel scripts first if they exist
-            if categories.contains(&"TopLevel".to_string()) {
-                let scripts = self.database.get_scripts_by_category("TopLevel").await?;
-                for script in scripts {
-                    items.push(MenuItem::Script(script));
+            for script in self.visible_scripts().await? {
+                if script.category == "TopLevel" {
+                    top_level.push(script);
+                    continue;
                 }
-                if !items.is_empty() {
-                    items.push(MenuItem::Separator("Categories".to_string()));
+
+                match categories.last_mut() {
+                    Some(category) if category.name == script.category => category.scripts.push(script),
+                    _ => {
+                        let mut menu_category = MenuCategory::new(script.category.clone(), PathBuf::from(&script.category));
+                        menu_category.scripts.push(script);
+                        categories.push(menu_category);
+                    }
                 }
             }
 
-            // Add other categories
-            for category in categories {
-                if category != "TopLevel" {
-                    let scripts = self.database.get_scripts_by_category(&category).await?;
-                    if !scripts.is_empty() {
-                        let mut menu_category = MenuCategory::new(category.clone(), PathBuf::from(&category));
-                        menu_category.scripts = scripts;
-                        items.push(MenuItem::Category(menu_category));
-                    }
+            // Workflows join the categories of their directories; a
+            // category can hold only workflows
+            let mut top_level_workflows = Vec::new();
+            let workflows = self.database.get_all_workflows().await?;
+            for workflow in workflows.into_iter().filter(|workflow| self.access.can_see_workflow(workflow)) {
+                if workflow.category == "TopLevel" {
+                    top_level_workflows.push(workflow);
+                } else if let Some(category) = categories.iter_mut().find(|category| category.name == workflow.category) {
+                    category.workflows.push(workflow);
+                } else {
+                    let mut menu_category = MenuCategory::new(workflow.category.clone(), PathBuf::from(&workflow.category));
+                    menu_category.workflows.push(workflow);
+                    categories.push(menu_category);
                 }
             }
+            categories.sort_by(|a, b| a.name.cmp(&b.name));
+
+            // Add TopLevel scripts first if they exist
+            if !top_level.is_empty() || !top_level_workflows.is_empty() {
+                items.extend(top_level.into_iter().map(MenuItem::Script));
+                items.extend(top_level_workflows.into_iter().map(MenuItem::Workflow));
+                items.push(MenuItem::Separator("Categories".to_string()));
+            }
+
+            // Add other categories
+            items.extend(categories.into_iter().map(MenuItem::Category));
 
             // Add navigation items
             items.push(MenuItem::Search);
@@ -185,7 +375,8 @@ impl MenuSystem {
             self.state.filtered_items = items;
         } else {
             // Show scripts in current category
-            let scripts = self.database.get_scripts_by_category(&self.state.current_category).await?;
+            let mut scripts = self.database.get_scripts_by_category(&self.state.current_category).await?;
+            scripts.retain(|script| self.access.can_see(script));
             let mut items = Vec::new();
 
             // Group by separator
@@ -200,6 +391,15 @@ impl MenuSystem {
                 items.push(MenuItem::Script(script));
             }
 
+            let mut workflows = self.database.get_workflows_by_category(&self.state.current_category).await?;
+            workflows.retain(|workflow| self.access.can_see_workflow(workflow));
+            if !workflows.is_empty() {
+                if !items.is_empty() {
+                    items.push(MenuItem::Separator("Workflows".to_string()));
+                }
+                items.extend(workflows.into_iter().map(MenuItem::Workflow));
+            }
+
             // Add navigation items
             items.push(MenuItem::Back);
             items.push(MenuItem::Home);
@@ -212,6 +412,7 @@ impl MenuSystem {
         if self.state.selected_index >= self.state.filtered_items.len() && !self.state.filtered_items.is_empty() {
             self.state.selected_index = self.state.filtered_items.len() - 1;
         }
+        self.note_run_denied();
 
         Ok(())
     }
@@ -225,25 +426,103 @@ impl MenuSystem {
     }
 
     async fn exit_search_mode(&mut self) -> Result<()> {
+        self.menu_dirty = true;
         self.state.search_mode = false;
         self.state.search_query.clear();
+        self.state.suggestions.clear();
+        self.state.search_matches.clear();
+        self.state.body_matches.clear();
         self.state.selected_index = 0;
         Ok(())
     }
 
+    /// Every indexed script the access policy lets this user see.
+    async fn visible_scripts(&self) -> Result<Vec<Script>> {
+        let mut scripts = self.database.get_all_scripts().await?;
+        scripts.retain(|script| self.access.can_see(script));
+        Ok(scripts)
+    }
+
+    /// Note which of the listed scripts this user may see but not run, so
+    /// the UI can show them disabled.
+    fn note_run_denied(&mut self) {
+        self.state.run_denied = self
+            .state
+            .filtered_items
+            .iter()
+            .filter_map(|item| match item {
+                MenuItem::Script(script) if !self.access.can_run(script) => Some(script.path.clone()),
+                _ => None,
+            })
+            .collect();
+    }
+
     async fn update_search_results(&mut self) -> Result<()> {
-        if self.state.search_query.is_empty() {
+        let scripts = self.visible_scripts().await?;
+        self.update_suggestions(&scripts);
+        self.refresh_search_results(scripts).await
+    }
+
+    async fn refresh_search_results(&mut self, scripts: Vec<Script>) -> Result<()> {
+        let query = SearchQuery::parse(&self.state.search_query);
+        self.state.search_matches.clear();
+        self.state.body_matches.clear();
+        if query.is_empty() {
             self.state.filtered_items.clear();
             return Ok(());
         }
 
-        let scripts = self.database.search_scripts(&self.state.search_query).await?;
-        let mut items = Vec::new();
+        // Full-text candidates first; fall back to fuzzy matching over every
+        // script so typos and abbreviations still find something. A query made
+        // only of qualifiers starts from the whole catalogue.
+        let mut results = if query.text.trim().is_empty() {
+            scripts.clone()
+        } else {
+            let candidates = self.database.search_scripts(&query.text).await?;
+            if candidates.is_empty() {
+                self.search_engine.fuzzy_search(&scripts, &query.text)
+            } else {
+                self.search_engine.rank_candidates(&candidates, &query.text)
+            }
+        };
+
+        // With body search toggled on, scripts whose body matches the text
+        // follow the metadata matches
+        if self.state.search_bodies && !query.text.trim().is_empty() {
+            for path in self.find_in_bodies(&query.text).await? {
+                if !results.iter().any(|script| script.path == path) {
+                    if let Some(script) = scripts.iter().find(|script| script.path == path) {
+                        results.push(script.clone());
+                    }
+                }
+            }
+        }
 
-        // Use fuzzy matching for better results
-        let fuzzy_results = self.search_engine.fuzzy_search(&scripts, &self.state.search_query);
+        for filter in &query.filters {
+            if let FilterKind::Body(text) = &filter.kind {
+                let paths = if filter.negated {
+                    self.database
+                        .search_bodies(text)
+                        .await?
+                        .into_iter()
+                        .map(|(path, _)| path)
+                        .collect()
+                } else {
+                    self.find_in_bodies(text).await?
+                };
+                results.retain(|script| paths.contains(&script.path) != filter.negated);
+            }
+        }
+        // Full-text candidates come straight from the database
+        results.retain(|script| self.access.can_see(script));
+        let results = self.search_engine.apply_filters(&results, &query.filters);
 
-        for script in fuzzy_results {
+        let mut items = Vec::new();
+        for script in results {
+            if !query.text.trim().is_empty() {
+                let details = self.search_engine.match_details(&script, &query.text);
+                self.state.search_matches.insert(script.path.clone(), details);
+            }
             items.push(MenuItem::Script(script));
         }
 
@@ -252,10 +531,54 @@ impl MenuSystem {
 
         self.state.filtered_items = items;
         self.state.selected_index = 0;
+        self.note_run_denied();
 
         Ok(())
     }
 
+    /// Paths of scripts whose body matches `text`, recording the matching
+    /// line of each for display.
+    async fn find_in_bodies(&mut self, text: &str) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for (path, body) in self.database.search_bodies(text).await? {
+            if let Some(body_match) = self.search_engine.locate_body_match(&body, text) {
+                self.state.body_matches.entry(path.clone()).or_insert(body_match);
+            }
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    /// Recompute completions for the word under the cursor.
+    fn update_suggestions(&mut self, scripts: &[Script]) {
+        let (prefix, word) = query::split_last_word(&self.state.search_query);
+        self.completion_prefix = prefix.to_string();
+        self.state.suggestion_index = None;
+        self.state.suggestions = if word.is_empty() {
+            Vec::new()
+        } else {
+            self.search_engine.complete_word(scripts, word)
+        };
+    }
+
+    /// Replace the word being typed with the next suggestion. Repeated Tab
+    /// presses cycle through the same list.
+    async fn accept_suggestion(&mut self) -> Result<()> {
+        if self.state.suggestions.is_empty() {
+            return Ok(());
+        }
+
+        let index = match self.state.suggestion_index {
+            Some(index) => (index + 1) % self.state.suggestions.len(),
+            None => 0,
+        };
+        self.state.suggestion_index = Some(index);
+        self.state.search_query = format!("{}{}", self.completion_prefix, self.state.suggestions[index]);
+
+        let scripts = self.visible_scripts().await?;
+        self.refresh_search_results(scripts).await
+    }
+
     async fn execute_selected_item(&mut self) -> Result<()> {
         if self.state.filtered_items.is_empty() {
             return Ok(());
@@ -267,6 +590,9 @@ impl MenuSystem {
             MenuItem::Script(script) => {
                 self.execute_script(script).await?;
             }
+            MenuItem::Workflow(workflow) => {
+                self.run_workflow(workflow).await?;
+            }
             MenuItem::Category(category) => {
                 self.enter_category(&category.name).await?;
             }
@@ -291,19 +617,31 @@ impl MenuSystem {
     }
 
     async fn execute_script(&mut self, script: &Script) -> Result<()> {
+        if let Some(denied) = self.executor.access_denied(script) {
+            self.executor.refuse(script, &params::ParameterValues::default(), None, &denied);
+            self.state.status_message = Some(denied);
+            return Ok(());
+        }
+        if script.requires_elevation() && !self.state.can_elevate {
+            self.executor.refuse(script, &params::ParameterValues::default(), None, NO_SUDO);
+            self.state.status_message = Some(format!(
+                "{} requires root privileges and you cannot use sudo",
+                script.display_name()
+            ));
+            return Ok(());
+        }
+
         self.ui.cleanup()?;
 
-        let start_time = Instant::now();
-        let exit_code = self.executor.execute(script).await?;
-        let duration = start_time.elapsed();
+        if let Some(parameters) = params::collect(script, &self.glyphs)? {
+            let outcome = self.executor.execute(script, &parameters).await?;
 
-        // Record execution in database
-        if let Some(script_id) = script.id {
+            // Record execution in database
             self.database.record_execution(
-                script_id,
-                exit_code,
-                duration.as_millis() as u64,
-                None, // TODO: Add parameter support
+                script,
+                &outcome,
+                parameters.to_history_json().as_deref(),
+                None,
             ).await?;
         }
 
@@ -316,6 +654,81 @@ impl MenuSystem {
         Ok(())
     }
 
+    async fn run_workflow(&mut self, workflow: &Workflow) -> Result<()> {
+        self.ui.cleanup()?;
+
+        // A broken workflow file is reported, not fatal to the menu
+        let result = workflow::run(&self.executor, &self.database, &self.roots, &workflow.path, &[], &self.glyphs).await;
+        if let Err(e) = result {
+            println!("{} {:#}", self.glyphs.failure, e);
+        }
+
+        // Wait for user input before returning to menu
+        println!("\nPress Enter to return to menu...");
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+
+        self.ui.initialize()?;
+        Ok(())
+    }
+
+    /// Add the selected script to the batch, or take it out again.
+    fn toggle_mark(&mut self) {
+        let Some(MenuItem::Script(script)) = self.state.filtered_items.get(self.state.selected_index) else {
+            return;
+        };
+
+        if let Some(position) = self.state.marked.iter().position(|marked| marked.path == script.path) {
+            self.state.marked.remove(position);
+        } else if let Some(denied) = self.executor.access_denied(script) {
+            self.state.status_message = Some(denied);
+            return;
+        } else if script.requires_elevation() && !self.state.can_elevate {
+            self.state.status_message = Some(format!(
+                "{} requires root privileges and you cannot use sudo",
+                script.display_name()
+            ));
+            return;
+        } else {
+            self.state.marked.push(script.clone());
+        }
+
+        self.state.status_message = match self.state.marked.len() {
+            0 => None,
+            count => Some(format!(
+                "{} marked - press {} to run",
+                if count == 1 { "1 script".to_string() } else { format!("{} scripts", count) },
+                self.keymap.primary_key(Action::RunMarked, &self.glyphs)
+            )),
+        };
+        self.move_selection(1);
+    }
+
+    async fn run_marked(&mut self) -> Result<()> {
+        if self.state.marked.is_empty() {
+            self.state.status_message = Some(format!(
+                "No scripts marked - press {} on a script to mark it",
+                self.keymap.primary_key(Action::Mark, &self.glyphs)
+            ));
+            return Ok(());
+        }
+
+        self.ui.cleanup()?;
+
+        // Keep the marks if the batch is cancelled before it starts
+        if batch::run(&self.executor, &self.database, &self.state.marked, &self.glyphs).await? {
+            self.state.marked.clear();
+        }
+
+        // Wait for user input before returning to menu
+        println!("\nPress Enter to return to menu...");
+        let mut input = String::new();
+        std::io::stdin().read_line(&mut input)?;
+
+        self.ui.initialize()?;
+        Ok(())
+    }
+
     async fn select_by_number(&mut self, index: usize) -> Result<()> {
         if index < self.state.filtered_items.len() {
             self.state.selected_index = index;
@@ -325,6 +738,7 @@ impl MenuSystem {
     }
 
     async fn enter_category(&mut self, category: &str) -> Result<()> {
+        self.menu_dirty = true;
         self.state.breadcrumb.push(category.to_string());
         self.state.current_category = category.to_string();
         self.state.selected_index = 0;
@@ -332,6 +746,7 @@ impl MenuSystem {
     }
 
     async fn go_back(&mut self) -> Result<()> {
+        self.menu_dirty = true;
         if self.state.breadcrumb.len() > 1 {
             self.state.breadcrumb.pop();
             if let Some(parent) = self.state.breadcrumb.last() {
@@ -346,12 +761,32 @@ impl MenuSystem {
         Ok(())
     }
 
+    /// Return to an ancestor category, by its depth in the breadcrumb.
+    async fn jump_to_breadcrumb(&mut self, depth: usize) -> Result<()> {
+        if depth == 0 {
+            return self.go_home().await;
+        }
+        if depth + 1 >= self.state.breadcrumb.len() {
+            return Ok(()); // Already there
+        }
+
+        self.menu_dirty = true;
+        self.state.breadcrumb.truncate(depth + 1);
+        self.state.current_category = self.state.breadcrumb[depth].clone();
+        self.state.selected_index = 0;
+        Ok(())
+    }
+
     async fn go_home(&mut self) -> Result<()> {
+        self.menu_dirty = true;
         self.state.breadcrumb = vec!["Home".to_string()];
         self.state.current_category = "root".to_string();
         self.state.selected_index = 0;
         self.state.search_mode = false;
         self.state.search_query.clear();
+        self.state.suggestions.clear();
+        self.state.search_matches.clear();
+        self.state.body_matches.clear();
         Ok(())
     }
 
@@ -381,4 +816,10 @@ impl MenuSystem {
         self.ui.show_help_dialog()?;
         Ok(())
     }
+
+    async fn show_history(&mut self) -> Result<()> {
+        let records = self.database.get_execution_history(HISTORY_DIALOG_LIMIT).await?;
+        self.ui.show_history_dialog(&records)?;
+        Ok(())
+    }
 }
\ No newline at end of file
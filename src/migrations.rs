@@ -0,0 +1,480 @@
+use anyhow::{bail, Context, Result};
+use rusqlite::{Connection, Transaction};
+use std::path::{Path, PathBuf};
+
+/// A single schema upgrade step. Migrations are applied in order, inside one
+/// transaction, and the resulting version is stored in `PRAGMA user_version`.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub apply: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+/// All schema migrations, oldest first. Append new entries; never edit or
+/// reorder existing ones, as deployed databases record how far they got.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Initial schema",
+        apply: initial_schema,
+    },
+    Migration {
+        version: 2,
+        description: "Privilege elevation (#MSUDO)",
+        apply: |tx| add_column(tx, "scripts", "elevation", "TEXT NOT NULL DEFAULT 'never'"),
+    },
+    Migration {
+        version: 3,
+        description: "Execution timeouts and resource limits",
+        apply: |tx| {
+            add_column(tx, "scripts", "timeout_secs", "INTEGER")?;
+            add_column(tx, "scripts", "resource_limits", "TEXT")
+        },
+    },
+    Migration {
+        version: 4,
+        description: "Structured execution outcomes",
+        apply: |tx| {
+            add_column(tx, "execution_history", "script_path", "TEXT")?;
+            add_column(tx, "execution_history", "status", "TEXT NOT NULL DEFAULT 'exited'")?;
+            add_column(tx, "execution_history", "signal", "INTEGER")?;
+            add_column(tx, "execution_history", "core_dumped", "BOOLEAN DEFAULT FALSE")?;
+            add_column(tx, "execution_history", "cancelled", "BOOLEAN DEFAULT FALSE")?;
+            add_column(tx, "execution_history", "started_at", "DATETIME")?;
+            add_column(tx, "execution_history", "finished_at", "DATETIME")?;
+            add_column(tx, "execution_history", "hostname", "TEXT")?;
+            add_column(tx, "execution_history", "username", "TEXT")
+        },
+    },
+    Migration {
+        version: 5,
+        description: "Full-text search index",
+        apply: full_text_index,
+    },
+    Migration {
+        version: 6,
+        description: "Script body search index",
+        apply: |tx| {
+            // Populated by the next scan; bodies are not stored in `scripts`
+            tx.execute_batch(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS script_bodies_fts USING fts5(
+                    body,
+                    prefix = '2 3',
+                    tokenize = 'unicode61 remove_diacritics 2'
+                );",
+            )
+        },
+    },
+    Migration {
+        version: 7,
+        description: "Batch runs",
+        apply: batch_runs,
+    },
+    Migration {
+        version: 8,
+        description: "Workflows",
+        apply: workflows,
+    },
+    Migration {
+        version: 9,
+        description: "Layered toolbox roots",
+        apply: |tx| {
+            add_column(tx, "scripts", "source", "TEXT")?;
+            add_column(tx, "scripts", "overrides", "TEXT")?;
+            add_column(tx, "workflows", "source", "TEXT")?;
+            add_column(tx, "workflows", "overrides", "TEXT")
+        },
+    },
+    Migration {
+        version: 10,
+        description: "Git revisions of scripts and runs",
+        apply: |tx| {
+            add_column(tx, "scripts", "revision", "TEXT")?;
+            add_column(tx, "execution_history", "script_revision", "TEXT")
+        },
+    },
+    Migration {
+        version: 11,
+        description: "Script checksums and file permissions",
+        apply: |tx| {
+            add_column(tx, "scripts", "checksum", "TEXT")?;
+            add_column(tx, "scripts", "permission_issues", "TEXT NOT NULL DEFAULT '[]'")
+        },
+    },
+    Migration {
+        version: 12,
+        description: "Script roles (#MROLE)",
+        apply: |tx| add_column(tx, "scripts", "roles", "TEXT NOT NULL DEFAULT '[]'"),
+    },
+];
+
+/// What `migrate` changed, for the caller to report.
+#[derive(Debug, Clone)]
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub backup_path: Option<PathBuf>,
+}
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Bring the database up to the latest schema version.
+///
+/// The database is backed up before anything is changed, and a database
+/// written by a newer binary is refused rather than risk corrupting it.
+/// Returns `None` when the schema was already current.
+pub fn migrate(conn: &mut Connection, db_path: &Path) -> Result<Option<MigrationReport>> {
+    let current: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let latest = latest_version();
+
+    if current > latest {
+        bail!(
+            "Database {} uses schema version {}, but this toolbox binary only supports up to version {}. \
+             Please upgrade toolbox or use a different --database.",
+            db_path.display(),
+            current,
+            latest
+        );
+    }
+
+    if current == latest {
+        return Ok(None);
+    }
+
+    let backup_path = if has_tables(conn)? {
+        Some(backup(conn, db_path, current)?)
+    } else {
+        None
+    };
+
+    let tx = conn.transaction()?;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        (migration.apply)(&tx).with_context(|| {
+            format!("Migration {} ({}) failed", migration.version, migration.description)
+        })?;
+    }
+    tx.pragma_update(None, "user_version", latest)?;
+    tx.commit()?;
+
+    Ok(Some(MigrationReport {
+        from_version: current,
+        to_version: latest,
+        backup_path,
+    }))
+}
+
+fn has_tables(conn: &Connection) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn backup(conn: &Connection, db_path: &Path, version: u32) -> Result<PathBuf> {
+    let file_name = db_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "menu.db".to_string());
+    let backup_path = db_path.with_file_name(format!(
+        "{}.v{}-{}.bak",
+        file_name,
+        version,
+        chrono::Local::now().format("%Y%m%d%H%M%S")
+    ));
+
+    // VACUUM INTO produces a consistent copy even if the journal is not checkpointed
+    conn.execute("VACUUM INTO ?1", [backup_path.to_string_lossy()])
+        .with_context(|| format!("Failed to back up database to {}", backup_path.display()))?;
+
+    Ok(backup_path)
+}
+
+/// Add a column unless it already exists. Databases created before
+/// migrations were introduced may already have some of these columns.
+fn add_column(tx: &Transaction, table: &str, column: &str, definition: &str) -> rusqlite::Result<()> {
+    let exists = {
+        let mut stmt = tx.prepare(&format!("PRAGMA table_info({})", table))?;
+        let mut names = stmt.query_map([], |row| row.get::<_, String>("name"))?;
+        names.any(|name| name.map(|name| name == column).unwrap_or(false))
+    };
+
+    if !exists {
+        tx.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    Ok(())
+}
+
+fn initial_schema(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS scripts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            path TEXT NOT NULL UNIQUE,
+            category TEXT NOT NULL,
+            menu_name TEXT,
+            description TEXT,
+            detailed_description TEXT,
+            integration TEXT,
+            info_url TEXT,
+            icon TEXT,
+            color TEXT,
+            order_num INTEGER,
+            is_default BOOLEAN DEFAULT FALSE,
+            separator TEXT,
+            tags TEXT, -- JSON array
+            author TEXT,
+            parameters TEXT, -- JSON object
+            dependency_available BOOLEAN DEFAULT TRUE,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+        [],
+    )?;
+
+    // Older menu.db files predate this column
+    add_column(tx, "scripts", "dependency_available", "BOOLEAN DEFAULT TRUE")?;
+
+    // Create indexes for better performance
+    tx.execute("CREATE INDEX IF NOT EXISTS idx_scripts_category ON scripts(category)", [])?;
+    tx.execute("CREATE INDEX IF NOT EXISTS idx_scripts_order ON scripts(order_num)", [])?;
+    tx.execute("CREATE INDEX IF NOT EXISTS idx_scripts_name ON scripts(name)", [])?;
+
+    // Create table for script execution history
+    tx.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS execution_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            script_id INTEGER NOT NULL,
+            executed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            exit_code INTEGER,
+            duration_ms INTEGER,
+            parameters TEXT, -- JSON object
+            FOREIGN KEY (script_id) REFERENCES scripts (id)
+        )
+        "#,
+        [],
+    )?;
+
+    // Create table for user preferences
+    tx.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS user_preferences (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn full_text_index(tx: &Transaction) -> rusqlite::Result<()> {
+    // External-content FTS5 table: the text lives in `scripts`, the index is
+    // kept in sync by the triggers below, including across full rescans
+    tx.execute_batch(
+        r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS scripts_fts USING fts5(
+            name, menu_name, description, detailed_description, tags, author, category,
+            content = 'scripts',
+            content_rowid = 'id',
+            prefix = '2 3',
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS scripts_fts_insert AFTER INSERT ON scripts BEGIN
+            INSERT INTO scripts_fts (rowid, name, menu_name, description, detailed_description, tags, author, category)
+            VALUES (new.id, new.name, new.menu_name, new.description, new.detailed_description, new.tags, new.author, new.category);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS scripts_fts_delete AFTER DELETE ON scripts BEGIN
+            INSERT INTO scripts_fts (scripts_fts, rowid, name, menu_name, description, detailed_description, tags, author, category)
+            VALUES ('delete', old.id, old.name, old.menu_name, old.description, old.detailed_description, old.tags, old.author, old.category);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS scripts_fts_update AFTER UPDATE ON scripts BEGIN
+            INSERT INTO scripts_fts (scripts_fts, rowid, name, menu_name, description, detailed_description, tags, author, category)
+            VALUES ('delete', old.id, old.name, old.menu_name, old.description, old.detailed_description, old.tags, old.author, old.category);
+            INSERT INTO scripts_fts (rowid, name, menu_name, description, detailed_description, tags, author, category)
+            VALUES (new.id, new.name, new.menu_name, new.description, new.detailed_description, new.tags, new.author, new.category);
+        END;
+
+        INSERT INTO scripts_fts (scripts_fts) VALUES ('rebuild');
+        "#,
+    )
+}
+
+fn batch_runs(tx: &Transaction) -> rusqlite::Result<()> {
+    // One row per batch; its scripts' runs point back at it from history
+    tx.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS batch_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            mode TEXT NOT NULL, -- sequential | parallel
+            failure_policy TEXT NOT NULL, -- stop | continue
+            total INTEGER NOT NULL,
+            succeeded INTEGER NOT NULL DEFAULT 0,
+            failed INTEGER NOT NULL DEFAULT 0,
+            skipped INTEGER NOT NULL DEFAULT 0,
+            started_at DATETIME NOT NULL,
+            finished_at DATETIME,
+            hostname TEXT,
+            username TEXT
+        )
+        "#,
+        [],
+    )?;
+
+    add_column(tx, "execution_history", "batch_id", "INTEGER REFERENCES batch_runs (id)")?;
+    tx.execute("CREATE INDEX IF NOT EXISTS idx_execution_history_batch ON execution_history(batch_id)", [])?;
+
+    Ok(())
+}
+
+fn workflows(tx: &Transaction) -> rusqlite::Result<()> {
+    // Indexed by the scan like scripts; the definition itself is read from
+    // the file when the workflow runs
+    tx.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS workflows (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            path TEXT UNIQUE NOT NULL,
+            category TEXT NOT NULL,
+            description TEXT,
+            icon TEXT,
+            order_num INTEGER,
+            steps TEXT NOT NULL, -- JSON array of step summaries
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+        [],
+    )?;
+    tx.execute("CREATE INDEX IF NOT EXISTS idx_workflows_category ON workflows(category)", [])?;
+
+    // A workflow run is recorded like a batch, with its steps' runs pointing at it
+    add_column(tx, "batch_runs", "workflow", "TEXT")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The tables of a `menu.db` written before migrations existed.
+    const BASELINE_SCHEMA: &str = r#"
+        CREATE TABLE scripts (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            path TEXT NOT NULL UNIQUE,
+            category TEXT NOT NULL,
+            menu_name TEXT,
+            description TEXT,
+            detailed_description TEXT,
+            integration TEXT,
+            info_url TEXT,
+            icon TEXT,
+            color TEXT,
+            order_num INTEGER,
+            is_default BOOLEAN DEFAULT FALSE,
+            separator TEXT,
+            tags TEXT,
+            author TEXT,
+            parameters TEXT,
+            dependency_available BOOLEAN DEFAULT TRUE,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        CREATE TABLE execution_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            script_id INTEGER NOT NULL,
+            executed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            exit_code INTEGER,
+            duration_ms INTEGER,
+            parameters TEXT,
+            FOREIGN KEY (script_id) REFERENCES scripts (id)
+        );
+        CREATE TABLE user_preferences (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        INSERT INTO scripts (name, path, category) VALUES ('ping', '/opt/toolbox/Network/ping.sh', 'Network');
+        INSERT INTO execution_history (script_id, exit_code, duration_ms) VALUES (1, 0, 12);
+    "#;
+
+    fn columns(conn: &Connection, table: &str) -> Vec<String> {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table)).unwrap();
+        let names = stmt.query_map([], |row| row.get::<_, String>("name")).unwrap();
+        names.map(Result::unwrap).collect()
+    }
+
+    fn user_version(conn: &Connection) -> u32 {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn baseline_database_is_backed_up_and_upgraded() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("menu.db");
+        let mut conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(BASELINE_SCHEMA).unwrap();
+
+        let report = migrate(&mut conn, &db_path).unwrap().unwrap();
+        assert_eq!((report.from_version, report.to_version), (0, latest_version()));
+        assert_eq!(user_version(&conn), latest_version());
+        for column in ["elevation", "timeout_secs", "source", "revision", "checksum", "roles"] {
+            assert!(columns(&conn, "scripts").iter().any(|name| name == column), "scripts.{}", column);
+        }
+        for column in ["status", "signal", "started_at", "batch_id", "script_revision"] {
+            assert!(columns(&conn, "execution_history").iter().any(|name| name == column), "execution_history.{}", column);
+        }
+        let status: String = conn.query_row("SELECT status FROM execution_history", [], |row| row.get(0)).unwrap();
+        assert_eq!(status, "exited");
+
+        // The backup is the database as it was, next to it
+        let backup_path = report.backup_path.unwrap();
+        assert_eq!(backup_path.parent(), Some(dir.path()));
+        let name = backup_path.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("menu.db.v0-") && name.ends_with(".bak"), "{}", name);
+        let backup = Connection::open(&backup_path).unwrap();
+        assert_eq!(user_version(&backup), 0);
+        assert!(!columns(&backup, "scripts").iter().any(|name| name == "elevation"));
+        let count: i64 = backup.query_row("SELECT COUNT(*) FROM scripts", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+
+        assert!(migrate(&mut conn, &db_path).unwrap().is_none());
+    }
+
+    #[test]
+    fn new_database_is_created_without_a_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("menu.db");
+        let mut conn = Connection::open(&db_path).unwrap();
+
+        let report = migrate(&mut conn, &db_path).unwrap().unwrap();
+        assert!(report.backup_path.is_none());
+        assert_eq!(user_version(&conn), latest_version());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn newer_schema_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("menu.db");
+        let mut conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(BASELINE_SCHEMA).unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1).unwrap();
+
+        let error = migrate(&mut conn, &db_path).unwrap_err().to_string();
+        assert!(error.contains(&format!("schema version {}", latest_version() + 1)), "{}", error);
+        assert_eq!(user_version(&conn), latest_version() + 1);
+        assert!(!columns(&conn, "scripts").iter().any(|name| name == "elevation"));
+    }
+}
@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
+use crate::search::SearchResult;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Script {
     pub id: Option<i64>,
@@ -18,13 +21,107 @@ pub struct Script {
     pub is_default: bool,                  // #MDEFAULT
     pub separator: Option<String>,         // #MSEPARATOR
     pub tags: Vec<String>,                 // #MTAGS
+    pub roles: Vec<String>,                // #MROLE
     pub author: Option<String>,            // #MAUTHOR
     pub parameters: Option<String>,        // JSON parameters block
+    pub elevation: Elevation,              // #MSUDO
+    pub timeout_secs: Option<u64>,         // #MTIMEOUT
+    pub limits: ResourceLimits,            // #MLIMITS
     pub dependency_available: bool,        // Whether MI dependency is available
+    pub source: Option<String>,            // Label of the toolbox root it was found in
+    pub overrides: Option<String>,         // Label of the lower root whose copy it replaces
+    pub revision: Option<String>,          // Commit it was indexed from, for git roots
+    pub checksum: Option<String>,          // SHA-256 of the file when it was scanned
+    pub permission_issues: Vec<String>,    // Why others than root could modify it, e.g. world-writable
+    #[serde(skip)]
+    pub body: Option<String>,              // Indexed body text (scan only, not loaded from the database)
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Elevation {
+    Required,
+    Optional,
+    #[default]
+    Never,
+}
+
+impl Elevation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Elevation::Required => "required",
+            Elevation::Optional => "optional",
+            Elevation::Never => "never",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "required" => Some(Elevation::Required),
+            "optional" => Some(Elevation::Optional),
+            "never" => Some(Elevation::Never),
+            _ => None,
+        }
+    }
+}
+
+/// rlimit-based limits applied to a script's process before it starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    pub cpu_secs: Option<u64>,
+    pub memory_bytes: Option<u64>,
+    pub open_files: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Parse a `#MLIMITS` value such as `cpu=60,mem=512M,nofile=256`.
+    /// Unknown keys and malformed values are ignored.
+    pub fn parse(value: &str) -> Self {
+        let mut limits = Self::default();
+
+        for entry in value.split([',', ' ']).filter(|s| !s.is_empty()) {
+            let Some((key, raw)) = entry.split_once('=') else {
+                continue;
+            };
+            let raw = raw.trim();
+
+            match key.trim().to_lowercase().as_str() {
+                "cpu" => limits.cpu_secs = raw.parse().ok(),
+                "mem" | "memory" => limits.memory_bytes = parse_byte_size(raw),
+                "nofile" | "files" => limits.open_files = raw.parse().ok(),
+                _ => {}
+            }
+        }
+
+        limits
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cpu_secs.is_none() && self.memory_bytes.is_none() && self.open_files.is_none()
+    }
+}
+
+pub fn parse_byte_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (number, multiplier) = match value.chars().last()?.to_ascii_uppercase() {
+        'K' => (&value[..value.len() - 1], 1024),
+        'M' => (&value[..value.len() - 1], 1024 * 1024),
+        'G' => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+    number.trim().parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/// A line in a script body that matched a `body:` search, with the lines
+/// around it. Line numbers are 1-based, as in an editor.
+#[derive(Debug, Clone)]
+pub struct BodyMatch {
+    pub line_number: usize,
+    pub context: Vec<(usize, String)>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScriptParameter {
     pub name: String,
@@ -37,8 +134,10 @@ pub struct ScriptParameter {
     pub validation: Option<ParameterValidation>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ParameterType {
+    #[default]
     Text,
     Number,
     Password,
@@ -53,6 +152,7 @@ pub enum ParameterType {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParameterOption {
     pub value: String,
+    #[serde(default)]
     pub label: String,
     pub description: Option<String>,
 }
@@ -67,13 +167,211 @@ pub struct ParameterValidation {
     pub max_value: Option<f64>,
 }
 
+impl ScriptParameter {
+    /// The range, length and pattern rules a value must meet, in words.
+    /// Allowed options are not included; they are listed with their labels.
+    pub fn constraints(&self) -> Vec<String> {
+        let mut constraints = Vec::new();
+        let Some(validation) = &self.validation else {
+            return constraints;
+        };
+
+        match (validation.min_value, validation.max_value) {
+            (Some(min), Some(max)) => constraints.push(format!("{}-{}", min, max)),
+            (Some(min), None) => constraints.push(format!("at least {}", min)),
+            (None, Some(max)) => constraints.push(format!("at most {}", max)),
+            (None, None) => {}
+        }
+        match (validation.min_length, validation.max_length) {
+            (Some(min), Some(max)) => constraints.push(format!("{}-{} characters", min, max)),
+            (Some(min), None) => constraints.push(format!("at least {} characters", min)),
+            (None, Some(max)) => constraints.push(format!("at most {} characters", max)),
+            (None, None) => {}
+        }
+        match (&validation.pattern_description, &validation.pattern) {
+            (Some(description), _) => constraints.push(format!("must {}", description)),
+            (None, Some(pattern)) => constraints.push(format!("matches {}", pattern)),
+            (None, None) => {}
+        }
+        constraints
+    }
+}
+
+/// Structured result of a script run, as recorded in `execution_history`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionOutcome {
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub core_dumped: bool,
+    pub timed_out: bool,
+    pub cancelled: bool,
+    /// Never started: refused by the access policy, the integrity checks
+    /// or sudo. There is no exit code then.
+    pub refused: bool,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    pub hostname: String,
+    pub user: String,
+}
+
+impl ExecutionOutcome {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0) && !self.timed_out && !self.cancelled
+    }
+
+    /// Short machine-readable status stored in the `status` column.
+    pub fn status(&self) -> &'static str {
+        if self.refused {
+            "refused"
+        } else if self.timed_out {
+            "timed_out"
+        } else if self.cancelled {
+            "cancelled"
+        } else if self.signal.is_some() {
+            "signalled"
+        } else {
+            "exited"
+        }
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        (self.finished_at - self.started_at).num_milliseconds().max(0) as u64
+    }
+
+    /// Human-readable summary, e.g. "exit 2" or "killed by SIGSEGV (core dumped)".
+    pub fn describe(&self) -> String {
+        let mut text = if self.refused {
+            "not started (refused)".to_string()
+        } else if self.timed_out {
+            "timed out".to_string()
+        } else if self.cancelled {
+            "cancelled by user".to_string()
+        } else if let Some(signal) = self.signal {
+            format!("killed by {}", crate::system::signal_name(signal))
+        } else if let Some(code) = self.exit_code {
+            format!("exit {}", code)
+        } else {
+            "unknown".to_string()
+        };
+
+        if self.core_dumped {
+            text.push_str(" (core dumped)");
+        }
+        text
+    }
+}
+
+/// A row of `execution_history` joined with the script it ran.
+#[derive(Debug, Clone)]
+pub struct ExecutionRecord {
+    pub script_name: String,
+    pub script_path: PathBuf,
+    pub outcome: ExecutionOutcome,
+    /// Commit of the script's git root that ran, if it came from one.
+    pub revision: Option<String>,
+    /// The batch or workflow run this run was part of, if any.
+    pub batch: Option<BatchRun>,
+}
+
+/// How often a script has been run, aggregated from `execution_history`.
+#[derive(Debug, Clone)]
+pub struct ScriptUsage {
+    pub script_path: PathBuf,
+    pub runs: usize,
+    pub succeeded: usize,
+    pub average_duration_ms: u64,
+    pub last_run: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ScriptUsage {
+    pub fn failed(&self) -> usize {
+        self.runs - self.succeeded
+    }
+
+    /// Percentage of runs that succeeded.
+    pub fn success_rate(&self) -> f64 {
+        if self.runs == 0 {
+            0.0
+        } else {
+            self.succeeded as f64 * 100.0 / self.runs as f64
+        }
+    }
+}
+
+/// A row of `batch_runs`: several scripts run together from the menu, or
+/// the steps of a workflow.
+#[derive(Debug, Clone)]
+pub struct BatchRun {
+    pub id: i64,
+    pub mode: String,
+    /// Name of the workflow, for workflow runs.
+    pub workflow: Option<String>,
+    pub failure_policy: String,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+impl BatchRun {
+    /// One-line summary, e.g. "batch #3, parallel, stop on error: 2 ok, 1 failed"
+    /// or "workflow Renew certificates #4, stop on error: 3 ok, 0 failed".
+    pub fn describe(&self) -> String {
+        let policy = if self.failure_policy == "stop" { "stop on error" } else { "continue on error" };
+        let mut text = match &self.workflow {
+            Some(workflow) => format!(
+                "workflow {} #{}, {}: {} ok, {} failed",
+                workflow, self.id, policy, self.succeeded, self.failed
+            ),
+            None => format!(
+                "batch #{}, {}, {}: {} ok, {} failed",
+                self.id, self.mode, policy, self.succeeded, self.failed
+            ),
+        };
+        if self.skipped > 0 {
+            text.push_str(&format!(", {} skipped", self.skipped));
+        }
+        text
+    }
+}
+
+/// A `*.workflow.toml` file found by the scan: a named sequence of
+/// toolbox scripts. See `workflow.rs` for the file format.
+#[derive(Debug, Clone)]
+pub struct Workflow {
+    pub name: String,
+    pub path: PathBuf,
+    pub category: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub order: Option<i32>,
+    /// One line per step, e.g. "renew: LinuxTools/renew_certs.sh".
+    pub steps: Vec<String>,
+    /// Label of the toolbox root it was found in.
+    pub source: Option<String>,
+    /// Label of the lower root whose workflow at the same path it replaces.
+    pub overrides: Option<String>,
+}
+
+impl Workflow {
+    pub fn display_icon(&self) -> &str {
+        self.icon.as_deref().unwrap_or("🔁")
+    }
+
+    pub fn display_description(&self) -> &str {
+        self.description.as_deref().unwrap_or("No description")
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MenuCategory {
     pub name: String,
+    #[allow(dead_code)]
     pub path: PathBuf,
     pub scripts: Vec<Script>,
+    pub workflows: Vec<Workflow>,
     pub subcategories: Vec<MenuCategory>,
     pub icon: String,
+    #[allow(dead_code)]
     pub order: i32,
 }
 
@@ -85,12 +383,27 @@ pub struct MenuState {
     pub search_mode: bool,
     pub search_query: String,
     pub filtered_items: Vec<MenuItem>,
+    pub can_elevate: bool,
+    /// Scripts shown to this user that the access policy does not let them run.
+    pub run_denied: HashSet<PathBuf>,
+    /// Label entries with the toolbox root they come from (set when there is more than one).
+    pub show_sources: bool,
+    pub status_message: Option<String>,
+    pub search_matches: HashMap<PathBuf, SearchResult>,
+    pub search_bodies: bool,
+    pub body_matches: HashMap<PathBuf, BodyMatch>,
+    pub suggestions: Vec<String>,
+    pub suggestion_index: Option<usize>,
+    /// Scripts marked for a batch run, in the order they were marked.
+    pub marked: Vec<Script>,
 }
 
 #[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum MenuItem {
     Category(MenuCategory),
     Script(Script),
+    Workflow(Workflow),
     Separator(String),
     Back,
     Home,
@@ -98,6 +411,12 @@ pub enum MenuItem {
     Exit,
 }
 
+impl MenuState {
+    pub fn is_marked(&self, script: &Script) -> bool {
+        self.marked.iter().any(|marked| marked.path == script.path)
+    }
+}
+
 impl Default for MenuState {
     fn default() -> Self {
         Self {
@@ -107,6 +426,16 @@ impl Default for MenuState {
             search_mode: false,
             search_query: String::new(),
             filtered_items: Vec::new(),
+            can_elevate: false,
+            run_denied: HashSet::new(),
+            show_sources: false,
+            status_message: None,
+            search_matches: HashMap::new(),
+            search_bodies: false,
+            body_matches: HashMap::new(),
+            suggestions: Vec::new(),
+            suggestion_index: None,
+            marked: Vec::new(),
         }
     }
 }
@@ -130,9 +459,19 @@ impl Script {
             is_default: false,
             separator: None,
             tags: Vec::new(),
+            roles: Vec::new(),
             author: None,
             parameters: None,
+            elevation: Elevation::Never,
+            timeout_secs: None,
+            limits: ResourceLimits::default(),
             dependency_available: true,
+            source: None,
+            overrides: None,
+            revision: None,
+            checksum: None,
+            permission_issues: Vec::new(),
+            body: None,
             created_at: now,
             updated_at: now,
         }
@@ -150,13 +489,22 @@ impl Script {
         self.description.as_deref().unwrap_or("No description")
     }
 
+    /// Z1 is the colour code reserved for dangerous operations.
+    pub fn is_dangerous(&self) -> bool {
+        self.color.as_deref() == Some("Z1")
+    }
+
+    pub fn requires_elevation(&self) -> bool {
+        self.elevation == Elevation::Required
+    }
+
     pub fn has_parameters(&self) -> bool {
         self.parameters.is_some() && !self.parameters.as_ref().unwrap().trim().is_empty()
     }
 
     pub fn parse_parameters(&self) -> Result<Vec<ScriptParameter>, serde_json::Error> {
         if let Some(params_json) = &self.parameters {
-            serde_json::from_str(params_json)
+            crate::params::parse_schema(params_json)
         } else {
             Ok(Vec::new())
         }
@@ -169,6 +517,7 @@ impl MenuCategory {
             name,
             path,
             scripts: Vec::new(),
+            workflows: Vec::new(),
             subcategories: Vec::new(),
             icon: "📁".to_string(),
             order: 999,
@@ -176,10 +525,50 @@ impl MenuCategory {
     }
 
     pub fn total_items(&self) -> usize {
-        self.scripts.len() + self.subcategories.len()
+        self.scripts.len() + self.workflows.len() + self.subcategories.len()
     }
 
+    #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
-        self.scripts.is_empty() && self.subcategories.is_empty()
+        self.scripts.is_empty() && self.workflows.is_empty() && self.subcategories.is_empty()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_sizes_take_a_binary_suffix() {
+        assert_eq!(parse_byte_size("512"), Some(512));
+        assert_eq!(parse_byte_size("4k"), Some(4 * 1024));
+        assert_eq!(parse_byte_size(" 512M "), Some(512 * 1024 * 1024));
+        assert_eq!(parse_byte_size("2 G"), Some(2 * 1024 * 1024 * 1024));
+    }
+
+    #[test]
+    fn byte_sizes_that_overflow_or_do_not_parse_are_rejected() {
+        assert_eq!(parse_byte_size(&u64::MAX.to_string()), Some(u64::MAX));
+        assert_eq!(parse_byte_size(&format!("{}K", u64::MAX / 1024 + 1)), None);
+        assert_eq!(parse_byte_size("18446744073709551616"), None);
+        assert_eq!(parse_byte_size("17179869184G"), None);
+        for invalid in ["", "M", "-1M", "1.5G", "12T", "ten"] {
+            assert_eq!(parse_byte_size(invalid), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn limits_keep_the_valid_entries() {
+        let limits = ResourceLimits::parse("cpu=60, MEM=512M nofile=256");
+        assert_eq!(limits.cpu_secs, Some(60));
+        assert_eq!(limits.memory_bytes, Some(512 * 1024 * 1024));
+        assert_eq!(limits.open_files, Some(256));
+    }
+
+    #[test]
+    fn invalid_limits_are_left_unset() {
+        let limits = ResourceLimits::parse("cpu=-1,mem=99999999999G,nofile=many,stack=8M,cpu");
+        assert!(limits.is_empty());
+        assert!(ResourceLimits::parse("").is_empty());
+    }
+}
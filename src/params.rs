@@ -0,0 +1,426 @@
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::Deserialize;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::glyphs::Glyphs;
+use crate::models::{ParameterOption, ParameterType, ParameterValidation, Script, ScriptParameter};
+
+/// Scripts receive their parameters as `TOOLBOX_PARAM_<NAME>` variables.
+pub const PARAM_ENV_PREFIX: &str = "TOOLBOX_PARAM_";
+/// Workflow steps are told where to write their output variables.
+pub const OUTPUT_ENV: &str = "TOOLBOX_OUTPUT";
+/// Stored in history instead of the value of a password parameter.
+const MASKED_VALUE: &str = "********";
+
+/// One entry of a `#JSON_PARAMS_START` block as written in the script:
+///
+/// ```json
+/// "port": { "type": "number", "label": "Port Number", "default": "22", "min": 1, "max": 65535 }
+/// ```
+#[derive(Deserialize)]
+struct ParameterSpec {
+    #[serde(rename = "type", default)]
+    param_type: ParameterType,
+    label: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    required: bool,
+    default: Option<serde_json::Value>,
+    options: Option<Vec<ParameterOption>>,
+    pattern: Option<String>,
+    pattern_description: Option<String>,
+    min: Option<f64>,
+    max: Option<f64>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+}
+
+impl ParameterSpec {
+    fn into_parameter(self, name: String) -> ScriptParameter {
+        let default_value = match self.default {
+            None | Some(serde_json::Value::Null) => None,
+            Some(serde_json::Value::String(value)) => Some(value),
+            Some(value) => Some(value.to_string()),
+        };
+
+        ScriptParameter {
+            label: self.label.unwrap_or_else(|| name.clone()),
+            name,
+            param_type: self.param_type,
+            description: self.description,
+            default_value,
+            required: self.required,
+            options: self.options,
+            validation: Some(ParameterValidation {
+                min_length: self.min_length,
+                max_length: self.max_length,
+                pattern: self.pattern,
+                pattern_description: self.pattern_description,
+                min_value: self.min,
+                max_value: self.max,
+            }),
+        }
+    }
+}
+
+/// The parameter block is an object keyed by parameter name. Parameters are
+/// asked for in the order the script lists them, so the object is read
+/// entry by entry rather than into a (sorted) map. Workflow files declare
+/// their parameters with the same schema, written as TOML tables.
+#[derive(Default)]
+pub struct Schema(Vec<ScriptParameter>);
+
+impl Schema {
+    pub fn into_parameters(self) -> Vec<ScriptParameter> {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for Schema {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct SchemaVisitor;
+
+        impl<'de> Visitor<'de> for SchemaVisitor {
+            type Value = Schema;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object of parameter definitions keyed by name")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<Schema, A::Error> {
+                let mut parameters = Vec::new();
+                while let Some((name, spec)) = map.next_entry::<String, ParameterSpec>()? {
+                    parameters.push(spec.into_parameter(name));
+                }
+                Ok(Schema(parameters))
+            }
+        }
+
+        deserializer.deserialize_map(SchemaVisitor)
+    }
+}
+
+pub fn parse_schema(json: &str) -> std::result::Result<Vec<ScriptParameter>, serde_json::Error> {
+    serde_json::from_str::<Schema>(json).map(|schema| schema.0)
+}
+
+/// Parameter values collected for one run, in declaration order.
+#[derive(Debug, Clone, Default)]
+pub struct ParameterValues {
+    values: Vec<(String, String)>,
+    /// Names of password parameters, masked when recorded in history.
+    secrets: Vec<String>,
+    /// File a workflow step writes its `NAME=value` outputs to.
+    output_file: Option<PathBuf>,
+}
+
+impl ParameterValues {
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(value_name, _)| value_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_secret(&self, name: &str) -> bool {
+        self.secrets.iter().any(|secret| secret == name)
+    }
+
+    /// Set a value, replacing any earlier one for the same name.
+    pub fn insert(&mut self, name: &str, value: String, secret: bool) {
+        match self.values.iter_mut().find(|(value_name, _)| value_name == name) {
+            Some(entry) => entry.1 = value,
+            None => self.values.push((name.to_string(), value)),
+        }
+        if secret && !self.is_secret(name) {
+            self.secrets.push(name.to_string());
+        }
+    }
+
+    pub fn set_output_file(&mut self, path: PathBuf) {
+        self.output_file = Some(path);
+    }
+
+    /// Environment variables that pass the values to the script.
+    pub fn env(&self) -> Vec<(String, String)> {
+        let mut env: Vec<(String, String)> = self
+            .values
+            .iter()
+            .map(|(name, value)| (env_name(name), value.clone()))
+            .collect();
+        if let Some(output_file) = &self.output_file {
+            env.push((OUTPUT_ENV.to_string(), output_file.to_string_lossy().to_string()));
+        }
+        env
+    }
+
+    /// `name=value` pairs for display, with passwords masked.
+    pub fn describe(&self) -> String {
+        self.masked()
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// JSON object for the `parameters` column of `execution_history`,
+    /// with passwords masked.
+    pub fn to_history_json(&self) -> Option<String> {
+        if self.values.is_empty() {
+            return None;
+        }
+        Some(serde_json::Value::Object(self.masked_object()).to_string())
+    }
+
+    /// The values as a JSON object, with passwords masked.
+    pub fn masked_object(&self) -> serde_json::Map<String, serde_json::Value> {
+        self.masked()
+            .into_iter()
+            .map(|(name, value)| (name, serde_json::Value::String(value)))
+            .collect()
+    }
+
+    fn masked(&self) -> Vec<(String, String)> {
+        self.values
+            .iter()
+            .map(|(name, value)| {
+                if self.is_secret(name) {
+                    (name.clone(), MASKED_VALUE.to_string())
+                } else {
+                    (name.clone(), value.clone())
+                }
+            })
+            .collect()
+    }
+}
+
+/// `backup_type` becomes `TOOLBOX_PARAM_BACKUP_TYPE`.
+pub fn env_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!("{}{}", PARAM_ENV_PREFIX, name)
+}
+
+/// Ask for a script's parameters on the terminal. Returns `None` when the
+/// user gives up (end of input), in which case the script must not run.
+pub fn collect(script: &Script, glyphs: &Glyphs) -> Result<Option<ParameterValues>> {
+    if !script.has_parameters() {
+        return Ok(Some(ParameterValues::default()));
+    }
+
+    let parameters = match script.parse_parameters() {
+        Ok(parameters) => parameters,
+        Err(e) => {
+            println!("{} Invalid parameter definitions in {}: {}", glyphs.failure, script.path.display(), e);
+            return Ok(None);
+        }
+    };
+
+    prompt_all(&format!("Parameters for {}", script.display_name()), &parameters, glyphs)
+}
+
+/// Like `collect`, but with some values given up front as `NAME=VALUE`
+/// pairs (`toolbox run SCRIPT --param ...`); only the rest are asked for.
+pub fn collect_with_presets(
+    script: &Script,
+    presets: &[(String, String)],
+    glyphs: &Glyphs,
+) -> Result<Option<ParameterValues>> {
+    let parameters = script
+        .parse_parameters()
+        .with_context(|| format!("Invalid parameter definitions in {}", script.path.display()))?;
+
+    let mut values = ParameterValues::default();
+    for (name, value) in presets {
+        let Some(parameter) = parameters.iter().find(|parameter| &parameter.name == name) else {
+            bail!("{} has no parameter '{}'", script.display_name(), name);
+        };
+        apply_preset(&mut values, parameter, value)?;
+    }
+
+    let unanswered: Vec<ScriptParameter> = parameters
+        .iter()
+        .filter(|parameter| values.get(&parameter.name).is_none())
+        .cloned()
+        .collect();
+    let heading = format!("Parameters for {}", script.display_name());
+    let Some(prompted) = prompt_all(&heading, &unanswered, glyphs)? else {
+        return Ok(None);
+    };
+
+    // Keep the script's declaration order
+    let mut ordered = ParameterValues::default();
+    for parameter in &parameters {
+        let name = &parameter.name;
+        let source = if values.get(name).is_some() { &values } else { &prompted };
+        ordered.insert(name, source.get(name).unwrap_or_default().to_string(), source.is_secret(name));
+    }
+    Ok(Some(ordered))
+}
+
+/// Check a value given up front and add it to `values`.
+pub fn apply_preset(values: &mut ParameterValues, parameter: &ScriptParameter, value: &str) -> Result<()> {
+    let value = normalize(parameter, value);
+    validate(parameter, &value).map_err(|e| anyhow!("{}: {}", parameter.name, e))?;
+    values.insert(&parameter.name, value, parameter.param_type == ParameterType::Password);
+    Ok(())
+}
+
+/// Ask for each of `parameters` in turn under `heading`. Returns `None` on
+/// end of input.
+pub fn prompt_all(heading: &str, parameters: &[ScriptParameter], glyphs: &Glyphs) -> Result<Option<ParameterValues>> {
+    let mut values = ParameterValues::default();
+    if parameters.is_empty() {
+        return Ok(Some(values));
+    }
+
+    println!("{} {}", glyphs.params, heading);
+    for parameter in parameters {
+        let Some(value) = prompt(parameter, glyphs)? else {
+            return Ok(None);
+        };
+        values.insert(&parameter.name, value, parameter.param_type == ParameterType::Password);
+    }
+    println!();
+
+    Ok(Some(values))
+}
+
+fn prompt(parameter: &ScriptParameter, glyphs: &Glyphs) -> Result<Option<String>> {
+    println!();
+    println!("  {}{}", parameter.label, if parameter.required { " *" } else { "" });
+    if let Some(description) = &parameter.description {
+        println!("  {}", description);
+    }
+    for (i, option) in parameter.options.iter().flatten().enumerate() {
+        if option.label.is_empty() {
+            println!("    {}) {}", i + 1, option.value);
+        } else {
+            println!("    {}) {} - {}", i + 1, option.value, option.label);
+        }
+    }
+
+    let is_password = parameter.param_type == ParameterType::Password;
+    loop {
+        let hint = match parameter.param_type {
+            ParameterType::Boolean | ParameterType::Checkbox => " (y/n)",
+            _ => "",
+        };
+        let default = match &parameter.default_value {
+            Some(default) if !is_password => format!(" [{}]", default),
+            _ => String::new(),
+        };
+        print!("  {}{}{}: ", glyphs.arrow, hint, default);
+        io::stdout().flush()?;
+
+        let input = if is_password {
+            console::Term::stdout().read_secure_line()?
+        } else {
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line)? == 0 {
+                println!();
+                return Ok(None); // End of input
+            }
+            line
+        };
+
+        let value = normalize(parameter, input.trim());
+        match validate(parameter, &value) {
+            Ok(()) => return Ok(Some(value)),
+            Err(message) => println!("  {} {}", glyphs.failure, message),
+        }
+    }
+}
+
+/// Fill in the default and turn shorthand answers into the values scripts
+/// expect: an option number into its value, `y`/`n` into `true`/`false`.
+pub fn normalize(parameter: &ScriptParameter, input: &str) -> String {
+    if input.is_empty() {
+        return parameter.default_value.clone().unwrap_or_default();
+    }
+
+    match parameter.param_type {
+        ParameterType::Boolean | ParameterType::Checkbox => match input.to_lowercase().as_str() {
+            "y" | "yes" | "true" | "1" | "on" => "true".to_string(),
+            "n" | "no" | "false" | "0" | "off" => "false".to_string(),
+            _ => input.to_string(),
+        },
+        _ => {
+            let options = parameter.options.as_deref().unwrap_or_default();
+            input
+                .parse::<usize>()
+                .ok()
+                .filter(|&number| number >= 1)
+                .and_then(|number| options.get(number - 1))
+                .map(|option| option.value.clone())
+                .unwrap_or_else(|| input.to_string())
+        }
+    }
+}
+
+pub fn validate(parameter: &ScriptParameter, value: &str) -> std::result::Result<(), String> {
+    if value.is_empty() {
+        return if parameter.required {
+            Err("A value is required".to_string())
+        } else {
+            Ok(())
+        };
+    }
+
+    match parameter.param_type {
+        ParameterType::Boolean | ParameterType::Checkbox if value != "true" && value != "false" => {
+            return Err("Answer y or n".to_string());
+        }
+        _ => {}
+    }
+
+    if let Some(options) = parameter.options.as_deref().filter(|options| !options.is_empty()) {
+        if !options.iter().any(|option| option.value == value) {
+            return Err(format!("Choose 1-{} or one of the listed values", options.len()));
+        }
+    }
+
+    let Some(validation) = &parameter.validation else {
+        return Ok(());
+    };
+
+    if parameter.param_type == ParameterType::Number {
+        let number: f64 = value.parse().map_err(|_| "Enter a number".to_string())?;
+        if let Some(min) = validation.min_value.filter(|&min| number < min) {
+            return Err(format!("Must be at least {}", min));
+        }
+        if let Some(max) = validation.max_value.filter(|&max| number > max) {
+            return Err(format!("Must be at most {}", max));
+        }
+    }
+
+    let length = value.chars().count();
+    if let Some(min_length) = validation.min_length.filter(|&min| length < min) {
+        return Err(format!("Must be at least {} characters", min_length));
+    }
+    if let Some(max_length) = validation.max_length.filter(|&max| length > max) {
+        return Err(format!("Must be at most {} characters", max_length));
+    }
+
+    // A broken pattern in a script should not make it impossible to run
+    if let Some(pattern) = validation.pattern.as_deref().and_then(|pattern| Regex::new(pattern).ok()) {
+        if !pattern.is_match(value) {
+            return Err(match &validation.pattern_description {
+                Some(description) => format!("Must {}", description),
+                None => format!("Must match {}", pattern),
+            });
+        }
+    }
+
+    Ok(())
+}
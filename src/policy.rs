@@ -0,0 +1,277 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::models::{Script, Workflow};
+use crate::system::{self, Identity};
+
+/// The access policy. It is kept outside the users' own configuration so
+/// that only root can change it.
+pub const POLICY_FILE: &str = "/etc/toolbox/policy.toml";
+
+/// What a user may do with a script, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    None,
+    See,
+    Run,
+}
+
+/// The policy file:
+///
+/// ```toml
+/// default = "none"              # for scripts no grant covers: none (default) | see | run
+///
+/// [roles]                       # named by #MROLE; an undefined role is the Unix group of that name
+/// netadmin = { users = ["alice"], groups = ["netops"] }
+///
+/// [[grant]]
+/// groups = ["helpdesk"]         # and/or users, roles; "*" is everyone
+/// categories = ["Network"]      # with their subcategories; "*" is every script
+/// tags = ["readonly"]
+/// scripts = ["Network/ping.sh"] # name or path below the toolbox root
+/// access = "run"                # see | run
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PolicyFile {
+    default: Option<Access>,
+    roles: BTreeMap<String, Members>,
+    grant: Vec<Grant>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Members {
+    users: Vec<String>,
+    groups: Vec<String>,
+}
+
+impl Members {
+    fn include(&self, identity: &Identity) -> bool {
+        includes(&self.users, &self.groups, identity)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Grant {
+    #[serde(default)]
+    users: Vec<String>,
+    #[serde(default)]
+    groups: Vec<String>,
+    #[serde(default)]
+    roles: Vec<String>,
+    #[serde(default)]
+    categories: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    scripts: Vec<String>,
+    access: Access,
+}
+
+impl Grant {
+    fn covers(&self, category: &str, name: &str, path: &Path, tags: &[String]) -> bool {
+        self.categories
+            .iter()
+            .any(|pattern| pattern == "*" || category == pattern || category.starts_with(&format!("{}::", pattern)))
+            || self.tags.iter().any(|pattern| tags.iter().any(|tag| tag.eq_ignore_ascii_case(pattern)))
+            || self.scripts.iter().any(|pattern| pattern == name || path.ends_with(pattern))
+    }
+}
+
+/// The access policy as it applies to the user running the menu. Without a
+/// policy file everyone may run everything, except `#MROLE` scripts, which
+/// only the members of their roles may run.
+#[derive(Debug, Clone)]
+pub struct Policy {
+    identity: Identity,
+    default: Access,
+    /// Only the grants given to this user.
+    grants: Vec<Grant>,
+    roles: BTreeMap<String, Members>,
+}
+
+impl Policy {
+    pub fn load() -> Result<Self> {
+        let identity = Identity::detect();
+        let path = Path::new(POLICY_FILE);
+        let file = if path.exists() {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read policy file: {}", path.display()))?;
+            toml::from_str(&content).with_context(|| format!("Invalid policy file: {}", path.display()))?
+        } else {
+            PolicyFile { default: Some(Access::Run), ..PolicyFile::default() }
+        };
+        Ok(Self::new(identity, file))
+    }
+
+    fn new(identity: Identity, file: PolicyFile) -> Self {
+        let grants = file
+            .grant
+            .into_iter()
+            .filter(|grant| {
+                includes(&grant.users, &grant.groups, &identity)
+                    || grant.roles.iter().any(|role| has_role(&file.roles, &identity, role))
+            })
+            .collect();
+
+        Self { identity, default: file.default.unwrap_or(Access::None), grants, roles: file.roles }
+    }
+
+    /// What this user may do with `script`. `#MROLE` only narrows what the
+    /// grants allow: when the script names roles, users in none of them may
+    /// at most see it.
+    pub fn access(&self, script: &Script) -> Access {
+        if self.identity.is_root() {
+            return Access::Run;
+        }
+
+        let granted = self.granted(&script.category, &script.name, &script.path, &script.tags);
+        if script.roles.is_empty() || script.roles.iter().any(|role| has_role(&self.roles, &self.identity, role)) {
+            granted
+        } else {
+            granted.min(Access::See)
+        }
+    }
+
+    /// Whether this user may see `workflow`. Its steps are checked as
+    /// scripts when it runs.
+    pub fn can_see_workflow(&self, workflow: &Workflow) -> bool {
+        self.identity.is_root() || self.granted(&workflow.category, &workflow.name, &workflow.path, &[]) >= Access::See
+    }
+
+    pub fn can_see(&self, script: &Script) -> bool {
+        self.access(script) >= Access::See
+    }
+
+    /// The user the policy applies to.
+    pub fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    pub fn can_run(&self, script: &Script) -> bool {
+        self.access(script) == Access::Run
+    }
+
+    /// Log a refused attempt to run `script` and return what to tell the user.
+    pub fn deny_run(&self, script: &Script) -> String {
+        system::syslog(
+            libc::LOG_WARNING,
+            &format!(
+                "denied run of {} for user {} (uid {})",
+                script.path.display(),
+                self.identity.user,
+                self.identity.uid
+            ),
+        );
+        format!("You are not permitted to run {}", script.display_name())
+    }
+
+    fn granted(&self, category: &str, name: &str, path: &Path, tags: &[String]) -> Access {
+        self.grants
+            .iter()
+            .filter(|grant| grant.covers(category, name, path, tags))
+            .map(|grant| grant.access)
+            .fold(self.default, Access::max)
+    }
+}
+
+fn includes(users: &[String], groups: &[String], identity: &Identity) -> bool {
+    users.iter().any(|user| user == "*" || *user == identity.user)
+        || groups.iter().any(|group| group == "*" || identity.groups.contains(group))
+}
+
+fn has_role(roles: &BTreeMap<String, Members>, identity: &Identity, role: &str) -> bool {
+    match roles.get(role) {
+        Some(members) => members.include(identity),
+        None => identity.groups.iter().any(|group| group == role),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const POLICY: &str = r#"
+        [roles]
+        netadmin = { users = ["alice"] }
+
+        [[grant]]
+        groups = ["helpdesk"]
+        categories = ["Network"]
+        access = "run"
+
+        [[grant]]
+        users = ["*"]
+        tags = ["readonly"]
+        access = "see"
+    "#;
+
+    fn policy(content: &str, user: &str, groups: &[&str]) -> Policy {
+        let identity = Identity {
+            uid: 1000,
+            user: user.to_string(),
+            groups: groups.iter().map(|group| group.to_string()).collect(),
+        };
+        Policy::new(identity, toml::from_str(content).unwrap())
+    }
+
+    fn script(path: &str, category: &str, roles: &[&str]) -> Script {
+        let mut script = Script::new("script".to_string(), PathBuf::from(path), category.to_string());
+        script.roles = roles.iter().map(|role| role.to_string()).collect();
+        script
+    }
+
+    #[test]
+    fn grants_apply_to_their_members_and_scripts() {
+        let ping = script("/opt/toolbox/Network/Tools/ping.sh", "Network::Tools", &[]);
+        assert_eq!(policy(POLICY, "bob", &["helpdesk"]).access(&ping), Access::Run);
+        assert_eq!(policy(POLICY, "carol", &["staff"]).access(&ping), Access::None);
+
+        let mut df = script("/opt/toolbox/Disk/df.sh", "Disk", &[]);
+        df.tags = vec!["ReadOnly".to_string()];
+        assert_eq!(policy(POLICY, "carol", &["staff"]).access(&df), Access::See);
+    }
+
+    #[test]
+    fn roles_never_widen_the_grants() {
+        let restart = script("/opt/toolbox/Network/restart.sh", "Network", &["netadmin"]);
+        let disk = script("/opt/toolbox/Disk/wipe.sh", "Disk", &["netadmin"]);
+        // alice is in the role but has no grant for either category
+        assert_eq!(policy(POLICY, "alice", &["staff"]).access(&restart), Access::None);
+        assert_eq!(policy(POLICY, "alice", &["helpdesk"]).access(&restart), Access::Run);
+        assert_eq!(policy(POLICY, "alice", &["helpdesk"]).access(&disk), Access::None);
+        // bob has the grant but not the role
+        assert_eq!(policy(POLICY, "bob", &["helpdesk"]).access(&restart), Access::See);
+    }
+
+    #[test]
+    fn undefined_roles_are_unix_groups() {
+        let backup = script("/opt/toolbox/Network/backup.sh", "Network", &["dba", "netadmin"]);
+        assert_eq!(policy(POLICY, "dave", &["helpdesk", "dba"]).access(&backup), Access::Run);
+        assert_eq!(policy(POLICY, "erin", &["helpdesk"]).access(&backup), Access::See);
+    }
+
+    #[test]
+    fn without_a_policy_only_roles_restrict() {
+        let open = PolicyFile { default: Some(Access::Run), ..PolicyFile::default() };
+        let identity = Identity { uid: 1000, user: "bob".to_string(), groups: vec!["dba".to_string()] };
+        let policy = Policy::new(identity, open);
+        assert_eq!(policy.access(&script("/opt/toolbox/a.sh", "TopLevel", &[])), Access::Run);
+        assert_eq!(policy.access(&script("/opt/toolbox/b.sh", "TopLevel", &["dba"])), Access::Run);
+        assert_eq!(policy.access(&script("/opt/toolbox/c.sh", "TopLevel", &["netadmin"])), Access::See);
+    }
+
+    #[test]
+    fn root_may_run_everything() {
+        let mut root = policy(POLICY, "root", &[]);
+        root.identity.uid = 0;
+        assert_eq!(root.access(&script("/opt/toolbox/Disk/wipe.sh", "Disk", &["netadmin"])), Access::Run);
+    }
+}
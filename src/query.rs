@@ -0,0 +1,311 @@
+/// Parsed form of the search box input.
+///
+/// Free text is matched against the full-text index; qualifiers such as
+/// `tag:backup`, `cat:LinuxTools`, `author:"Toolbox Team"`, `is:dangerous`
+/// or `has:params` narrow the results, and a leading `-` negates them.
+/// `body:"certbot renew"` matches against the indexed script bodies, and
+/// `source:team` keeps the scripts from one toolbox root.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pub text: String,
+    pub filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub negated: bool,
+    pub kind: FilterKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterKind {
+    Tag(String),
+    Category(String),
+    Author(String),
+    Source(String),
+    Is(ScriptFlag),
+    Has(ScriptFeature),
+    Body(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptFlag {
+    Dangerous,
+    Available,
+    Sudo,
+    Default,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptFeature {
+    Params,
+    Tags,
+    Info,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Self {
+        let mut query = SearchQuery::default();
+        let mut words = Vec::new();
+
+        for token in tokenize(input) {
+            match parse_filter(&token) {
+                Some(filter) => query.filters.push(filter),
+                None => words.push(token.trim_matches('"').to_string()),
+            }
+        }
+
+        query.text = words.join(" ");
+        query
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty() && self.filters.is_empty()
+    }
+}
+
+impl Filter {
+    /// Label shown on the filter chip, e.g. `-tag:experimental`.
+    pub fn label(&self) -> String {
+        let (qualifier, value) = match &self.kind {
+            FilterKind::Tag(value) => ("tag", value.clone()),
+            FilterKind::Category(value) => ("cat", value.clone()),
+            FilterKind::Author(value) => ("author", value.clone()),
+            FilterKind::Source(value) => ("source", value.clone()),
+            FilterKind::Is(flag) => ("is", flag.as_str().to_string()),
+            FilterKind::Has(feature) => ("has", feature.as_str().to_string()),
+            FilterKind::Body(value) => ("body", value.clone()),
+        };
+
+        let value = if value.contains(' ') {
+            format!("\"{}\"", value)
+        } else {
+            value
+        };
+        format!("{}{}:{}", if self.negated { "-" } else { "" }, qualifier, value)
+    }
+}
+
+impl ScriptFlag {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScriptFlag::Dangerous => "dangerous",
+            ScriptFlag::Available => "available",
+            ScriptFlag::Sudo => "sudo",
+            ScriptFlag::Default => "default",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "dangerous" => Some(ScriptFlag::Dangerous),
+            "available" => Some(ScriptFlag::Available),
+            "sudo" => Some(ScriptFlag::Sudo),
+            "default" => Some(ScriptFlag::Default),
+            _ => None,
+        }
+    }
+}
+
+impl ScriptFeature {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScriptFeature::Params => "params",
+            ScriptFeature::Tags => "tags",
+            ScriptFeature::Info => "info",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "params" | "parameters" => Some(ScriptFeature::Params),
+            "tags" => Some(ScriptFeature::Tags),
+            "info" => Some(ScriptFeature::Info),
+            _ => None,
+        }
+    }
+}
+
+/// Split the input into everything before the word being typed and that
+/// word itself. The word is empty when the input ends in whitespace.
+pub fn split_last_word(input: &str) -> (&str, &str) {
+    let mut in_quotes = false;
+    let mut start = 0;
+
+    for (i, c) in input.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c.is_whitespace() && !in_quotes => start = i + c.len_utf8(),
+            _ => {}
+        }
+    }
+
+    input.split_at(start)
+}
+
+/// Split on whitespace, keeping double-quoted sections (including a quoted
+/// qualifier value such as `author:"Toolbox Team"`) together.
+fn tokenize(input: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn parse_filter(token: &str) -> Option<Filter> {
+    let (negated, token) = match token.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, token),
+    };
+
+    let (qualifier, value) = token.split_once(':')?;
+    let value = value.trim_matches('"').trim();
+    if value.is_empty() {
+        return None;
+    }
+
+    let kind = match qualifier.to_lowercase().as_str() {
+        "tag" => FilterKind::Tag(value.to_string()),
+        "cat" | "category" => FilterKind::Category(value.to_string()),
+        "author" => FilterKind::Author(value.to_string()),
+        "source" => FilterKind::Source(value.to_string()),
+        "is" => FilterKind::Is(ScriptFlag::parse(&value.to_lowercase())?),
+        "has" => FilterKind::Has(ScriptFeature::parse(&value.to_lowercase())?),
+        "body" => FilterKind::Body(value.to_string()),
+        _ => return None,
+    };
+
+    Some(Filter { negated, kind })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(negated: bool, kind: FilterKind) -> Filter {
+        Filter { negated, kind }
+    }
+
+    #[test]
+    fn free_text_is_joined_with_single_spaces() {
+        let query = SearchQuery::parse("  renew   certs ");
+        assert_eq!(query.text, "renew certs");
+        assert!(query.filters.is_empty());
+    }
+
+    #[test]
+    fn qualifiers_become_filters() {
+        let query = SearchQuery::parse("backup tag:nightly cat:LinuxTools is:sudo has:parameters source:team");
+        assert_eq!(query.text, "backup");
+        assert_eq!(
+            query.filters,
+            vec![
+                filter(false, FilterKind::Tag("nightly".into())),
+                filter(false, FilterKind::Category("LinuxTools".into())),
+                filter(false, FilterKind::Is(ScriptFlag::Sudo)),
+                filter(false, FilterKind::Has(ScriptFeature::Params)),
+                filter(false, FilterKind::Source("team".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn leading_dash_negates() {
+        let query = SearchQuery::parse("-tag:experimental -is:dangerous");
+        assert_eq!(
+            query.filters,
+            vec![
+                filter(true, FilterKind::Tag("experimental".into())),
+                filter(true, FilterKind::Is(ScriptFlag::Dangerous)),
+            ]
+        );
+    }
+
+    #[test]
+    fn quoted_values_keep_their_spaces() {
+        let query = SearchQuery::parse(r#"author:"Toolbox Team" body:"certbot renew" "exact phrase""#);
+        assert_eq!(query.text, "exact phrase");
+        assert_eq!(
+            query.filters,
+            vec![
+                filter(false, FilterKind::Author("Toolbox Team".into())),
+                filter(false, FilterKind::Body("certbot renew".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn qualifiers_and_flags_are_case_insensitive() {
+        let query = SearchQuery::parse("Category:Net IS:Available HAS:Info");
+        assert_eq!(
+            query.filters,
+            vec![
+                filter(false, FilterKind::Category("Net".into())),
+                filter(false, FilterKind::Is(ScriptFlag::Available)),
+                filter(false, FilterKind::Has(ScriptFeature::Info)),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_or_empty_qualifiers_stay_text() {
+        let query = SearchQuery::parse("host:web1 is:fast tag: tag:\"\"");
+        assert!(query.filters.is_empty());
+        assert_eq!(query.text, "host:web1 is:fast tag: tag:");
+    }
+
+    #[test]
+    fn unterminated_quote_runs_to_the_end() {
+        let query = SearchQuery::parse(r#"author:"Toolbox Team tag:x"#);
+        assert_eq!(query.filters, vec![filter(false, FilterKind::Author("Toolbox Team tag:x".into()))]);
+    }
+
+    #[test]
+    fn labels_parse_back_to_the_same_filter() {
+        let filters = [
+            filter(true, FilterKind::Tag("experimental".into())),
+            filter(false, FilterKind::Author("Toolbox Team".into())),
+            filter(false, FilterKind::Has(ScriptFeature::Tags)),
+            filter(true, FilterKind::Is(ScriptFlag::Default)),
+            filter(false, FilterKind::Body("certbot renew".into())),
+        ];
+        for original in filters {
+            let query = SearchQuery::parse(&original.label());
+            assert_eq!(query.filters, vec![original.clone()], "label {}", original.label());
+            assert!(query.text.is_empty());
+        }
+    }
+
+    #[test]
+    fn is_empty_ignores_whitespace() {
+        assert!(SearchQuery::parse("   ").is_empty());
+        assert!(!SearchQuery::parse("tag:x").is_empty());
+    }
+
+    #[test]
+    fn split_last_word_respects_quotes() {
+        assert_eq!(split_last_word("backup ta"), ("backup ", "ta"));
+        assert_eq!(split_last_word("backup "), ("backup ", ""));
+        assert_eq!(split_last_word(r#"author:"Toolbox Te"#), ("", r#"author:"Toolbox Te"#));
+        assert_eq!(split_last_word("née cat:Li"), ("née ", "cat:Li"));
+    }
+}
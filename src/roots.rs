@@ -0,0 +1,218 @@
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+/// A directory of toolbox scripts, and the label the menu shows on the
+/// entries found in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Root {
+    pub label: String,
+    pub path: PathBuf,
+    /// Repository the directory is a managed checkout of, if any.
+    pub git: Option<GitSource>,
+}
+
+/// A git repository checked out into a root by `toolbox sources update`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitSource {
+    /// Anything `git clone` accepts, including the path of a local bare repository.
+    pub url: String,
+    /// Branch, tag or commit to check out; the remote's default branch if unset.
+    pub rev: Option<String>,
+}
+
+impl Root {
+    /// A root labelled `label`, or after its directory name.
+    pub fn new(label: Option<&str>, path: PathBuf) -> Self {
+        let label = match label {
+            Some(label) => label.trim().to_string(),
+            None => path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string()),
+        };
+        Self { label, path, git: None }
+    }
+
+    /// A root checked out from `git.url`. Without a label it is named after
+    /// the repository, and without a path it is checked out under the
+    /// user's cache directory.
+    pub fn from_git(label: Option<&str>, path: Option<PathBuf>, git: GitSource) -> Self {
+        let label = label.map(str::to_string).unwrap_or_else(|| {
+            let name = git.url.trim_end_matches('/').rsplit(['/', ':']).next().unwrap_or_default();
+            name.strip_suffix(".git").unwrap_or(name).to_string()
+        });
+        let path = path.unwrap_or_else(|| {
+            dirs::cache_dir()
+                .unwrap_or_else(|| crate::expand_tilde("~/.cache"))
+                .join("toolbox")
+                .join("sources")
+                .join(&label)
+        });
+        Self { label, path, git: Some(git) }
+    }
+
+    /// Parse a `--path` value: `PATH` or `LABEL=PATH`.
+    pub fn parse(spec: &str) -> Self {
+        match spec.split_once('=') {
+            Some((label, path)) if !label.is_empty() && !label.contains('/') => {
+                Self::new(Some(label), crate::expand_tilde(path))
+            }
+            _ => Self::new(None, crate::expand_tilde(spec)),
+        }
+    }
+}
+
+/// The toolbox roots in precedence order, lowest first. A script or
+/// workflow in a later root replaces the one at the same relative path in
+/// the earlier roots, so a team or a user can override a shared script
+/// without copying the whole tree.
+#[derive(Debug, Clone)]
+pub struct Roots {
+    roots: Vec<Root>,
+}
+
+impl Roots {
+    pub fn new(roots: Vec<Root>) -> Result<Self> {
+        if roots.is_empty() {
+            bail!("No toolbox roots configured");
+        }
+        for (index, root) in roots.iter().enumerate() {
+            if root.label.is_empty() {
+                bail!("Toolbox root {} has an empty label", root.path.display());
+            }
+            if roots[..index].iter().any(|other| other.label == root.label) {
+                bail!(
+                    "Toolbox root label '{}' is used more than once; give the roots labels (LABEL=PATH)",
+                    root.label
+                );
+            }
+        }
+        Ok(Self { roots })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Root> {
+        self.roots.iter()
+    }
+
+    /// Whether there is more than one root, so entries need their label.
+    pub fn is_layered(&self) -> bool {
+        self.roots.len() > 1
+    }
+
+    /// The lowest root, normally the system-wide toolbox. The legacy
+    /// index is kept there.
+    pub fn base(&self) -> &Root {
+        &self.roots[0]
+    }
+
+    pub fn find(&self, label: &str) -> Option<&Root> {
+        self.roots.iter().find(|root| root.label == label)
+    }
+
+    /// The root `path` lies in; the innermost one when roots are nested.
+    pub fn root_of(&self, path: &Path) -> Option<&Root> {
+        self.roots
+            .iter()
+            .filter(|root| path.starts_with(&root.path))
+            .max_by_key(|root| root.path.components().count())
+    }
+
+    /// `path` relative to its root, or unchanged if it is in none.
+    pub fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        match self.root_of(path) {
+            Some(root) => path.strip_prefix(&root.path).unwrap_or(path),
+            None => path,
+        }
+    }
+
+    /// The file a toolbox-relative path names: the one in the highest root
+    /// that has it, otherwise the path under the base root.
+    pub fn resolve(&self, relative: &Path) -> PathBuf {
+        self.roots
+            .iter()
+            .rev()
+            .map(|root| root.path.join(relative))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| self.base().path.join(relative))
+    }
+
+    /// The label of the root `path` lies in.
+    pub fn label_of(&self, path: &Path) -> Option<String> {
+        self.root_of(path).map(|root| root.label.clone())
+    }
+}
+
+/// An entry's root for display, e.g. `team (overrides system)`.
+pub fn describe_source(source: Option<&str>, overrides: Option<&str>) -> Option<String> {
+    let source = source?;
+    Some(match overrides {
+        Some(lower) => format!("{} (overrides {})", source, lower),
+        None => source.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specs_are_labelled_paths_or_plain_paths() {
+        let root = Root::parse("team=/srv/team");
+        assert_eq!((root.label.as_str(), root.path.as_path()), ("team", Path::new("/srv/team")));
+
+        // An `=` after a slash is part of the path
+        let root = Root::parse("/srv/a=b/toolbox");
+        assert_eq!((root.label.as_str(), root.path.as_path()), ("toolbox", Path::new("/srv/a=b/toolbox")));
+        let root = Root::parse("./x=y");
+        assert_eq!((root.label.as_str(), root.path.as_path()), ("x=y", Path::new("./x=y")));
+        let root = Root::parse("=/srv/team");
+        assert_eq!(root.path, Path::new("=/srv/team"));
+    }
+
+    #[test]
+    fn paths_belong_to_the_innermost_root() {
+        let roots = Roots::new(vec![
+            Root::new(Some("system"), PathBuf::from("/opt/toolbox")),
+            Root::new(Some("vendor"), PathBuf::from("/opt/toolbox/vendor")),
+        ])
+        .unwrap();
+        assert_eq!(roots.label_of(Path::new("/opt/toolbox/Disk/df.sh")).as_deref(), Some("system"));
+        assert_eq!(roots.label_of(Path::new("/opt/toolbox/vendor/Disk/df.sh")).as_deref(), Some("vendor"));
+        assert_eq!(roots.relative(Path::new("/opt/toolbox/vendor/Disk/df.sh")), Path::new("Disk/df.sh"));
+        assert!(roots.root_of(Path::new("/opt/toolboxes/df.sh")).is_none());
+        assert_eq!(roots.relative(Path::new("/tmp/df.sh")), Path::new("/tmp/df.sh"));
+    }
+
+    #[test]
+    fn resolve_prefers_the_highest_root_then_the_base() {
+        let dir = tempfile::tempdir().unwrap();
+        for root in ["system", "team", "personal"] {
+            std::fs::create_dir_all(dir.path().join(root).join("Disk")).unwrap();
+        }
+        std::fs::write(dir.path().join("system/Disk/df.sh"), "").unwrap();
+        std::fs::write(dir.path().join("team/Disk/df.sh"), "").unwrap();
+        std::fs::write(dir.path().join("system/Disk/du.sh"), "").unwrap();
+        let roots = Roots::new(
+            ["system", "team", "personal"]
+                .iter()
+                .map(|label| Root::new(Some(label), dir.path().join(label)))
+                .collect(),
+        )
+        .unwrap();
+
+        assert_eq!(roots.resolve(Path::new("Disk/df.sh")), dir.path().join("team/Disk/df.sh"));
+        assert_eq!(roots.resolve(Path::new("Disk/du.sh")), dir.path().join("system/Disk/du.sh"));
+        assert_eq!(roots.resolve(Path::new("Disk/missing.sh")), dir.path().join("system/Disk/missing.sh"));
+    }
+
+    #[test]
+    fn labels_must_be_unique_and_non_empty() {
+        assert!(Roots::new(Vec::new()).is_err());
+        assert!(Roots::new(vec![
+            Root::new(None, PathBuf::from("/opt/toolbox")),
+            Root::new(None, PathBuf::from("/srv/toolbox")),
+        ])
+        .is_err());
+        assert!(Roots::new(vec![Root::new(Some(" "), PathBuf::from("/opt/toolbox"))]).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+use std::fs;
+use std::path::Path;
+
+use crate::models::parse_byte_size;
+
+pub const SCAN_CONFIG_FILE: &str = "toolbox_scan_config.ini";
+
+/// Settings read from `toolbox_scan_config.ini` in the toolbox directory.
+/// A missing file or section leaves the defaults in place.
+#[derive(Debug, Clone, Default)]
+pub struct ScanConfig {
+    pub body_index: BodyIndexConfig,
+}
+
+/// `[BodyIndex]` section: whether script bodies are indexed for `body:`
+/// searches, and which files are too large to bother with.
+#[derive(Debug, Clone)]
+pub struct BodyIndexConfig {
+    pub enabled: bool,
+    pub max_file_size: u64,
+}
+
+impl Default for BodyIndexConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_file_size: 256 * 1024,
+        }
+    }
+}
+
+impl ScanConfig {
+    pub fn load(toolbox_path: &Path) -> Self {
+        let path = toolbox_path.join(SCAN_CONFIG_FILE);
+        match fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut config = Self::default();
+        let mut section = String::new();
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+
+            if section == "BodyIndex" {
+                match key.trim().to_lowercase().as_str() {
+                    "enabled" => config.body_index.enabled = value.eq_ignore_ascii_case("true"),
+                    "max_size" => {
+                        if let Some(size) = parse_byte_size(value) {
+                            config.body_index.max_file_size = size;
+                        } else {
+                            eprintln!("Warning: Invalid [BodyIndex] max_size in {}: {}", SCAN_CONFIG_FILE, value);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        config
+    }
+}
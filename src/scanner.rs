@@ -1,19 +1,39 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use regex::Regex;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-use crate::models::Script;
+use crate::integrity;
+use crate::models::{Elevation, ResourceLimits, Script, Workflow};
+use crate::roots::{Root, Roots};
+use crate::scan_config::ScanConfig;
+use crate::sources;
+use crate::workflow::{self, WORKFLOW_SUFFIX};
+
+/// Metadata headers are only recognised this close to the top of a script.
+const METADATA_HEADER_LINES: usize = 50;
+
+/// A root being scanned, with its settings and the commit checked out in
+/// it when it is a git root.
+struct Layer<'a> {
+    root: &'a Root,
+    config: ScanConfig,
+    revision: Option<String>,
+}
+
+fn is_script(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("sh")
+}
 
 pub struct ScriptScanner {
-    toolbox_path: PathBuf,
+    roots: Roots,
     metadata_patterns: HashMap<String, Regex>,
 }
 
 impl ScriptScanner {
-    pub fn new(toolbox_path: PathBuf) -> Self {
+    pub fn new(roots: Roots) -> Self {
         let mut metadata_patterns = HashMap::new();
         
         // Compile regex patterns for metadata extraction
@@ -28,64 +48,178 @@ impl ScriptScanner {
         metadata_patterns.insert("MDEFAULT".to_string(), Regex::new(r"^#MDEFAULT\s+(true|false)$").unwrap());
         metadata_patterns.insert("MSEPARATOR".to_string(), Regex::new(r"^#MSEPARATOR\s+(.+)$").unwrap());
         metadata_patterns.insert("MTAGS".to_string(), Regex::new(r"^#MTAGS\s+(.+)$").unwrap());
+        metadata_patterns.insert("MROLE".to_string(), Regex::new(r"^#MROLE\s+(.+)$").unwrap());
         metadata_patterns.insert("MAUTHOR".to_string(), Regex::new(r"^#MAUTHOR\s+(.+)$").unwrap());
+        metadata_patterns.insert("MSUDO".to_string(), Regex::new(r"^#MSUDO\s+(\S+)\s*$").unwrap());
+        metadata_patterns.insert("MTIMEOUT".to_string(), Regex::new(r"^#MTIMEOUT\s+(\d+)\s*([smh]?)$").unwrap());
+        metadata_patterns.insert("MLIMITS".to_string(), Regex::new(r"^#MLIMITS\s+(.+)$").unwrap());
 
         Self {
-            toolbox_path,
+            roots,
             metadata_patterns,
         }
     }
 
+    /// Scan every root, lowest first. A script at the same path relative
+    /// to its root as one in a lower root replaces it.
     pub async fn scan(&self) -> Result<Vec<Script>> {
-        let mut scripts = Vec::new();
+        // A missing personal or team root is not worth stopping for
+        for root in self.roots.iter().filter(|root| self.roots.is_layered() && !root.path.exists()) {
+            if root.git.is_some() {
+                eprintln!("Warning: Toolbox root '{}' has not been fetched yet - run toolbox sources update", root.label);
+            } else {
+                eprintln!("Warning: Toolbox root '{}' does not exist: {}", root.label, root.path.display());
+            }
+        }
 
-        if !self.toolbox_path.exists() {
-            return Err(anyhow::anyhow!(
-                "Toolbox directory does not exist: {}",
-                self.toolbox_path.display()
-            ));
+        let layers = self.layers()?;
+        let mut files = Vec::new();
+        for layer in &layers {
+            files.extend(self.files(layer.root, is_script).into_iter().map(|path| (layer, path)));
         }
+        let mut scripts = self.parse_layered(files).await;
 
-        for entry in WalkDir::new(&self.toolbox_path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-            
-            // Only process .sh files
-            if path.extension().and_then(|s| s.to_str()) != Some("sh") {
-                continue;
-            }
+        // Sort scripts by category and order
+        scripts.sort_by(|a, b| {
+            a.category.cmp(&b.category)
+                .then_with(|| a.order.unwrap_or(999).cmp(&b.order.unwrap_or(999)))
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.path.cmp(&b.path))
+        });
 
-            // Skip if not a file
-            if !path.is_file() {
-                continue;
+        Ok(scripts)
+    }
+
+    /// Rescan only the scripts at the given paths relative to the roots,
+    /// e.g. those a source update touched. Returns the scripts to index at
+    /// those paths, and every path in any root whose index entry they
+    /// replace (including copies a change uncovered or now overrides).
+    pub async fn scan_paths(&self, relative_paths: &[PathBuf]) -> Result<(Vec<Script>, Vec<PathBuf>)> {
+        let layers = self.layers()?;
+        let mut files = Vec::new();
+        let mut replaced = Vec::new();
+        for relative_path in relative_paths.iter().filter(|path| is_script(path)) {
+            for layer in &layers {
+                let path = layer.root.path.join(relative_path);
+                if path.is_file() && self.roots.root_of(&path) == Some(layer.root) {
+                    files.push((layer, path.clone()));
+                }
+                replaced.push(path);
             }
+        }
+        Ok((self.parse_layered(files).await, replaced))
+    }
 
-            match self.parse_script(path).await {
-                Ok(script) => scripts.push(script),
+    /// Parse `files`, given lowest root first, keeping the highest copy of
+    /// each path relative to its root.
+    async fn parse_layered(&self, files: Vec<(&Layer<'_>, PathBuf)>) -> Vec<Script> {
+        let mut layered: HashMap<PathBuf, Script> = HashMap::new();
+        for (layer, path) in files {
+            match self.parse_script(layer, &path).await {
+                Ok(mut script) => {
+                    let relative_path = path.strip_prefix(&layer.root.path).unwrap_or(&path).to_path_buf();
+                    if let Some(lower) = layered.get(&relative_path) {
+                        script.overrides = lower.source.clone();
+                    }
+                    layered.insert(relative_path, script);
+                }
                 Err(e) => {
                     eprintln!("Warning: Failed to parse {}: {}", path.display(), e);
                 }
             }
         }
+        layered.into_values().collect()
+    }
 
-        // Sort scripts by category and order
-        scripts.sort_by(|a, b| {
-            a.category.cmp(&b.category)
-                .then_with(|| a.order.unwrap_or(999).cmp(&b.order.unwrap_or(999)))
-                .then_with(|| a.name.cmp(&b.name))
-        });
+    /// Find the `*.workflow.toml` files in the roots, overriding like
+    /// scripts. A workflow is listed in the category of its directory,
+    /// like a script, unless the file names another.
+    pub async fn scan_workflows(&self) -> Result<Vec<Workflow>> {
+        let mut layered: HashMap<PathBuf, Workflow> = HashMap::new();
+
+        for layer in self.layers()? {
+            let root = layer.root;
+            let is_workflow = |path: &Path| {
+                path.file_name()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|name| name.ends_with(WORKFLOW_SUFFIX))
+            };
+            for path in self.files(root, is_workflow) {
+                let definition = match workflow::load(&path) {
+                    Ok(definition) => definition,
+                    Err(e) => {
+                        eprintln!("Warning: Failed to parse {}: {:#}", path.display(), e);
+                        continue;
+                    }
+                };
+
+                let relative_path = path.strip_prefix(&root.path).unwrap_or(&path).to_path_buf();
+                let overrides = layered.get(&relative_path).and_then(|lower| lower.source.clone());
+                let workflow = Workflow {
+                    category: definition
+                        .category
+                        .clone()
+                        .unwrap_or_else(|| self.determine_category(&relative_path)),
+                    steps: definition
+                        .steps
+                        .iter()
+                        .map(|step| format!("{}: {}", step.label(), step.script))
+                        .collect(),
+                    name: definition.name,
+                    path: path.clone(),
+                    description: definition.description,
+                    icon: definition.icon,
+                    order: definition.order,
+                    source: Some(root.label.clone()),
+                    overrides,
+                };
+                layered.insert(relative_path, workflow);
+            }
+        }
 
-        Ok(scripts)
+        let mut workflows: Vec<Workflow> = layered.into_values().collect();
+        workflows.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(workflows)
     }
 
-    async fn parse_script(&self, path: &Path) -> Result<Script> {
+    /// The roots that exist, lowest first, with their scan settings.
+    fn layers(&self) -> Result<Vec<Layer<'_>>> {
+        let layers: Vec<Layer> = self
+            .roots
+            .iter()
+            .filter(|root| root.path.exists())
+            .map(|root| Layer {
+                root,
+                config: ScanConfig::load(&root.path),
+                revision: root.git.as_ref().and_then(|_| sources::revision(&root.path)),
+            })
+            .collect();
+        if layers.is_empty() {
+            let paths: Vec<String> = self.roots.iter().map(|root| root.path.display().to_string()).collect();
+            bail!("Toolbox directory does not exist: {}", paths.join(", "));
+        }
+        Ok(layers)
+    }
+
+    /// Files under `root` accepted by `wanted`, leaving out those of
+    /// another root nested inside it.
+    fn files(&self, root: &Root, wanted: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+        WalkDir::new(&root.path)
+            .follow_links(true)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .map(|entry| entry.into_path())
+            .filter(|path| wanted(path) && path.is_file())
+            .filter(|path| self.roots.root_of(path) == Some(root))
+            .collect()
+    }
+
+    async fn parse_script(&self, layer: &Layer<'_>, path: &Path) -> Result<Script> {
+        let root = layer.root;
         let content = fs::read_to_string(path)
             .with_context(|| format!("Failed to read script: {}", path.display()))?;
 
-        let relative_path = path.strip_prefix(&self.toolbox_path)
+        let relative_path = path.strip_prefix(&root.path)
             .unwrap_or(path);
 
         let category = self.determine_category(relative_path);
@@ -95,6 +229,10 @@ impl ScriptScanner {
             .to_string();
 
         let mut script = Script::new(name, path.to_path_buf(), category);
+        script.source = Some(root.label.clone());
+        script.revision = layer.revision.clone();
+        script.checksum = Some(integrity::sha256(content.as_bytes()));
+        script.permission_issues = integrity::permission_issues(path);
 
         // Parse metadata from the script content
         self.extract_metadata(&content, &mut script)?;
@@ -107,9 +245,50 @@ impl ScriptScanner {
         // Check dependency availability
         script.dependency_available = self.check_dependency_available(&script);
 
+        if self.should_index_body(&layer.config, path, &content) {
+            script.body = Some(self.extract_body(&content));
+        }
+
         Ok(script)
     }
 
+    fn should_index_body(&self, config: &ScanConfig, path: &Path, content: &str) -> bool {
+        let body_index = &config.body_index;
+        if !body_index.enabled {
+            return false;
+        }
+
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(u64::MAX);
+        // A NUL byte means this is not really a text script
+        size <= body_index.max_file_size && !content.contains('\0')
+    }
+
+    /// The script text with the metadata header and JSON parameter block
+    /// blanked out. Lines are kept (empty) so line numbers match the file.
+    fn extract_body(&self, content: &str) -> String {
+        let mut in_json_block = false;
+
+        content
+            .lines()
+            .enumerate()
+            .map(|(index, line)| {
+                let trimmed = line.trim();
+                if trimmed.starts_with("#JSON_PARAMS_START") {
+                    in_json_block = true;
+                }
+                let excluded = in_json_block
+                    || (index < METADATA_HEADER_LINES
+                        && self.metadata_patterns.values().any(|pattern| pattern.is_match(trimmed)));
+                if trimmed.starts_with("#JSON_PARAMS_END") {
+                    in_json_block = false;
+                }
+
+                if excluded { "" } else { line }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     fn determine_category(&self, relative_path: &Path) -> String {
         if let Some(parent) = relative_path.parent() {
             if parent == Path::new("") {
@@ -123,7 +302,7 @@ impl ScriptScanner {
     }
 
     fn extract_metadata(&self, content: &str, script: &mut Script) -> Result<()> {
-        let lines: Vec<&str> = content.lines().take(50).collect(); // Only check first 50 lines
+        let lines: Vec<&str> = content.lines().take(METADATA_HEADER_LINES).collect(); // Only check the header
 
         for line in lines {
             let line = line.trim();
@@ -157,8 +336,40 @@ impl ScriptScanner {
                     .map(|s| s.trim().to_string())
                     .filter(|s| !s.is_empty())
                     .collect();
+            } else if let Some(captures) = self.metadata_patterns.get("MROLE").unwrap().captures(line) {
+                script.roles = captures[1]
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect();
             } else if let Some(captures) = self.metadata_patterns.get("MAUTHOR").unwrap().captures(line) {
                 script.author = Some(captures[1].trim().to_string());
+            } else if let Some(captures) = self.metadata_patterns.get("MSUDO").unwrap().captures(line) {
+                script.elevation = Elevation::parse(&captures[1]).unwrap_or_else(|| {
+                    eprintln!(
+                        "Warning: Invalid #MSUDO '{}' in {}: expected required, optional or never",
+                        &captures[1],
+                        script.path.display()
+                    );
+                    Elevation::default()
+                });
+            } else if let Some(captures) = self.metadata_patterns.get("MTIMEOUT").unwrap().captures(line) {
+                let multiplier = match &captures[2] {
+                    "m" => 60,
+                    "h" => 3600,
+                    _ => 1,
+                };
+                script.timeout_secs = captures[1].parse::<u64>().ok().and_then(|value| value.checked_mul(multiplier));
+                if script.timeout_secs.is_none() {
+                    eprintln!(
+                        "Warning: Invalid #MTIMEOUT '{}{}' in {}: too large",
+                        &captures[1],
+                        &captures[2],
+                        script.path.display()
+                    );
+                }
+            } else if let Some(captures) = self.metadata_patterns.get("MLIMITS").unwrap().captures(line) {
+                script.limits = ResourceLimits::parse(&captures[1]);
             }
         }
 
@@ -205,6 +416,7 @@ impl ScriptScanner {
         Ok(None)
     }
 
+    #[allow(dead_code)]
     pub fn detect_script_features(&self, content: &str) -> ScriptFeatures {
         let mut features = ScriptFeatures::default();
 
@@ -241,7 +453,7 @@ impl ScriptScanner {
         features
     }
 
-    fn check_dependency_available(&self, script: &Script) -> bool {
+    pub fn check_dependency_available(&self, script: &Script) -> bool {
         if let Some(integration) = &script.integration {
             // Skip certain categories that don't represent commands
             let skip_categories = ["ToolboxCore", "SystemUtilities", "LinuxTools", 
@@ -288,6 +500,7 @@ impl ScriptScanner {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Default)]
 pub struct ScriptFeatures {
     pub has_package_manager: bool,
@@ -296,4 +509,105 @@ pub struct ScriptFeatures {
     pub has_file_output: bool,
     pub has_conditional_flow: bool,
     pub is_dangerous: bool,
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scanner() -> ScriptScanner {
+        ScriptScanner::new(Roots::new(vec![Root::new(Some("system"), PathBuf::from("/opt/toolbox"))]).unwrap())
+    }
+
+    /// `system`, `team` and `personal` roots under `dir`, each with the
+    /// scripts given for it.
+    fn layered(dir: &Path, files: &[(&str, &str)]) -> ScriptScanner {
+        for (root, relative) in files {
+            let path = dir.join(root).join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, format!("#!/bin/bash\n#MD from {}\n", root)).unwrap();
+        }
+        let roots = ["system", "team", "personal"]
+            .iter()
+            .map(|label| Root::new(Some(label), dir.join(label)))
+            .collect();
+        ScriptScanner::new(Roots::new(roots).unwrap())
+    }
+
+    fn by_path(scripts: &[Script], dir: &Path, path: &str) -> Script {
+        scripts.iter().find(|script| script.path == dir.join(path)).unwrap().clone()
+    }
+
+    fn timeout(header: &str) -> Option<u64> {
+        let mut script = Script::new("script".to_string(), PathBuf::from("/opt/toolbox/script.sh"), "TopLevel".to_string());
+        scanner().extract_metadata(&format!("#!/bin/bash\n{}\n", header), &mut script).unwrap();
+        script.timeout_secs
+    }
+
+    #[test]
+    fn timeouts_take_a_unit_suffix() {
+        assert_eq!(timeout("#MTIMEOUT 90"), Some(90));
+        assert_eq!(timeout("#MTIMEOUT 90s"), Some(90));
+        assert_eq!(timeout("#MTIMEOUT 5m"), Some(300));
+        assert_eq!(timeout("#MTIMEOUT 2 h"), Some(7200));
+    }
+
+    #[test]
+    fn timeouts_that_overflow_or_do_not_parse_are_unset() {
+        assert_eq!(timeout(&format!("#MTIMEOUT {}", u64::MAX)), Some(u64::MAX));
+        assert_eq!(timeout(&format!("#MTIMEOUT {}m", u64::MAX / 60 + 1)), None);
+        assert_eq!(timeout(&format!("#MTIMEOUT {}h", u64::MAX / 3600 + 1)), None);
+        assert_eq!(timeout("#MTIMEOUT 18446744073709551616"), None);
+        assert_eq!(timeout("#MTIMEOUT 5d"), None);
+        assert_eq!(timeout("#MTIMEOUT -5"), None);
+    }
+
+    #[tokio::test]
+    async fn the_highest_root_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        let scanner = layered(
+            dir.path(),
+            &[
+                ("system", "Disk/df.sh"),
+                ("team", "Disk/df.sh"),
+                ("personal", "Disk/df.sh"),
+                ("system", "Disk/du.sh"),
+                ("team", "Net/ping.sh"),
+            ],
+        );
+
+        let scripts = scanner.scan().await.unwrap();
+        assert_eq!(scripts.len(), 3);
+        let df = by_path(&scripts, dir.path(), "personal/Disk/df.sh");
+        assert_eq!(df.source.as_deref(), Some("personal"));
+        assert_eq!(df.overrides.as_deref(), Some("team"));
+        assert_eq!(df.description.as_deref(), Some("from personal"));
+        let du = by_path(&scripts, dir.path(), "system/Disk/du.sh");
+        assert_eq!((du.source.as_deref(), du.overrides), (Some("system"), None));
+        let ping = by_path(&scripts, dir.path(), "team/Net/ping.sh");
+        assert_eq!((ping.source.as_deref(), ping.overrides, ping.category.as_str()), (Some("team"), None, "Net"));
+    }
+
+    #[tokio::test]
+    async fn rescanning_a_path_keeps_the_highest_copy_and_replaces_every_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let scanner = layered(dir.path(), &[("system", "Disk/df.sh"), ("team", "Disk/df.sh"), ("system", "Disk/du.sh")]);
+
+        let (scripts, replaced) = scanner
+            .scan_paths(&[PathBuf::from("Disk/df.sh"), PathBuf::from("README.md")])
+            .await
+            .unwrap();
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0].path, dir.path().join("team/Disk/df.sh"));
+        assert_eq!(scripts[0].overrides.as_deref(), Some("system"));
+        // `personal` was never created, so it has no entries to replace
+        assert_eq!(replaced, ["system", "team"].map(|root| dir.path().join(root).join("Disk/df.sh")));
+
+        // Once the override is gone the lower copy comes back
+        fs::remove_file(dir.path().join("team/Disk/df.sh")).unwrap();
+        let (scripts, _) = scanner.scan_paths(&[PathBuf::from("Disk/df.sh")]).await.unwrap();
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0].path, dir.path().join("system/Disk/df.sh"));
+        assert_eq!(scripts[0].overrides, None);
+    }
+}
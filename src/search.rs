@@ -1,7 +1,8 @@
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
 use std::collections::HashMap;
 
-use crate::models::Script;
+use crate::models::{BodyMatch, Script};
+use crate::query::{Filter, FilterKind, ScriptFeature, ScriptFlag};
 
 pub struct SearchEngine {
     matcher: SkimMatcherV2,
@@ -24,11 +25,28 @@ impl SearchEngine {
             .collect();
 
         // Sort by score (higher is better)
-        scored_scripts.sort_by(|a, b| b.1.cmp(&a.1));
+        scored_scripts.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
 
         scored_scripts.into_iter().map(|(script, _)| script).collect()
     }
 
+    /// Re-rank full-text candidates by fuzzy score. Unlike `fuzzy_search`,
+    /// candidates without a fuzzy match are kept, in their original (BM25)
+    /// order, since the full-text index already judged them relevant.
+    pub fn rank_candidates(&self, candidates: &[Script], query: &str) -> Vec<Script> {
+        let mut scored: Vec<(usize, &Script, i64)> = candidates
+            .iter()
+            .enumerate()
+            .map(|(position, script)| {
+                let score = self.calculate_script_score(script, query).unwrap_or(0);
+                (position, script, score)
+            })
+            .collect();
+
+        scored.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+        scored.into_iter().map(|(_, script, _)| script.clone()).collect()
+    }
+
     fn calculate_script_score(&self, script: &Script, query: &str) -> Option<i64> {
         let mut max_score = 0i64;
 
@@ -108,6 +126,77 @@ impl SearchEngine {
             .collect()
     }
 
+    /// Narrow `scripts` down to those satisfying every filter of a parsed query.
+    pub fn apply_filters(&self, scripts: &[Script], filters: &[Filter]) -> Vec<Script> {
+        let mut results = scripts.to_vec();
+
+        for filter in filters {
+            let matching = match &filter.kind {
+                FilterKind::Category(category) => self.search_by_category(&results, category),
+                FilterKind::Tag(tag) => self.search_by_tags(&results, std::slice::from_ref(tag)),
+                FilterKind::Author(author) => {
+                    let author = author.to_lowercase();
+                    results
+                        .iter()
+                        .filter(|script| {
+                            script
+                                .author
+                                .as_ref()
+                                .is_some_and(|a| a.to_lowercase().contains(&author))
+                        })
+                        .cloned()
+                        .collect()
+                }
+                FilterKind::Source(source) => results
+                    .iter()
+                    .filter(|script| script.source.as_deref().is_some_and(|s| s.eq_ignore_ascii_case(source)))
+                    .cloned()
+                    .collect(),
+                FilterKind::Is(flag) => results
+                    .iter()
+                    .filter(|script| Self::has_flag(script, *flag))
+                    .cloned()
+                    .collect(),
+                FilterKind::Has(feature) => results
+                    .iter()
+                    .filter(|script| Self::has_feature(script, *feature))
+                    .cloned()
+                    .collect(),
+                // Bodies are not loaded with scripts; the caller resolves
+                // body filters against the body index
+                FilterKind::Body(_) => continue,
+            };
+
+            results = if filter.negated {
+                results
+                    .into_iter()
+                    .filter(|script| !matching.iter().any(|m| m.path == script.path))
+                    .collect()
+            } else {
+                matching
+            };
+        }
+
+        results
+    }
+
+    fn has_flag(script: &Script, flag: ScriptFlag) -> bool {
+        match flag {
+            ScriptFlag::Dangerous => script.is_dangerous(),
+            ScriptFlag::Available => script.dependency_available,
+            ScriptFlag::Sudo => script.requires_elevation(),
+            ScriptFlag::Default => script.is_default,
+        }
+    }
+
+    fn has_feature(script: &Script, feature: ScriptFeature) -> bool {
+        match feature {
+            ScriptFeature::Params => script.has_parameters(),
+            ScriptFeature::Tags => !script.tags.is_empty(),
+            ScriptFeature::Info => script.info_url.is_some(),
+        }
+    }
+
     pub fn get_suggestions(&self, scripts: &[Script], partial_query: &str) -> Vec<String> {
         if partial_query.len() < 2 {
             return Vec::new();
@@ -162,6 +251,7 @@ impl SearchEngine {
 
 #[derive(Debug, Clone)]
 pub struct SearchResult {
+    #[allow(dead_code)]
     pub script: Script,
     pub score: i64,
     pub matched_fields: Vec<String>,
@@ -183,38 +273,56 @@ impl SearchEngine {
                 let mut total_score = 0i64;
 
                 // Check each field and collect matches
-                if let Some((score, indices)) = self.matcher.fuzzy_indices(&script.name, query) {
+                if let Some(score) = self.matcher.fuzzy_match(&script.name, query) {
                     total_score += score * 3;
                     result.matched_fields.push("name".to_string());
-                    let ranges: Vec<(usize, usize)> = indices.iter().map(|&i| (i, i + 1)).collect();
-                    result.highlight_ranges.insert("name".to_string(), ranges);
+                    result.highlight_ranges.insert("name".to_string(), self.highlight_matches(&script.name, query));
                 }
 
                 if let Some(menu_name) = &script.menu_name {
-                    if let Some((score, indices)) = self.matcher.fuzzy_indices(menu_name, query) {
+                    if let Some(score) = self.matcher.fuzzy_match(menu_name, query) {
                         total_score += score * 3;
                         result.matched_fields.push("menu_name".to_string());
-                        let ranges: Vec<(usize, usize)> = indices.iter().map(|&i| (i, i + 1)).collect();
-                        result.highlight_ranges.insert("menu_name".to_string(), ranges);
+                        result.highlight_ranges.insert("menu_name".to_string(), self.highlight_matches(menu_name, query));
                     }
                 }
 
                 if let Some(description) = &script.description {
-                    if let Some((score, indices)) = self.matcher.fuzzy_indices(description, query) {
+                    if let Some(score) = self.matcher.fuzzy_match(description, query) {
                         total_score += score * 2;
                         result.matched_fields.push("description".to_string());
-                        let ranges: Vec<(usize, usize)> = indices.iter().map(|&i| (i, i + 1)).collect();
-                        result.highlight_ranges.insert("description".to_string(), ranges);
+                        result.highlight_ranges.insert("description".to_string(), self.highlight_matches(description, query));
                     }
                 }
 
+                if let Some(details) = &script.detailed_description {
+                    if let Some(score) = self.matcher.fuzzy_match(details, query) {
+                        total_score += score;
+                        result.matched_fields.push("detailed_description".to_string());
+                        result.highlight_ranges.insert("detailed_description".to_string(), self.highlight_matches(details, query));
+                    }
+                }
+
+                if let Some(author) = &script.author {
+                    if let Some(score) = self.matcher.fuzzy_match(author, query) {
+                        total_score += score;
+                        result.matched_fields.push("author".to_string());
+                        result.highlight_ranges.insert("author".to_string(), self.highlight_matches(author, query));
+                    }
+                }
+
+                if let Some(score) = self.matcher.fuzzy_match(&script.category, query) {
+                    total_score += score;
+                    result.matched_fields.push("category".to_string());
+                    result.highlight_ranges.insert("category".to_string(), self.highlight_matches(&script.category, query));
+                }
+
                 // Check tags
                 for (i, tag) in script.tags.iter().enumerate() {
-                    if let Some((score, indices)) = self.matcher.fuzzy_indices(tag, query) {
+                    if let Some(score) = self.matcher.fuzzy_match(tag, query) {
                         total_score += score * 2;
                         result.matched_fields.push(format!("tag_{}", i));
-                        let ranges: Vec<(usize, usize)> = indices.iter().map(|&idx| (idx, idx + 1)).collect();
-                        result.highlight_ranges.insert(format!("tag_{}", i), ranges);
+                        result.highlight_ranges.insert(format!("tag_{}", i), self.highlight_matches(tag, query));
                     }
                 }
 
@@ -228,7 +336,111 @@ impl SearchEngine {
             .collect();
 
         // Sort by score
-        results.sort_by(|a, b| b.score.cmp(&a.score));
+        results.sort_by_key(|result| std::cmp::Reverse(result.score));
         results
     }
-}
\ No newline at end of file
+
+    /// Find the line of `body` that best matches `query` (the most query
+    /// words, first occurrence wins) and return it with one line of context
+    /// either side.
+    pub fn locate_body_match(&self, body: &str, query: &str) -> Option<BodyMatch> {
+        let words: Vec<String> = query
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        if words.is_empty() {
+            return None;
+        }
+
+        let lines: Vec<&str> = body.lines().collect();
+        let (index, _) = lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| {
+                let line = line.to_lowercase();
+                (index, words.iter().filter(|word| line.contains(word.as_str())).count())
+            })
+            .filter(|&(_, hits)| hits > 0)
+            .min_by_key(|&(index, hits)| (std::cmp::Reverse(hits), index))?;
+
+        let start = index.saturating_sub(1);
+        let end = (index + 2).min(lines.len());
+        let context = (start..end)
+            .map(|i| (i + 1, lines[i].trim_end().to_string()))
+            .collect();
+
+        Some(BodyMatch {
+            line_number: index + 1,
+            context,
+        })
+    }
+
+    /// Match details for one script, used to highlight a search result.
+    /// Each word of the query is matched on its own so multi-word queries
+    /// still highlight every field they hit.
+    pub fn match_details(&self, script: &Script, query: &str) -> SearchResult {
+        let mut details = SearchResult {
+            script: script.clone(),
+            score: 0,
+            matched_fields: Vec::new(),
+            highlight_ranges: HashMap::new(),
+        };
+
+        for word in query.split_whitespace() {
+            for result in self.advanced_search(std::slice::from_ref(script), word) {
+                details.score += result.score;
+                for field in result.matched_fields {
+                    if !details.matched_fields.contains(&field) {
+                        details.matched_fields.push(field);
+                    }
+                }
+                for (field, ranges) in result.highlight_ranges {
+                    details.highlight_ranges.entry(field).or_default().extend(ranges);
+                }
+            }
+        }
+
+        details
+    }
+
+    /// Completions for the word being typed. Qualified words complete their
+    /// value (`tag:ba` -> `tag:backup`); plain words use `get_suggestions`.
+    pub fn complete_word(&self, scripts: &[Script], word: &str) -> Vec<String> {
+        let (negation, rest) = match word.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", word),
+        };
+
+        let Some((qualifier, partial)) = rest.split_once(':') else {
+            return self.get_suggestions(scripts, word);
+        };
+        let partial = partial.trim_matches('"').to_lowercase();
+
+        let values: Vec<String> = match qualifier {
+            "tag" => scripts.iter().flat_map(|script| script.tags.iter().cloned()).collect(),
+            "cat" | "category" => scripts.iter().map(|script| script.category.clone()).collect(),
+            "author" => scripts.iter().filter_map(|script| script.author.clone()).collect(),
+            "source" => scripts.iter().filter_map(|script| script.source.clone()).collect(),
+            "is" => ["dangerous", "available", "sudo", "default"].iter().map(|s| s.to_string()).collect(),
+            "has" => ["params", "tags", "info"].iter().map(|s| s.to_string()).collect(),
+            _ => Vec::new(),
+        };
+
+        let mut completions: Vec<String> = values
+            .into_iter()
+            .filter(|value| value.to_lowercase().starts_with(&partial))
+            .map(|value| {
+                if value.contains(' ') {
+                    format!("{}{}:\"{}\"", negation, qualifier, value)
+                } else {
+                    format!("{}{}:{}", negation, qualifier, value)
+                }
+            })
+            .collect();
+        completions.sort();
+        completions.dedup();
+        completions.truncate(10);
+        completions
+    }
+}
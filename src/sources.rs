@@ -0,0 +1,144 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::roots::{GitSource, Root};
+use crate::workflow::WORKFLOW_SUFFIX;
+
+/// What `update` did to a git root: the revisions before and after, and
+/// the scripts and workflows that differ between them, relative to the root.
+#[derive(Debug, Clone)]
+pub struct Update {
+    /// `None` when the root was cloned by this update.
+    pub from: Option<String>,
+    pub to: String,
+    pub added: Vec<PathBuf>,
+    pub changed: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+impl Update {
+    pub fn is_unchanged(&self) -> bool {
+        self.from.as_deref() == Some(self.to.as_str())
+    }
+
+    /// Every path the update touched, for rescanning.
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        self.added.iter().chain(&self.changed).chain(&self.removed)
+    }
+}
+
+/// Fetch `git` into `root` (cloning it the first time) and check out the
+/// configured revision, detached, so the root stays pinned until the next
+/// update. Local changes in the checkout are refused rather than lost.
+pub fn update(root: &Root, git: &GitSource) -> Result<Update> {
+    let from = if root.path.join(".git").exists() {
+        if !run(&root.path, &["status", "--porcelain", "--untracked-files=no"])?.is_empty() {
+            bail!(
+                "{} has local changes; commit or discard them before updating '{}'",
+                root.path.display(),
+                root.label
+            );
+        }
+        run(&root.path, &["fetch", "--quiet", "--tags", "--force", "--prune", "origin"])?;
+        revision(&root.path)
+    } else {
+        if root.path.exists() && fs::read_dir(&root.path)?.next().is_some() {
+            bail!("{} exists and is not a git checkout; cannot clone '{}' into it", root.path.display(), git.url);
+        }
+        if let Some(parent) = root.path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let path = root.path.to_string_lossy();
+        run(Path::new("."), &["clone", "--quiet", "--no-checkout", "--", &git.url, &path])?;
+        None
+    };
+
+    let to = resolve(&root.path, git.rev.as_deref())?;
+    run(&root.path, &["-c", "advice.detachedHead=false", "checkout", "--quiet", "--force", "--detach", &to])?;
+
+    let mut update = Update { from, to, added: Vec::new(), changed: Vec::new(), removed: Vec::new() };
+    match &update.from {
+        Some(from) => {
+            // -z: paths are given as they are, not quoted, and may hold any byte but NUL
+            let diff = output(&root.path, &["diff", "-z", "--name-status", "--no-renames", from, &update.to])?;
+            let mut fields = diff.split('\0');
+            while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+                let path = PathBuf::from(path);
+                if !is_indexed(&path) {
+                    continue;
+                }
+                match status {
+                    "A" => update.added.push(path),
+                    "D" => update.removed.push(path),
+                    _ => update.changed.push(path),
+                }
+            }
+        }
+        None => {
+            let files = output(&root.path, &["ls-tree", "-z", "-r", "--name-only", &update.to])?;
+            update.added = files
+                .split('\0')
+                .filter(|file| !file.is_empty())
+                .map(PathBuf::from)
+                .filter(|path| is_indexed(path))
+                .collect();
+        }
+    }
+    Ok(update)
+}
+
+/// The commit checked out in `path`, if it is a git checkout.
+pub fn revision(path: &Path) -> Option<String> {
+    run(path, &["rev-parse", "--verify", "--quiet", "HEAD"]).ok().filter(|rev| !rev.is_empty())
+}
+
+/// Abbreviated form of a commit id, for display.
+pub fn short(revision: &str) -> &str {
+    &revision[..revision.len().min(10)]
+}
+
+/// The commit `rev` names after a fetch: a remote branch, a tag or a
+/// commit id, in that order; the remote's default branch when unset.
+fn resolve(path: &Path, rev: Option<&str>) -> Result<String> {
+    if let Some(rev) = rev.filter(|rev| rev.starts_with('-')) {
+        bail!("'{}' is not a branch, tag or commit", rev);
+    }
+    let candidates = match rev {
+        Some(rev) => vec![format!("refs/remotes/origin/{}", rev), format!("refs/tags/{}", rev), rev.to_string()],
+        None => vec!["refs/remotes/origin/HEAD".to_string()],
+    };
+    candidates
+        .iter()
+        .find_map(|candidate| {
+            run(path, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", candidate)])
+                .ok()
+                .filter(|commit| !commit.is_empty())
+        })
+        .ok_or_else(|| anyhow!("No branch, tag or commit '{}' in {}", rev.unwrap_or("HEAD"), path.display()))
+}
+
+fn is_indexed(path: &Path) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    path.extension().is_some_and(|extension| extension == "sh") || name.ends_with(WORKFLOW_SUFFIX)
+}
+
+/// Run git in `dir` and return its trimmed standard output.
+fn run(dir: &Path, args: &[&str]) -> Result<String> {
+    Ok(output(dir, args)?.trim().to_string())
+}
+
+/// Run git in `dir` and return its standard output as it is.
+fn output(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git; is it installed?")?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,338 @@
+use anyhow::{anyhow, Result};
+use std::ffi::CStr;
+use std::fs;
+use std::io;
+use std::process::{Command, Stdio};
+
+use crate::models::ResourceLimits;
+
+/// Privilege information about the user running the menu, detected once at startup.
+#[derive(Debug, Clone, Copy)]
+pub struct PrivilegeContext {
+    pub is_root: bool,
+    pub can_sudo: bool,
+}
+
+impl PrivilegeContext {
+    pub fn detect() -> Self {
+        let is_root = is_root();
+        Self {
+            is_root,
+            can_sudo: !is_root && can_sudo(),
+        }
+    }
+
+    /// Whether scripts marked `#MSUDO required` can be run by this user.
+    pub fn can_elevate(&self) -> bool {
+        self.is_root || self.can_sudo
+    }
+}
+
+pub fn is_root() -> bool {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Best-effort check whether the current user is allowed to use sudo.
+///
+/// A non-interactive `sudo -n -l` succeeds when credentials are cached or no
+/// password is needed. Otherwise sudo reports that a password is required,
+/// which still means the user is listed in sudoers.
+pub fn can_sudo() -> bool {
+    let output = match Command::new("sudo")
+        .args(["-n", "-l"])
+        .stdin(Stdio::null())
+        .output()
+    {
+        Ok(output) => output,
+        Err(_) => return false, // sudo is not installed
+    };
+
+    if output.status.success() {
+        return true;
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr).to_lowercase();
+    stderr.contains("password is required")
+}
+
+/// Validate (and cache) sudo credentials up front so the script is not
+/// interrupted by a password prompt halfway through its run.
+pub fn validate_sudo() -> Result<()> {
+    let status = Command::new("sudo").arg("-v").status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("sudo authentication failed"))
+    }
+}
+
+pub fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    // SAFETY: the buffer is valid for `buf.len()` bytes.
+    if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } == 0 {
+        let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+        return String::from_utf8_lossy(&buf[..len]).into_owned();
+    }
+    "unknown".to_string()
+}
+
+/// Name of the user running the menu, looked up from the real uid. When
+/// the menu itself runs as root through sudo, this is the user who ran
+/// sudo, which is what history should record.
+pub fn current_user() -> String {
+    if let Some((user, _)) = sudo_user() {
+        return user;
+    }
+    // SAFETY: getuid has no preconditions and cannot fail.
+    let uid = unsafe { libc::getuid() };
+    user_name(uid).unwrap_or_else(|| format!("uid:{}", uid))
+}
+
+/// The user who ran the menu through sudo, and their uid if sudo passed
+/// it on. `SUDO_USER` is only trusted when running as root, since nobody
+/// but root can set the environment of a root process.
+pub fn sudo_user() -> Option<(String, Option<u32>)> {
+    // SAFETY: geteuid has no preconditions and cannot fail.
+    if unsafe { libc::geteuid() } != 0 {
+        return None;
+    }
+    let user = std::env::var("SUDO_USER").ok().filter(|user| !user.is_empty())?;
+    let uid = std::env::var("SUDO_UID").ok().and_then(|uid| uid.parse().ok());
+    Some((user, uid))
+}
+
+/// The real user running the menu and the groups their process is in,
+/// looked up from the uid rather than the environment so that access
+/// checks cannot be talked around with `USER=`.
+#[derive(Debug, Clone)]
+pub struct Identity {
+    pub uid: u32,
+    pub user: String,
+    pub groups: Vec<String>,
+}
+
+impl Identity {
+    pub fn detect() -> Self {
+        // SAFETY: getuid and getgid have no preconditions and cannot fail.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let user = user_name(uid).unwrap_or_else(|| format!("uid:{}", uid));
+
+        let mut gids = vec![gid];
+        gids.extend(supplementary_groups().into_iter().filter(|&other| other != gid));
+        let groups = gids.into_iter().filter_map(group_name).collect();
+
+        Self { uid, user, groups }
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.uid == 0
+    }
+}
+
+fn user_name(uid: libc::uid_t) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; 4096];
+    // SAFETY: passwd is plain data that getpwuid_r fills in.
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+    // SAFETY: every pointer is valid, and the buffer for `buf.len()` bytes.
+    let rc = unsafe { libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+    // SAFETY: on success pw_name points to a NUL-terminated string in `buf`.
+    Some(unsafe { CStr::from_ptr(passwd.pw_name) }.to_string_lossy().into_owned())
+}
+
+fn group_name(gid: libc::gid_t) -> Option<String> {
+    let mut buf = vec![0 as libc::c_char; 16384];
+    // SAFETY: group is plain data that getgrgid_r fills in.
+    let mut group: libc::group = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+    // SAFETY: every pointer is valid, and the buffer for `buf.len()` bytes.
+    let rc = unsafe { libc::getgrgid_r(gid, &mut group, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+    // SAFETY: on success gr_name points to a NUL-terminated string in `buf`.
+    Some(unsafe { CStr::from_ptr(group.gr_name) }.to_string_lossy().into_owned())
+}
+
+fn supplementary_groups() -> Vec<libc::gid_t> {
+    // SAFETY: a zero size only asks for the count.
+    let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+    if count <= 0 {
+        return Vec::new();
+    }
+    let mut gids = vec![0; count as usize];
+    // SAFETY: the buffer holds `count` gids.
+    let count = unsafe { libc::getgroups(count, gids.as_mut_ptr()) };
+    gids.truncate(count.max(0) as usize);
+    gids
+}
+
+/// Write `message` to the system log's authpriv facility, where access
+/// decisions and the audit trail are expected to be found.
+pub fn syslog(priority: libc::c_int, message: &str) {
+    let Ok(message) = std::ffi::CString::new(message) else {
+        return;
+    };
+    // SAFETY: the ident and format are static C strings and `message` is
+    // NUL-terminated; openlog keeps the ident pointer, which stays valid.
+    unsafe {
+        libc::openlog(c"toolbox".as_ptr(), libc::LOG_PID, libc::LOG_AUTHPRIV);
+        libc::syslog(priority, c"%s".as_ptr(), message.as_ptr());
+    }
+}
+
+pub fn signal_name(signal: i32) -> String {
+    let name = match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        _ => return format!("signal {}", signal),
+    };
+    name.to_string()
+}
+
+/// Apply `#MLIMITS` to the current process. Called between fork and exec.
+pub fn apply_resource_limits(limits: &ResourceLimits) -> io::Result<()> {
+    let set = |resource, value: u64| -> io::Result<()> {
+        let limit = libc::rlimit {
+            rlim_cur: value as libc::rlim_t,
+            rlim_max: value as libc::rlim_t,
+        };
+        // SAFETY: `limit` is a valid rlimit struct for the duration of the call.
+        if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    };
+
+    if let Some(cpu_secs) = limits.cpu_secs {
+        set(libc::RLIMIT_CPU, cpu_secs)?;
+    }
+    if let Some(memory_bytes) = limits.memory_bytes {
+        set(libc::RLIMIT_AS, memory_bytes)?;
+    }
+    if let Some(open_files) = limits.open_files {
+        set(libc::RLIMIT_NOFILE, open_files)?;
+    }
+
+    Ok(())
+}
+
+/// Send a signal to a script started in its own process group `pid`, and
+/// to any descendant that has left that group (sudo with `use_pty` runs
+/// the command in a session of its own).
+///
+/// The processes of an elevated script belong to root, so they can only be
+/// reached through `sudo kill`. sudo itself can always be signalled, and
+/// relays what it receives to the command when `sudo kill` is not allowed.
+pub fn signal_script(pid: u32, signal: i32, elevated: bool) {
+    let descendants = descendants(pid);
+
+    // SAFETY: kill has no memory-safety preconditions.
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), signal);
+        for descendant in &descendants {
+            libc::kill(*descendant as libc::pid_t, signal);
+        }
+    }
+
+    if elevated {
+        // -n: never prompt; the credentials were validated before the run
+        let _ = Command::new("sudo")
+            .args(["-n", "kill", "-s", &signal.to_string(), "--", &format!("-{}", pid)])
+            .args(descendants.iter().map(u32::to_string))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+}
+
+/// Whether our process group is the foreground group of the terminal on
+/// stdin, so that a script can be handed the terminal.
+pub fn owns_terminal() -> bool {
+    // SAFETY: isatty, tcgetpgrp and getpgrp have no memory-safety preconditions.
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 && libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp() }
+}
+
+/// Make the calling process's group the foreground group of the terminal
+/// on stdin, if stdin is one, so that a script in its own process group can
+/// still read the terminal and gets Ctrl+C. Runs in the child between fork
+/// and exec, so it only makes async-signal-safe calls.
+pub fn take_terminal() {
+    // SAFETY: getpgrp has no preconditions and cannot fail.
+    hand_terminal(unsafe { libc::getpgrp() });
+}
+
+/// Take the terminal back from the script group `pid` once it has exited.
+pub fn restore_terminal(pid: u32) {
+    // SAFETY: tcgetpgrp has no memory-safety preconditions.
+    if unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) } == pid as libc::pid_t {
+        // SAFETY: as above.
+        hand_terminal(unsafe { libc::getpgrp() });
+    }
+}
+
+/// A process group outside the foreground is sent SIGTTOU for changing the
+/// foreground group, so the signal is blocked meanwhile.
+fn hand_terminal(group: libc::pid_t) {
+    // SAFETY: the signal sets are initialised by sigemptyset before use and
+    // outlive the calls; isatty and tcsetpgrp have no memory-safety preconditions.
+    unsafe {
+        if libc::isatty(libc::STDIN_FILENO) != 1 {
+            return;
+        }
+        let mut block: libc::sigset_t = std::mem::zeroed();
+        let mut previous: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut block);
+        libc::sigaddset(&mut block, libc::SIGTTOU);
+        libc::pthread_sigmask(libc::SIG_BLOCK, &block, &mut previous);
+        libc::tcsetpgrp(libc::STDIN_FILENO, group);
+        libc::pthread_sigmask(libc::SIG_SETMASK, &previous, std::ptr::null_mut());
+    }
+}
+
+/// All descendants of `pid`, found by walking /proc.
+fn descendants(pid: u32) -> Vec<u32> {
+    let mut found = Vec::new();
+    for child in child_pids(pid) {
+        found.push(child);
+        found.extend(descendants(child));
+    }
+    found
+}
+
+fn child_pids(parent: u32) -> Vec<u32> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| parent_pid(*pid) == Some(parent))
+        .collect()
+}
+
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name may contain spaces, so parse after its closing paren:
+    // "<pid> (<comm>) <state> <ppid> ..."
+    let rest = &stat[stat.rfind(')')? + 1..];
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
@@ -0,0 +1,275 @@
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+/// A small Mustache-style template, rendered against a JSON value:
+///
+/// - `{{name}}` and `{{name.field}}` insert a value (`{{.}}` is the current
+///   item, `{{list.0}}` the first element of a list);
+/// - `{{#name}}...{{/name}}` repeats for each element of a list, renders
+///   once with an object as the current item, or once if the value is true;
+/// - `{{^name}}...{{/name}}` renders when the value is missing, false,
+///   empty or an empty list;
+/// - `{{! comment }}` is dropped.
+///
+/// Values are inserted as they are, since the output is Markdown rather
+/// than HTML. A line holding nothing but a section or comment tag is
+/// removed entirely, so templates can put tags on their own lines.
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+enum Node {
+    Text(String),
+    Variable(String),
+    Section { name: String, inverted: bool, nodes: Vec<Node> },
+}
+
+enum Tag<'a> {
+    Variable(&'a str),
+    Open(&'a str, bool),
+    Close(&'a str),
+    Comment,
+}
+
+impl Template {
+    /// Parse `source`; `name` identifies the template in error messages.
+    pub fn parse(name: &str, source: &str) -> Result<Self> {
+        // Each open section: its name, whether inverted, and the nodes collected before it
+        let mut stack: Vec<(String, bool, Vec<Node>)> = Vec::new();
+        let mut nodes = Vec::new();
+        let mut rest = source;
+        let mut rest_at_line_start = true;
+
+        while let Some(start) = rest.find("{{") {
+            let Some(length) = rest[start..].find("}}") else {
+                bail!("{}: unclosed '{{{{'", name);
+            };
+            let end = start + length + 2;
+            let tag = parse_tag(rest[start + 2..end - 2].trim());
+
+            // Standalone tags take their whole line with them
+            let (mut text_end, mut resume) = (start, end);
+            if !matches!(tag, Tag::Variable(_)) {
+                let line_start = rest[..start].rfind('\n').map_or(0, |index| index + 1);
+                let line_end = rest[end..].find('\n').map(|index| end + index + 1);
+                let at_line_start = line_start > 0 || rest_at_line_start;
+                let blank_before = rest[line_start..start].trim().is_empty();
+                let blank_after = rest[end..line_end.unwrap_or(rest.len())].trim().is_empty();
+                if at_line_start && blank_before && blank_after {
+                    text_end = line_start;
+                    resume = line_end.unwrap_or(rest.len());
+                }
+            }
+
+            if text_end > 0 {
+                nodes.push(Node::Text(rest[..text_end].to_string()));
+            }
+            match tag {
+                Tag::Variable(variable) => nodes.push(Node::Variable(variable.to_string())),
+                Tag::Open(section, inverted) => {
+                    stack.push((section.to_string(), inverted, std::mem::take(&mut nodes)));
+                }
+                Tag::Close(section) => {
+                    let Some((open, inverted, outer)) = stack.pop() else {
+                        bail!("{}: '{{{{/{}}}}}' without a matching opening tag", name, section);
+                    };
+                    if open != section {
+                        bail!("{}: '{{{{/{}}}}}' closes '{}'", name, section, open);
+                    }
+                    let inner = std::mem::replace(&mut nodes, outer);
+                    nodes.push(Node::Section { name: open, inverted, nodes: inner });
+                }
+                Tag::Comment => {}
+            }
+            rest_at_line_start = rest[..resume].ends_with('\n');
+            rest = &rest[resume..];
+        }
+
+        if let Some((open, _, _)) = stack.pop() {
+            bail!("{}: section '{}' is never closed", name, open);
+        }
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+        Ok(Self { nodes })
+    }
+
+    pub fn render(&self, data: &Value) -> String {
+        let mut output = String::new();
+        render_nodes(&self.nodes, &mut vec![data], &mut output);
+        output
+    }
+}
+
+fn parse_tag(tag: &str) -> Tag<'_> {
+    if let Some(name) = tag.strip_prefix('#') {
+        Tag::Open(name.trim(), false)
+    } else if let Some(name) = tag.strip_prefix('^') {
+        Tag::Open(name.trim(), true)
+    } else if let Some(name) = tag.strip_prefix('/') {
+        Tag::Close(name.trim())
+    } else if tag.starts_with('!') {
+        Tag::Comment
+    } else {
+        Tag::Variable(tag)
+    }
+}
+
+fn render_nodes(nodes: &[Node], context: &mut Vec<&Value>, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Variable(name) => match lookup(context, name) {
+                Some(Value::String(value)) => output.push_str(value),
+                Some(Value::Null) | None => {}
+                Some(value) => output.push_str(&value.to_string()),
+            },
+            Node::Section { name, inverted, nodes } => {
+                let value = lookup(context, name);
+                if *inverted {
+                    if !value.is_some_and(is_truthy) {
+                        render_nodes(nodes, context, output);
+                    }
+                    continue;
+                }
+
+                match value {
+                    Some(Value::Array(items)) => {
+                        for item in items {
+                            context.push(item);
+                            render_nodes(nodes, context, output);
+                            context.pop();
+                        }
+                    }
+                    Some(value @ Value::Object(_)) => {
+                        context.push(value);
+                        render_nodes(nodes, context, output);
+                        context.pop();
+                    }
+                    Some(value) if is_truthy(value) => render_nodes(nodes, context, output),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Find `name` in the innermost item that has its first segment, then
+/// follow the rest of the dotted path from there.
+fn lookup<'a>(context: &[&'a Value], name: &str) -> Option<&'a Value> {
+    if name == "." {
+        return context.last().copied();
+    }
+
+    let mut segments = name.split('.');
+    let first = segments.next()?;
+    let mut value = context.iter().rev().find_map(|item| item.get(first))?;
+    for segment in segments {
+        value = match (value, segment.parse::<usize>()) {
+            (Value::Array(items), Ok(index)) => items.get(index)?,
+            _ => value.get(segment)?,
+        };
+    }
+    Some(value)
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(value) => *value,
+        Value::String(value) => !value.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Number(_) | Value::Object(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(source: &str, data: Value) -> String {
+        Template::parse("test", source).unwrap().render(&data)
+    }
+
+    fn parse_error(source: &str) -> String {
+        Template::parse("test", source).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn variables_follow_dotted_paths_and_list_indexes() {
+        let data = json!({
+            "name": "backup",
+            "script": { "author": "Ops", "runs": 12, "default": false },
+            "tags": ["disk", "nightly"],
+        });
+        assert_eq!(render("# {{ name }} by {{script.author}}", data.clone()), "# backup by Ops");
+        assert_eq!(render("{{script.runs}} {{script.default}} {{tags.1}}", data.clone()), "12 false nightly");
+        assert_eq!(render("[{{missing}}][{{script.missing}}][{{tags.5}}]", data), "[][][]");
+    }
+
+    #[test]
+    fn values_are_inserted_unescaped() {
+        assert_eq!(render("{{text}}", json!({ "text": "<a> & `b` | c" })), "<a> & `b` | c");
+        assert_eq!(render("{{null}}", json!({ "null": null })), "");
+    }
+
+    #[test]
+    fn sections_repeat_for_lists_and_enter_objects() {
+        let data = json!({
+            "scripts": [{ "name": "a" }, { "name": "b" }],
+            "stats": { "runs": 3 },
+            "category": "Net",
+        });
+        assert_eq!(render("{{#scripts}}{{name}}/{{category}} {{/scripts}}", data.clone()), "a/Net b/Net ");
+        assert_eq!(render("{{#stats}}{{runs}} runs{{/stats}}", data.clone()), "3 runs");
+        assert_eq!(render("{{#tags}}{{.}},{{/tags}}", json!({ "tags": ["x", "y"] })), "x,y,");
+        assert_eq!(render("{{#scripts}}x{{/scripts}}", json!({ "scripts": [] })), "");
+    }
+
+    #[test]
+    fn sections_and_inverted_sections_follow_truthiness() {
+        let data = json!({ "yes": true, "no": false, "empty": "", "zero": 0, "list": [] });
+        for (name, truthy) in [("yes", true), ("no", false), ("empty", false), ("zero", true), ("list", false), ("missing", false)] {
+            let expected = if truthy { "shown" } else { "" };
+            assert_eq!(render(&format!("{{{{#{0}}}}}shown{{{{/{0}}}}}", name), data.clone()), expected, "{}", name);
+            let expected = if truthy { "" } else { "shown" };
+            assert_eq!(render(&format!("{{{{^{0}}}}}shown{{{{/{0}}}}}", name), data.clone()), expected, "^{}", name);
+        }
+    }
+
+    #[test]
+    fn standalone_section_and_comment_lines_are_removed() {
+        let source = "# Scripts\n{{! one line per script }}\n{{#scripts}}\n- {{name}}\n  {{/scripts}}\n{{^scripts}}\nNone\n{{/scripts}}\nEnd";
+        assert_eq!(render(source, json!({ "scripts": [{ "name": "a" }, { "name": "b" }] })), "# Scripts\n- a\n- b\nEnd");
+        assert_eq!(render(source, json!({ "scripts": [] })), "# Scripts\nNone\nEnd");
+        assert_eq!(render("{{#a}}\nx\n{{/a}}", json!({ "a": true })), "x\n");
+    }
+
+    #[test]
+    fn tags_sharing_a_line_with_text_keep_the_line() {
+        assert_eq!(render("a {{#x}}b{{/x}} c\n", json!({ "x": true })), "a b c\n");
+        assert_eq!(render("a {{! note }}\nb", json!({})), "a \nb");
+        assert_eq!(render("{{v}} {{#x}}\ny{{/x}}", json!({ "v": 1, "x": true })), "1 \ny");
+    }
+
+    #[test]
+    fn nested_sections_see_outer_names() {
+        let data = json!({
+            "categories": [
+                { "name": "Net", "scripts": [{ "name": "ping" }, { "name": "dig", "category": "DNS" }] },
+            ],
+            "category": "top",
+        });
+        let source = "{{#categories}}{{#scripts}}{{name}}:{{category}} {{/scripts}}{{/categories}}";
+        assert_eq!(render(source, data), "ping:top dig:DNS ");
+    }
+
+    #[test]
+    fn malformed_templates_are_rejected() {
+        assert_eq!(parse_error("a {{name"), "test: unclosed '{{'");
+        assert_eq!(parse_error("{{/x}}"), "test: '{{/x}}' without a matching opening tag");
+        assert_eq!(parse_error("{{#x}}{{/y}}"), "test: '{{/y}}' closes 'x'");
+        assert_eq!(parse_error("{{#x}}{{#y}}{{/y}}"), "test: section 'x' is never closed");
+    }
+}
@@ -0,0 +1,374 @@
+use anyhow::{anyhow, bail, Context, Result};
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Styles for every part of the menu, plus the `#MCOLOR` Z-codes.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    pub header: Style,
+    pub search_header: Style,
+    pub border: Style,
+    pub search_border: Style,
+    pub text: Style,
+    pub muted: Style,
+    pub highlight: Style,
+    pub matched: Style,
+    pub separator: Style,
+    pub category: Style,
+    pub back: Style,
+    pub home: Style,
+    pub search: Style,
+    pub exit: Style,
+    pub status: Style,
+    pub chip: Style,
+    pub chip_negated: Style,
+    pub success: Style,
+    pub warning: Style,
+    pub error: Style,
+    pub disabled: Style,
+    /// Z-codes in display order (Z1, Z2, ... Z10).
+    pub zcodes: Vec<ZCode>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ZCode {
+    pub code: String,
+    pub label: String,
+    pub style: Style,
+}
+
+/// A theme file, e.g. `~/.config/toolbox/theme.toml`:
+///
+/// ```toml
+/// base = "dark"
+///
+/// [palette]
+/// highlight = { bg = "blue", bold = true }
+///
+/// [zcodes.Z5]
+/// label = "Database"
+/// fg = "#ff8700"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    base: Option<String>,
+    palette: BTreeMap<String, StyleSpec>,
+    zcodes: BTreeMap<String, ZCodeSpec>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StyleSpec {
+    fg: Option<String>,
+    bg: Option<String>,
+    bold: Option<bool>,
+    dim: Option<bool>,
+    italic: Option<bool>,
+    underlined: Option<bool>,
+    reversed: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ZCodeSpec {
+    label: Option<String>,
+    #[serde(flatten)]
+    style: StyleSpec,
+}
+
+impl Theme {
+    pub const BUILT_IN: [&'static str; 4] = ["dark", "light", "high-contrast", "no-colour"];
+
+    /// Resolve the theme to use: a built-in by name, optionally customised
+    /// by a theme file. `NO_COLOR` and monochrome terminals strip all
+    /// colours but keep bold/underline/reverse so the menu stays usable.
+    pub fn load(name: Option<&str>, file: Option<&Path>) -> Result<Self> {
+        let spec = match file {
+            Some(path) if path.exists() => {
+                let content = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read theme file: {}", path.display()))?;
+                toml::from_str::<ThemeFile>(&content)
+                    .with_context(|| format!("Invalid theme file: {}", path.display()))?
+            }
+            _ => ThemeFile::default(),
+        };
+
+        let base = name.or(spec.base.as_deref()).unwrap_or("dark");
+        let mut theme = Self::built_in(base).ok_or_else(|| {
+            anyhow!("Unknown theme '{}'; expected one of: {}", base, Self::BUILT_IN.join(", "))
+        })?;
+
+        for (role, style) in &spec.palette {
+            let target = theme
+                .role_mut(role)
+                .ok_or_else(|| anyhow!("Unknown palette entry '{}' in theme file", role))?;
+            *target = style.apply(*target)?;
+        }
+
+        for (code, zcode) in &spec.zcodes {
+            if !is_zcode(code) {
+                bail!("Invalid Z-code '{}' in theme file; expected Z followed by a number", code);
+            }
+            let existing = theme.zcodes.iter().position(|z| &z.code == code);
+            let base_style = existing.map(|i| theme.zcodes[i].style).unwrap_or(theme.text);
+            let entry = ZCode {
+                code: code.clone(),
+                label: zcode
+                    .label
+                    .clone()
+                    .or_else(|| existing.map(|i| theme.zcodes[i].label.clone()))
+                    .unwrap_or_else(|| code.clone()),
+                style: zcode.style.apply(base_style)?,
+            };
+            match existing {
+                Some(i) => theme.zcodes[i] = entry,
+                None => theme.zcodes.push(entry),
+            }
+        }
+        theme.zcodes.sort_by_key(|z| z.code[1..].parse::<u32>().unwrap_or(u32::MAX));
+
+        if colours_disabled() {
+            theme = theme.without_colours();
+        }
+
+        Ok(theme)
+    }
+
+    pub fn built_in(name: &str) -> Option<Self> {
+        let fg = |color: Color| Style::default().fg(color);
+        let bold = |style: Style| style.add_modifier(Modifier::BOLD);
+
+        let theme = match name {
+            "dark" => Self {
+                name: name.to_string(),
+                header: bold(fg(Color::Cyan)),
+                search_header: bold(fg(Color::Yellow)),
+                border: fg(Color::White),
+                search_border: fg(Color::Yellow),
+                text: fg(Color::White),
+                muted: fg(Color::DarkGray),
+                highlight: bold(Style::default().bg(Color::DarkGray)),
+                matched: fg(Color::LightYellow).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                separator: fg(Color::DarkGray),
+                category: fg(Color::Blue),
+                back: fg(Color::Yellow),
+                home: fg(Color::Green),
+                search: fg(Color::Cyan),
+                exit: fg(Color::Red),
+                status: fg(Color::Yellow),
+                chip: fg(Color::Black).bg(Color::Cyan),
+                chip_negated: fg(Color::Black).bg(Color::Red),
+                success: fg(Color::Green),
+                warning: fg(Color::Yellow),
+                error: fg(Color::Red),
+                disabled: fg(Color::DarkGray),
+                zcodes: standard_zcodes([Color::Red, Color::Green, Color::Yellow, Color::Blue]),
+            },
+            "light" => Self {
+                name: name.to_string(),
+                header: bold(fg(Color::Blue)),
+                search_header: bold(fg(Color::Magenta)),
+                border: fg(Color::Black),
+                search_border: fg(Color::Magenta),
+                text: fg(Color::Black),
+                muted: fg(Color::DarkGray),
+                highlight: bold(fg(Color::Black).bg(Color::Gray)),
+                matched: fg(Color::Magenta).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                separator: fg(Color::DarkGray),
+                category: fg(Color::Blue),
+                back: fg(Color::Magenta),
+                home: fg(Color::Green),
+                search: fg(Color::Blue),
+                exit: fg(Color::Red),
+                status: bold(fg(Color::Magenta)),
+                chip: fg(Color::White).bg(Color::Blue),
+                chip_negated: fg(Color::White).bg(Color::Red),
+                success: fg(Color::Green),
+                warning: fg(Color::Magenta),
+                error: fg(Color::Red),
+                disabled: fg(Color::Gray),
+                zcodes: standard_zcodes([Color::Red, Color::Green, Color::Magenta, Color::Blue]),
+            },
+            "high-contrast" => Self {
+                name: name.to_string(),
+                header: bold(fg(Color::White)),
+                search_header: bold(fg(Color::LightYellow)),
+                border: fg(Color::White),
+                search_border: fg(Color::LightYellow),
+                text: fg(Color::White),
+                muted: fg(Color::Gray),
+                highlight: bold(fg(Color::Black).bg(Color::LightYellow)),
+                matched: fg(Color::LightCyan).add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                separator: fg(Color::Gray),
+                category: bold(fg(Color::LightCyan)),
+                back: bold(fg(Color::LightYellow)),
+                home: bold(fg(Color::LightGreen)),
+                search: bold(fg(Color::LightCyan)),
+                exit: bold(fg(Color::LightRed)),
+                status: bold(fg(Color::Black).bg(Color::LightYellow)),
+                chip: bold(fg(Color::Black).bg(Color::LightCyan)),
+                chip_negated: bold(fg(Color::Black).bg(Color::LightRed)),
+                success: bold(fg(Color::LightGreen)),
+                warning: bold(fg(Color::LightYellow)),
+                error: bold(fg(Color::LightRed)),
+                disabled: fg(Color::Gray).add_modifier(Modifier::CROSSED_OUT),
+                zcodes: standard_zcodes([Color::LightRed, Color::LightGreen, Color::LightYellow, Color::LightCyan])
+                    .into_iter()
+                    .map(|z| ZCode { style: bold(z.style), ..z })
+                    .collect(),
+            },
+            "no-colour" | "no-color" => Self::built_in("dark")?.without_colours(),
+            _ => return None,
+        };
+
+        Some(theme)
+    }
+
+    /// Style for a script's `#MCOLOR` code; unknown codes use plain text.
+    pub fn script_style(&self, color_code: Option<&str>) -> Style {
+        color_code
+            .and_then(|code| self.zcodes.iter().find(|z| z.code == code))
+            .map(|z| z.style)
+            .unwrap_or(self.text)
+    }
+
+    /// Drop every colour, keeping modifiers. Roles that relied on colour
+    /// alone get a modifier instead so they remain distinguishable.
+    pub fn without_colours(self) -> Self {
+        let plain = |style: Style| Style {
+            fg: None,
+            bg: None,
+            ..style
+        };
+        let with = |style: Style, modifier: Modifier| plain(style).add_modifier(modifier);
+
+        Self {
+            name: "no-colour".to_string(),
+            header: with(self.header, Modifier::BOLD),
+            search_header: with(self.search_header, Modifier::BOLD),
+            border: plain(self.border),
+            search_border: plain(self.search_border),
+            text: plain(self.text),
+            muted: with(self.muted, Modifier::DIM),
+            highlight: with(self.highlight, Modifier::REVERSED),
+            matched: with(self.matched, Modifier::UNDERLINED),
+            separator: with(self.separator, Modifier::DIM),
+            category: with(self.category, Modifier::BOLD),
+            back: plain(self.back),
+            home: plain(self.home),
+            search: plain(self.search),
+            exit: plain(self.exit),
+            status: with(self.status, Modifier::BOLD),
+            chip: with(self.chip, Modifier::REVERSED),
+            chip_negated: with(self.chip_negated, Modifier::REVERSED | Modifier::CROSSED_OUT),
+            success: plain(self.success),
+            warning: with(self.warning, Modifier::BOLD),
+            error: with(self.error, Modifier::BOLD),
+            disabled: with(self.disabled, Modifier::DIM),
+            zcodes: self
+                .zcodes
+                .into_iter()
+                .map(|z| {
+                    // Dangerous scripts must still stand out without colour
+                    let style = if z.code == "Z1" { with(z.style, Modifier::BOLD) } else { plain(z.style) };
+                    ZCode { style, ..z }
+                })
+                .collect(),
+        }
+    }
+
+    fn role_mut(&mut self, role: &str) -> Option<&mut Style> {
+        Some(match role {
+            "header" => &mut self.header,
+            "search_header" => &mut self.search_header,
+            "border" | "list" => &mut self.border,
+            "search_border" => &mut self.search_border,
+            "text" => &mut self.text,
+            "muted" => &mut self.muted,
+            "highlight" => &mut self.highlight,
+            "matched" => &mut self.matched,
+            "separator" => &mut self.separator,
+            "category" => &mut self.category,
+            "back" => &mut self.back,
+            "home" => &mut self.home,
+            "search" => &mut self.search,
+            "exit" => &mut self.exit,
+            "status" => &mut self.status,
+            "chip" => &mut self.chip,
+            "chip_negated" => &mut self.chip_negated,
+            "success" => &mut self.success,
+            "warning" => &mut self.warning,
+            "error" => &mut self.error,
+            "disabled" => &mut self.disabled,
+            _ => return None,
+        })
+    }
+}
+
+impl StyleSpec {
+    fn apply(&self, mut style: Style) -> Result<Style> {
+        if let Some(fg) = &self.fg {
+            style.fg = Some(parse_color(fg)?);
+        }
+        if let Some(bg) = &self.bg {
+            style.bg = Some(parse_color(bg)?);
+        }
+
+        for (enabled, modifier) in [
+            (self.bold, Modifier::BOLD),
+            (self.dim, Modifier::DIM),
+            (self.italic, Modifier::ITALIC),
+            (self.underlined, Modifier::UNDERLINED),
+            (self.reversed, Modifier::REVERSED),
+        ] {
+            style = match enabled {
+                Some(true) => style.add_modifier(modifier),
+                Some(false) => style.remove_modifier(modifier),
+                None => style,
+            };
+        }
+
+        Ok(style)
+    }
+}
+
+fn standard_zcodes(colors: [Color; 4]) -> Vec<ZCode> {
+    ["Dangerous", "Safe", "Caution", "Info"]
+        .iter()
+        .zip(colors)
+        .enumerate()
+        .map(|(i, (label, color))| ZCode {
+            code: format!("Z{}", i + 1),
+            label: label.to_string(),
+            style: Style::default().fg(color),
+        })
+        .collect()
+}
+
+fn is_zcode(code: &str) -> bool {
+    code.strip_prefix('Z')
+        .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn parse_color(value: &str) -> Result<Color> {
+    value
+        .parse::<Color>()
+        .map_err(|_| anyhow!("Invalid colour '{}' in theme file", value))
+}
+
+/// Honour https://no-color.org and terminals that cannot show colour.
+fn colours_disabled() -> bool {
+    if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return true;
+    }
+
+    match std::env::var("TERM") {
+        Ok(term) => term == "dumb" || term.ends_with("-mono") || term.ends_with("-m"),
+        Err(_) => false,
+    }
+}
@@ -1,28 +1,121 @@
 use anyhow::Result;
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, MouseEventKind},
     execute,
     terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear as ClearWidget, Gauge, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{
+        block::{Position, Title},
+        Block, Borders, Clear as ClearWidget, Gauge, List, ListItem, ListState, Paragraph, Wrap,
+    },
     Frame, Terminal,
 };
 use std::io::{self, Stdout};
 
-use crate::models::{MenuItem, MenuState, Script};
+use crate::glyphs::{self, Glyphs};
+use crate::models::{ExecutionRecord, MenuItem, MenuState, Script};
+use crate::keymap::{Action, Keymap};
+use crate::query::SearchQuery;
+use crate::roots::describe_source;
+use crate::search::SearchResult;
+use crate::sources;
+use crate::theme::Theme;
+
+/// The detail panel is only shown next to the menu on terminals at least
+/// this wide.
+const DETAIL_PANEL_MIN_WIDTH: u16 = 100;
+/// Lines moved per mouse wheel step in the detail panel and help popup.
+const WHEEL_SCROLL_LINES: u16 = 3;
 
 pub struct MenuUI {
     terminal: Option<Terminal<CrosstermBackend<Stdout>>>,
+    keymap: Keymap,
+    theme: Theme,
+    glyphs: Glyphs,
+    layout: ScreenLayout,
+}
+
+/// What is under the mouse pointer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseTarget {
+    /// An entry of the menu or search results, by index.
+    Item(usize),
+    /// A breadcrumb segment, by depth (0 is Home).
+    Breadcrumb(usize),
+    DetailPanel,
+}
+
+/// Where the last frame put things, so mouse events can be mapped back to
+/// menu entries. The list state is kept between frames so the scroll
+/// position does not jump on every redraw.
+#[derive(Default)]
+struct ScreenLayout {
+    list: ListState,
+    list_area: Rect,
+    item_heights: Vec<u16>,
+    detail_area: Option<Rect>,
+    detail_lines: u16,
+    detail_scroll: u16,
+    detail_item: Option<usize>,
+    /// Header row and the columns covered by each breadcrumb segment.
+    breadcrumb_row: u16,
+    breadcrumbs: Vec<(u16, u16)>,
 }
 
 impl MenuUI {
-    pub fn new() -> Self {
-        Self { terminal: None }
+    pub fn new(keymap: Keymap, theme: Theme, glyphs: Glyphs) -> Self {
+        Self { terminal: None, keymap, theme, glyphs, layout: ScreenLayout::default() }
+    }
+
+    /// Find the menu entry, breadcrumb or panel at a terminal cell.
+    pub fn mouse_target(&self, column: u16, row: u16) -> Option<MouseTarget> {
+        let layout = &self.layout;
+
+        if row == layout.breadcrumb_row {
+            if let Some(depth) = layout
+                .breadcrumbs
+                .iter()
+                .position(|&(start, end)| column >= start && column < end)
+            {
+                return Some(MouseTarget::Breadcrumb(depth));
+            }
+        }
+
+        if let Some(detail_area) = layout.detail_area {
+            if contains(detail_area, column, row) {
+                return Some(MouseTarget::DetailPanel);
+            }
+        }
+
+        let inner = layout.list_area.inner(&Margin { horizontal: 1, vertical: 1 });
+        if !contains(inner, column, row) {
+            return None;
+        }
+
+        // Entries can span several lines (search results with body context)
+        let mut top = inner.y;
+        for (index, height) in layout.item_heights.iter().enumerate().skip(layout.list.offset()) {
+            if row < top + height {
+                return Some(MouseTarget::Item(index));
+            }
+            top += height;
+        }
+        None
+    }
+
+    /// Scroll the detail panel by `delta` lines; clamped when next drawn.
+    pub fn scroll_detail(&mut self, delta: i32) {
+        let layout = &mut self.layout;
+        layout.detail_scroll = if delta < 0 {
+            layout.detail_scroll.saturating_sub(delta.unsigned_abs() as u16 * WHEEL_SCROLL_LINES)
+        } else {
+            layout.detail_scroll.saturating_add(delta as u16 * WHEEL_SCROLL_LINES)
+        };
     }
 
     pub fn initialize(&mut self) -> Result<()> {
@@ -51,20 +144,22 @@ impl MenuUI {
     }
 
     pub fn render(&mut self, state: &MenuState) -> Result<()> {
+        let (keymap, theme, glyphs) = (&self.keymap, &self.theme, &self.glyphs);
+        let layout = &mut self.layout;
         if let Some(terminal) = &mut self.terminal {
             let search_mode = state.search_mode;
             terminal.draw(|f| {
                 if search_mode {
-                    Self::render_search_mode(f, state);
+                    Self::render_search_mode(f, state, keymap, theme, glyphs, layout);
                 } else {
-                    Self::render_menu_mode(f, state);
+                    Self::render_menu_mode(f, state, keymap, theme, glyphs, layout);
                 }
             })?;
         }
         Ok(())
     }
 
-    fn render_menu_mode(f: &mut Frame, state: &MenuState) {
+    fn render_menu_mode(f: &mut Frame, state: &MenuState, keymap: &Keymap, theme: &Theme, glyphs: &Glyphs, layout: &mut ScreenLayout) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
@@ -75,69 +170,113 @@ impl MenuUI {
             .split(f.size());
 
         // Header
-        Self::render_header(f, chunks[0], state);
+        Self::render_header(f, chunks[0], state, theme, glyphs, layout);
 
-        // Menu
-        Self::render_menu_list(f, chunks[1], state);
+        // Menu, with details of the selected entry beside it on wide terminals
+        if chunks[1].width >= DETAIL_PANEL_MIN_WIDTH {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .split(chunks[1]);
+            Self::render_menu_list(f, columns[0], state, keymap, theme, glyphs, layout);
+            Self::render_detail_panel(f, columns[1], state, theme, glyphs, layout);
+        } else {
+            Self::render_menu_list(f, chunks[1], state, keymap, theme, glyphs, layout);
+            layout.detail_area = None;
+        }
 
         // Footer
-        Self::render_footer(f, chunks[2], state);
+        Self::render_footer(f, chunks[2], state, keymap, theme, glyphs);
     }
 
-    fn render_search_mode(f: &mut Frame, state: &MenuState) {
+    fn render_search_mode(f: &mut Frame, state: &MenuState, keymap: &Keymap, theme: &Theme, glyphs: &Glyphs, layout: &mut ScreenLayout) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3), // Header
-                Constraint::Length(3), // Search input
+                Constraint::Length(4), // Search input and suggestions
                 Constraint::Min(0),    // Results
                 Constraint::Length(3), // Footer
             ])
             .split(f.size());
 
         // Header
-        Self::render_search_header(f, chunks[0]);
+        Self::render_search_header(f, chunks[0], theme, glyphs);
 
         // Search input
-        Self::render_search_input(f, chunks[1], state);
+        Self::render_search_input(f, chunks[1], state, theme, glyphs);
 
         // Results
-        Self::render_search_results(f, chunks[2], state);
+        Self::render_search_results(f, chunks[2], state, theme, glyphs, layout);
+        layout.detail_area = None;
+        layout.breadcrumbs.clear();
 
         // Footer
-        Self::render_search_footer(f, chunks[3]);
+        Self::render_search_footer(f, chunks[3], keymap, theme, glyphs);
     }
 
-    fn render_header(f: &mut Frame, area: ratatui::layout::Rect, state: &MenuState) {
-        let breadcrumb = state.breadcrumb.join(" > ");
-        let title = format!("🛡️ Toolbox Suite - {}", breadcrumb);
-        
+    fn render_header(f: &mut Frame, area: ratatui::layout::Rect, state: &MenuState, theme: &Theme, glyphs: &Glyphs, layout: &mut ScreenLayout) {
+        // Breadcrumb segments above the current category can be clicked
+        let mut spans = vec![Span::raw(format!("{} Toolbox Suite - ", glyphs.app))];
+        let mut segment_spans = Vec::new();
+        for (depth, segment) in state.breadcrumb.iter().enumerate() {
+            if depth > 0 {
+                spans.push(Span::raw(" > "));
+            }
+            segment_spans.push(spans.len());
+            if depth + 1 < state.breadcrumb.len() {
+                spans.push(Span::styled(segment.clone(), Style::default().add_modifier(Modifier::UNDERLINED)));
+            } else {
+                spans.push(Span::raw(segment.clone()));
+            }
+        }
+        let title = Line::from(spans);
+
+        // Work out where each segment lands. The paragraph centres the line
+        // using its own width measure; on screen, emoji made wide by a
+        // variation selector push the rest of the line one column further.
+        let inner = area.inner(&Margin { horizontal: 1, vertical: 1 });
+        let mut column = inner.x + (inner.width / 2).saturating_sub(title.width() as u16 / 2);
+        layout.breadcrumb_row = inner.y;
+        layout.breadcrumbs.clear();
+        for (index, span) in title.spans.iter().enumerate() {
+            let width = glyphs::display_width(&span.content) as u16;
+            if segment_spans.contains(&index) {
+                layout.breadcrumbs.push((column, column + width));
+            }
+            column += width;
+        }
+
         let header = Paragraph::new(title)
-            .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+            .style(theme.header)
             .alignment(Alignment::Center)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .style(Style::default().fg(Color::White)),
+                    .border_set(glyphs.borders)
+                    .style(theme.border),
             );
         
         f.render_widget(header, area);
     }
 
-    fn render_search_header(f: &mut Frame, area: ratatui::layout::Rect) {
-        let header = Paragraph::new("🔍 Search Mode")
-            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+    fn render_search_header(f: &mut Frame, area: ratatui::layout::Rect, theme: &Theme, glyphs: &Glyphs) {
+        let header = Paragraph::new(format!("{} Search Mode", glyphs.search))
+            .style(theme.search_header)
             .alignment(Alignment::Center)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .style(Style::default().fg(Color::White)),
+                    .border_set(glyphs.borders)
+                    .style(theme.border),
             );
         
         f.render_widget(header, area);
     }
 
-    fn render_menu_list(f: &mut Frame, area: ratatui::layout::Rect, state: &MenuState) {
+    fn render_menu_list(f: &mut Frame, area: ratatui::layout::Rect, state: &MenuState, keymap: &Keymap, theme: &Theme, glyphs: &Glyphs, layout: &mut ScreenLayout) {
+        let nav_key = |action: Action| glyphs::pad(&keymap.primary_key(action, glyphs), 2);
+
         let items: Vec<ListItem> = state
             .filtered_items
             .iter()
@@ -145,18 +284,35 @@ impl MenuUI {
             .map(|(i, item)| {
                 let (number, content, style) = match item {
                     MenuItem::Script(script) => {
-                        let icon = script.display_icon();
+                        let icon = glyphs.icon(script.display_icon(), glyphs.script);
                         let name = script.display_name();
                         let desc = script.display_description();
                         
-                        let color = Self::get_script_color(script);
-                        let style = Style::default().fg(color);
+                        let mut style = theme.script_style(script.color.as_deref());
                         
-                        let display_text = if !script.dependency_available {
-                            format!("🚫 {} {} - {} (Needs Installing)", icon, name, desc)
+                        let mut display_text = if !script.dependency_available {
+                            format!("{} {} {} - {} (Needs Installing)", glyphs.unavailable, icon, name, desc)
                         } else {
                             format!("{} {} - {}", icon, name, desc)
                         };
+
+                        if !script.permission_issues.is_empty() {
+                            display_text = format!("{} {}", glyphs.insecure, display_text);
+                        }
+
+                        if script.requires_elevation() {
+                            if state.can_elevate {
+                                display_text = format!("{} {}", glyphs.sudo, display_text);
+                            } else {
+                                display_text = format!("{} {} (Requires sudo)", glyphs.sudo, display_text);
+                                style = theme.disabled;
+                            }
+                        }
+
+                        if state.run_denied.contains(&script.path) {
+                            display_text = format!("{} (Not permitted)", display_text);
+                            style = theme.disabled;
+                        }
                         
                         (
                             format!("{:2}", i + 1),
@@ -164,125 +320,239 @@ impl MenuUI {
                             style,
                         )
                     }
+                    MenuItem::Workflow(workflow) => (
+                        format!("{:2}", i + 1),
+                        format!(
+                            "{} {} - {} ({} steps)",
+                            glyphs.icon(workflow.display_icon(), glyphs.workflow),
+                            workflow.name,
+                            workflow.display_description(),
+                            workflow.steps.len()
+                        ),
+                        theme.script_style(None),
+                    ),
                     MenuItem::Category(category) => (
                         format!("{:2}", i + 1),
-                        format!("{} {} ({} items)", category.icon, category.name, category.total_items()),
-                        Style::default().fg(Color::Blue),
+                        format!("{} {} ({} items)", glyphs.icon(&category.icon, glyphs.category), category.name, category.total_items()),
+                        theme.category,
                     ),
                     MenuItem::Separator(text) => (
                         "  ".to_string(),
-                        format!("──── {} ────", text),
-                        Style::default().fg(Color::DarkGray),
+                        format!("{} {} {}", glyphs.rule, text, glyphs.rule),
+                        theme.separator,
                     ),
                     MenuItem::Back => (
-                        "X ".to_string(),
-                        "⬅️  Back".to_string(),
-                        Style::default().fg(Color::Yellow),
+                        nav_key(Action::Back),
+                        format!("{} Back", glyphs.back),
+                        theme.back,
                     ),
                     MenuItem::Home => (
-                        "H ".to_string(),
-                        "🏠 Home".to_string(),
-                        Style::default().fg(Color::Green),
+                        nav_key(Action::Home),
+                        format!("{} Home", glyphs.home),
+                        theme.home,
                     ),
                     MenuItem::Search => (
-                        "S ".to_string(),
-                        "🔍 Search".to_string(),
-                        Style::default().fg(Color::Cyan),
+                        nav_key(Action::Search),
+                        format!("{} Search", glyphs.search),
+                        theme.search,
                     ),
                     MenuItem::Exit => (
-                        "Q ".to_string(),
-                        "❌ Exit".to_string(),
-                        Style::default().fg(Color::Red),
+                        nav_key(Action::Quit),
+                        format!("{} Exit", glyphs.exit),
+                        theme.exit,
                     ),
                 };
 
-                let line = Line::from(vec![
-                    Span::styled(number, Style::default().fg(Color::DarkGray)),
-                    Span::raw(" "),
-                    Span::styled(content, style),
-                ]);
+                let mut spans = vec![Span::styled(number, theme.muted), Span::raw(" ")];
+                if matches!(item, MenuItem::Script(script) if state.is_marked(script)) {
+                    spans.push(Span::styled(format!("{} ", glyphs.mark), theme.status));
+                }
+                spans.push(Span::styled(content, style));
+                let source = match item {
+                    MenuItem::Script(script) => script.source.as_deref(),
+                    MenuItem::Workflow(workflow) => workflow.source.as_deref(),
+                    _ => None,
+                };
+                spans.extend(Self::source_span(source, state, theme));
+                let line = Line::from(spans);
 
                 ListItem::new(line)
             })
             .collect();
 
-        let mut list_state = ListState::default();
-        list_state.select(Some(state.selected_index));
+        layout.list_area = area;
+        layout.item_heights = items.iter().map(|item| item.height() as u16).collect();
+        layout.list.select(Some(state.selected_index));
 
         let list = List::new(items)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Menu")
-                    .style(Style::default().fg(Color::White)),
-            )
-            .highlight_style(
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD),
+                    .border_set(glyphs.borders)
+                    .title(Self::marked_title("Menu".to_string(), state))
+                    .title(Self::zcode_legend(&state.filtered_items, theme, glyphs))
+                    .style(theme.border),
             )
-            .highlight_symbol("► ");
+            .highlight_style(theme.highlight)
+            .highlight_symbol(glyphs.selector);
 
-        f.render_stateful_widget(list, area, &mut list_state);
+        f.render_stateful_widget(list, area, &mut layout.list);
     }
 
-    fn render_search_input(f: &mut Frame, area: ratatui::layout::Rect, state: &MenuState) {
-        let input = Paragraph::new(format!("Query: {}_", state.search_query))
-            .style(Style::default().fg(Color::White))
+    fn render_search_input(f: &mut Frame, area: ratatui::layout::Rect, state: &MenuState, theme: &Theme, glyphs: &Glyphs) {
+        // Active qualifiers are shown as chips in the box title
+        let query = SearchQuery::parse(&state.search_query);
+        let mut title = vec![Span::raw("Search")];
+        if state.search_bodies {
+            title.push(Span::raw(" "));
+            title.push(Span::styled(" +bodies ", theme.chip));
+        }
+        for filter in &query.filters {
+            let chip_style = if filter.negated { theme.chip_negated } else { theme.chip };
+            title.push(Span::raw(" "));
+            title.push(Span::styled(format!(" {} ", filter.label()), chip_style));
+        }
+
+        // Completions for the word being typed; Tab cycles through them
+        let mut suggestion_line = vec![Span::styled("Tab: ", theme.muted)];
+        for (i, suggestion) in state.suggestions.iter().enumerate() {
+            if i > 0 {
+                suggestion_line.push(Span::styled(" | ", theme.muted));
+            }
+            let style = if state.suggestion_index == Some(i) {
+                theme.chip
+            } else {
+                theme.search
+            };
+            suggestion_line.push(Span::styled(suggestion.clone(), style));
+        }
+
+        let mut lines = vec![Line::from(format!("Query: {}_", state.search_query))];
+        if !state.suggestions.is_empty() {
+            lines.push(Line::from(suggestion_line));
+        }
+
+        let input = Paragraph::new(lines)
+            .style(theme.text)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("Search")
-                    .style(Style::default().fg(Color::Yellow)),
+                    .border_set(glyphs.borders)
+                    .title(Line::from(title))
+                    .style(theme.search_border),
             );
         
         f.render_widget(input, area);
     }
 
-    fn render_search_results(f: &mut Frame, area: ratatui::layout::Rect, state: &MenuState) {
+    fn render_search_results(f: &mut Frame, area: ratatui::layout::Rect, state: &MenuState, theme: &Theme, glyphs: &Glyphs, layout: &mut ScreenLayout) {
         let items: Vec<ListItem> = state
             .filtered_items
             .iter()
             .enumerate()
             .map(|(i, item)| {
-                let (content, style) = match item {
+                let mut spans = vec![Span::styled(format!("{:2} ", i + 1), theme.muted)];
+
+                match item {
                     MenuItem::Script(script) => {
-                        let icon = script.display_icon();
-                        let name = script.display_name();
-                        let desc = script.display_description();
-                        let category = &script.category;
-                        
-                        let color = Self::get_script_color(script);
-                        
-                        let display_text = if !script.dependency_available {
-                            format!("🚫 {} {} - {} [{}] (Needs Installing)", icon, name, desc, category)
+                        let can_elevate = !script.requires_elevation() || state.can_elevate;
+                        let permitted = !state.run_denied.contains(&script.path);
+                        let style = if can_elevate && permitted {
+                            theme.script_style(script.color.as_deref())
                         } else {
-                            format!("{} {} - {} [{}]", icon, name, desc, category)
+                            theme.disabled
                         };
-                        
-                        (
-                            display_text,
-                            Style::default().fg(color),
-                        )
+                        let matches = state.search_matches.get(&script.path);
+                        let ranges = |field: &str| {
+                            matches
+                                .and_then(|result| result.highlight_ranges.get(field))
+                                .map(Vec::as_slice)
+                                .unwrap_or(&[])
+                        };
+
+                        if state.is_marked(script) {
+                            spans.push(Span::styled(format!("{} ", glyphs.mark), theme.status));
+                        }
+
+                        let mut prefix = String::new();
+                        if script.requires_elevation() {
+                            prefix.push_str(glyphs.sudo);
+                            prefix.push(' ');
+                        }
+                        if !script.dependency_available {
+                            prefix.push_str(glyphs.unavailable);
+                            prefix.push(' ');
+                        }
+                        if !script.permission_issues.is_empty() {
+                            prefix.push_str(glyphs.insecure);
+                            prefix.push(' ');
+                        }
+                        let icon = glyphs.icon(script.display_icon(), glyphs.script);
+                        spans.push(Span::styled(format!("{}{} ", prefix, icon), style));
+
+                        let name_field = if script.menu_name.is_some() { "menu_name" } else { "name" };
+                        spans.extend(Self::highlight_spans(script.display_name(), ranges(name_field), style, theme));
+                        spans.push(Span::styled(" - ", style));
+                        spans.extend(Self::highlight_spans(script.display_description(), ranges("description"), style, theme));
+                        spans.push(Span::styled(" [", style));
+                        spans.extend(Self::highlight_spans(&script.category, ranges("category"), style, theme));
+                        spans.push(Span::styled("]", style));
+                        spans.extend(Self::source_span(script.source.as_deref(), state, theme));
+
+                        if !script.dependency_available {
+                            spans.push(Span::styled(" (Needs Installing)", style));
+                        }
+                        if !can_elevate {
+                            spans.push(Span::styled(" (Requires sudo)", style));
+                        }
+                        if !permitted {
+                            spans.push(Span::styled(" (Not permitted)", style));
+                        }
+
+                        // Fields that matched but are not part of the line itself
+                        if let Some(result) = matches {
+                            let hidden = Self::hidden_matches(script, result);
+                            if !hidden.is_empty() {
+                                spans.push(Span::styled(
+                                    format!("  matched: {}", hidden.join(", ")),
+                                    theme.muted.add_modifier(Modifier::ITALIC),
+                                ));
+                            }
+                        }
                     }
-                    MenuItem::Back => (
-                        "⬅️  Back to Menu".to_string(),
-                        Style::default().fg(Color::Yellow),
-                    ),
-                    _ => ("".to_string(), Style::default()),
-                };
+                    MenuItem::Back => spans.push(Span::styled(
+                        format!("{} Back to Menu", glyphs.back),
+                        theme.back,
+                    )),
+                    _ => {}
+                }
 
-                let line = Line::from(vec![
-                    Span::styled(format!("{:2} ", i + 1), Style::default().fg(Color::DarkGray)),
-                    Span::styled(content, style),
-                ]);
+                let mut lines = vec![Line::from(spans)];
 
-                ListItem::new(line)
+                // Matching body line with its surrounding context
+                if let MenuItem::Script(script) = item {
+                    if let Some(body_match) = state.body_matches.get(&script.path) {
+                        for (line_number, text) in &body_match.context {
+                            let style = if *line_number == body_match.line_number {
+                                theme.text
+                            } else {
+                                theme.muted
+                            };
+                            lines.push(Line::from(vec![
+                                Span::styled(format!("      {:>4} {} ", line_number, glyphs.gutter), theme.muted),
+                                Span::styled(text.clone(), style),
+                            ]));
+                        }
+                    }
+                }
+
+                ListItem::new(lines)
             })
             .collect();
 
-        let mut list_state = ListState::default();
-        list_state.select(Some(state.selected_index));
+        layout.list_area = area;
+        layout.item_heights = items.iter().map(|item| item.height() as u16).collect();
+        layout.list.select(Some(state.selected_index));
 
         let title = if state.search_query.is_empty() {
             "Search Results (type to search)".to_string()
@@ -294,131 +564,541 @@ impl MenuUI {
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title(title)
-                    .style(Style::default().fg(Color::Yellow)),
+                    .border_set(glyphs.borders)
+                    .title(Self::marked_title(title, state))
+                    .style(theme.search_border),
             )
-            .highlight_style(
-                Style::default()
-                    .bg(Color::DarkGray)
-                    .add_modifier(Modifier::BOLD),
+            .highlight_style(theme.highlight)
+            .highlight_symbol(glyphs.selector);
+
+        f.render_stateful_widget(list, area, &mut layout.list);
+    }
+
+    /// `[label]` after an entry, naming the toolbox root it comes from when
+    /// there is more than one.
+    fn source_span(source: Option<&str>, state: &MenuState, theme: &Theme) -> Option<Span<'static>> {
+        source
+            .filter(|_| state.show_sources)
+            .map(|source| Span::styled(format!(" [{}]", source), theme.muted))
+    }
+
+    /// Details of the selected entry: the full description and metadata
+    /// that the one-line menu entry has no room for.
+    fn render_detail_panel(f: &mut Frame, area: ratatui::layout::Rect, state: &MenuState, theme: &Theme, glyphs: &Glyphs, layout: &mut ScreenLayout) {
+        let field = |label: &str, value: String| {
+            Line::from(vec![
+                Span::styled(format!("{:<10}", label), theme.muted),
+                Span::styled(value, theme.text),
+            ])
+        };
+
+        let mut lines = Vec::new();
+        match state.filtered_items.get(state.selected_index) {
+            Some(MenuItem::Script(script)) => {
+                let icon = glyphs.icon(script.display_icon(), glyphs.script);
+                lines.push(Line::from(Span::styled(
+                    format!("{} {}", icon, script.display_name()),
+                    theme.script_style(script.color.as_deref()).add_modifier(Modifier::BOLD),
+                )));
+                lines.push(Line::from(Span::styled(script.display_description().to_string(), theme.text)));
+                if let Some(details) = &script.detailed_description {
+                    lines.push(Line::default());
+                    lines.push(Line::from(Span::styled(details.clone(), theme.text)));
+                }
+                lines.push(Line::default());
+                lines.push(field("Category", script.category.clone()));
+                lines.push(field("Path", script.path.display().to_string()));
+                if let Some(source) = describe_source(script.source.as_deref(), script.overrides.as_deref()) {
+                    lines.push(field("Source", source));
+                }
+                if let Some(revision) = &script.revision {
+                    lines.push(field("Revision", sources::short(revision).to_string()));
+                }
+                if let Some(checksum) = &script.checksum {
+                    lines.push(field("SHA-256", checksum[..checksum.len().min(16)].to_string()));
+                }
+                if !script.tags.is_empty() {
+                    lines.push(field("Tags", script.tags.join(", ")));
+                }
+                if let Some(author) = &script.author {
+                    lines.push(field("Author", author.clone()));
+                }
+                if let Some(info_url) = &script.info_url {
+                    lines.push(field("Info", info_url.clone()));
+                }
+                if !script.roles.is_empty() {
+                    lines.push(field("Roles", script.roles.join(", ")));
+                }
+                if script.requires_elevation() {
+                    lines.push(field("Runs as", format!("root {}", glyphs.sudo)));
+                }
+                if let Some(timeout) = script.timeout_secs {
+                    lines.push(field("Timeout", format!("{}s", timeout)));
+                }
+                if script.has_parameters() {
+                    lines.push(field("Params", "prompted before running".to_string()));
+                }
+                if !script.dependency_available {
+                    let dependency = script.integration.clone().unwrap_or_default();
+                    lines.push(Line::from(Span::styled(
+                        format!("{} Needs installing: {}", glyphs.unavailable, dependency),
+                        theme.warning,
+                    )));
+                }
+                if !script.permission_issues.is_empty() {
+                    lines.push(Line::from(Span::styled(
+                        format!("{} File is {}", glyphs.insecure, script.permission_issues.join(" and ")),
+                        theme.warning,
+                    )));
+                }
+            }
+            Some(MenuItem::Workflow(workflow)) => {
+                lines.push(Line::from(Span::styled(
+                    format!("{} {}", glyphs.icon(workflow.display_icon(), glyphs.workflow), workflow.name),
+                    theme.script_style(None).add_modifier(Modifier::BOLD),
+                )));
+                lines.push(Line::from(Span::styled(workflow.display_description().to_string(), theme.text)));
+                lines.push(Line::default());
+                lines.push(field("Category", workflow.category.clone()));
+                lines.push(field("Path", workflow.path.display().to_string()));
+                if let Some(source) = describe_source(workflow.source.as_deref(), workflow.overrides.as_deref()) {
+                    lines.push(field("Source", source));
+                }
+                lines.push(Line::default());
+                lines.push(Line::from(Span::styled("Steps", theme.muted)));
+                for (i, step) in workflow.steps.iter().enumerate() {
+                    lines.push(Line::from(Span::styled(format!("{:>2}. {}", i + 1, step), theme.text)));
+                }
+            }
+            Some(MenuItem::Category(category)) => {
+                lines.push(Line::from(Span::styled(
+                    format!("{} {}", glyphs.icon(&category.icon, glyphs.category), category.name),
+                    theme.category.add_modifier(Modifier::BOLD),
+                )));
+                lines.push(field("Items", category.total_items().to_string()));
+                lines.push(Line::default());
+                for subcategory in &category.subcategories {
+                    lines.push(Line::from(Span::styled(
+                        format!("{} {}", glyphs.icon(&subcategory.icon, glyphs.category), subcategory.name),
+                        theme.category,
+                    )));
+                }
+                for script in &category.scripts {
+                    lines.push(Line::from(Span::styled(
+                        format!("{} {}", glyphs.icon(script.display_icon(), glyphs.script), script.display_name()),
+                        theme.script_style(script.color.as_deref()),
+                    )));
+                }
+                for workflow in &category.workflows {
+                    lines.push(Line::from(Span::styled(
+                        format!("{} {}", glyphs.icon(workflow.display_icon(), glyphs.workflow), workflow.name),
+                        theme.script_style(None),
+                    )));
+                }
+            }
+            _ => {}
+        }
+
+        // Start at the top whenever the selection changes, and stop
+        // scrolling once the last line is in view
+        if layout.detail_item != Some(state.selected_index) {
+            layout.detail_item = Some(state.selected_index);
+            layout.detail_scroll = 0;
+        }
+        let inner = area.inner(&Margin { horizontal: 1, vertical: 1 });
+        layout.detail_lines = lines
+            .iter()
+            .map(|line| (line.width() as u16).max(1).div_ceil(inner.width.max(1)))
+            .sum();
+        layout.detail_scroll = layout.detail_scroll.min(layout.detail_lines.saturating_sub(inner.height));
+        layout.detail_area = Some(area);
+
+        let panel = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_set(glyphs.borders)
+                    .title("Details")
+                    .style(theme.border),
             )
-            .highlight_symbol("► ");
+            .wrap(Wrap { trim: false })
+            .scroll((layout.detail_scroll, 0));
 
-        f.render_stateful_widget(list, area, &mut list_state);
+        f.render_widget(panel, area);
     }
 
-    fn render_footer(f: &mut Frame, area: ratatui::layout::Rect, _state: &MenuState) {
-        let help_text = "Navigation: ↑↓/jk=Move | Enter=Select | 1-9=Quick | X=Back | H=Home | S=Search | Q=Quit | F1=Help";
+    /// Split `text` into spans, emphasising the characters covered by the
+    /// fuzzy matcher's (character index) ranges.
+    fn highlight_spans(text: &str, ranges: &[(usize, usize)], style: Style, theme: &Theme) -> Vec<Span<'static>> {
+        if ranges.is_empty() {
+            return vec![Span::styled(text.to_string(), style)];
+        }
+
+        let highlight = style.patch(theme.matched);
+        let mut spans = Vec::new();
+        let mut current = String::new();
+        let mut current_highlighted = false;
+
+        for (i, c) in text.chars().enumerate() {
+            let highlighted = ranges.iter().any(|&(start, end)| i >= start && i < end);
+            if highlighted != current_highlighted && !current.is_empty() {
+                let span_style = if current_highlighted { highlight } else { style };
+                spans.push(Span::styled(std::mem::take(&mut current), span_style));
+            }
+            current_highlighted = highlighted;
+            current.push(c);
+        }
+
+        if !current.is_empty() {
+            spans.push(Span::styled(current, if current_highlighted { highlight } else { style }));
+        }
+        spans
+    }
+
+    /// Describe matches in fields that the result line does not show,
+    /// e.g. `tag backup` or `author`.
+    fn hidden_matches(script: &Script, result: &SearchResult) -> Vec<String> {
+        result
+            .matched_fields
+            .iter()
+            .filter_map(|field| match field.as_str() {
+                "name" if script.menu_name.is_some() => Some("file name".to_string()),
+                "detailed_description" => Some("details".to_string()),
+                "author" => Some(format!("author {}", script.author.as_deref().unwrap_or(""))),
+                field => field
+                    .strip_prefix("tag_")
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .and_then(|index| script.tags.get(index))
+                    .map(|tag| format!("tag {}", tag)),
+            })
+            .collect()
+    }
+
+    fn render_footer(f: &mut Frame, area: ratatui::layout::Rect, state: &MenuState, keymap: &Keymap, theme: &Theme, glyphs: &Glyphs) {
+        let help_text = format!(
+            "Navigation: {}/{}=Move | {}=Select | 1-9=Quick | {}=Back | {}=Home | {}=Search | {}=Quit | {}=Help | {}=History",
+            keymap.primary_key(Action::Up, glyphs),
+            keymap.primary_key(Action::Down, glyphs),
+            keymap.primary_key(Action::Select, glyphs),
+            keymap.primary_key(Action::Back, glyphs),
+            keymap.primary_key(Action::Home, glyphs),
+            keymap.primary_key(Action::Search, glyphs),
+            keymap.primary_key(Action::Quit, glyphs),
+            keymap.primary_key(Action::Help, glyphs),
+            keymap.primary_key(Action::History, glyphs),
+        );
+
+        let (text, style) = match &state.status_message {
+            Some(message) => (message.as_str(), theme.status),
+            None => (help_text.as_str(), theme.muted),
+        };
         
-        let footer = Paragraph::new(help_text)
-            .style(Style::default().fg(Color::DarkGray))
+        let footer = Paragraph::new(text)
+            .style(style)
             .alignment(Alignment::Center)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .style(Style::default().fg(Color::White)),
+                    .border_set(glyphs.borders)
+                    .style(theme.border),
             )
             .wrap(Wrap { trim: true });
         
         f.render_widget(footer, area);
     }
 
-    fn render_search_footer(f: &mut Frame, area: ratatui::layout::Rect) {
-        let help_text = "Type to search | Enter=Select | X/Esc=Exit Search | ↑↓=Navigate";
+    fn render_search_footer(f: &mut Frame, area: ratatui::layout::Rect, keymap: &Keymap, theme: &Theme, glyphs: &Glyphs) {
+        let help_text = format!(
+            "Type to search | {}=Complete | {}=Bodies | {}=Select | {}=Exit Search | {}/{}=Navigate",
+            keymap.primary_key(Action::Complete, glyphs),
+            keymap.primary_key(Action::ToggleBodies, glyphs),
+            keymap.primary_key(Action::Select, glyphs),
+            keymap.primary_key(Action::Cancel, glyphs),
+            keymap.primary_key(Action::Up, glyphs),
+            keymap.primary_key(Action::Down, glyphs),
+        );
         
         let footer = Paragraph::new(help_text)
-            .style(Style::default().fg(Color::DarkGray))
+            .style(theme.muted)
             .alignment(Alignment::Center)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .style(Style::default().fg(Color::Yellow)),
+                    .border_set(glyphs.borders)
+                    .style(theme.search_border),
             );
         
         f.render_widget(footer, area);
     }
 
-    fn get_script_color(script: &Script) -> Color {
-        if let Some(color_code) = &script.color {
-            match color_code.as_str() {
-                "Z1" => Color::Red,     // Dangerous
-                "Z2" => Color::Green,   // Safe
-                "Z3" => Color::Yellow,  // Warning
-                "Z4" => Color::Blue,    // Info
-                _ => Color::White,
-            }
+    /// List title with the number of scripts marked for a batch run.
+    fn marked_title(title: String, state: &MenuState) -> String {
+        if state.marked.is_empty() {
+            title
         } else {
-            Color::White
+            format!("{} - {} marked", title, state.marked.len())
         }
     }
 
+    /// Bottom title of the menu listing the Z-codes used by the visible
+    /// scripts, each in its own style.
+    fn zcode_legend(items: &[MenuItem], theme: &Theme, glyphs: &Glyphs) -> Title<'static> {
+        let mut spans = Vec::new();
+        for zcode in &theme.zcodes {
+            let used = items.iter().any(|item| {
+                matches!(item, MenuItem::Script(script) if script.color.as_deref() == Some(zcode.code.as_str()))
+            });
+            if used {
+                spans.push(Span::raw(" "));
+                spans.push(Span::styled(format!("{} {}", glyphs.swatch, zcode.label), zcode.style));
+            }
+        }
+        if !spans.is_empty() {
+            spans.push(Span::raw(" "));
+        }
+
+        Title::from(Line::from(spans))
+            .position(Position::Bottom)
+            .alignment(Alignment::Right)
+    }
+
     pub fn show_help_dialog(&mut self) -> Result<()> {
+        let (keymap, theme, glyphs) = (&self.keymap, &self.theme, &self.glyphs);
+
+        let mut help_text = vec![format!("{} Toolbox Menu Help", glyphs.app), String::new(), "Navigation:".to_string()];
+        for action in [Action::Up, Action::Down, Action::PageUp, Action::PageDown, Action::First, Action::Last, Action::Select] {
+            help_text.push(keymap.help_line(action, glyphs));
+        }
+        help_text.push(format!("  {:<14} - {}", "1-9, 0", "Quick select by number"));
+
+        help_text.extend([String::new(), "Shortcuts:".to_string()]);
+        for action in [Action::Back, Action::Home, Action::Search, Action::History, Action::Help, Action::Quit, Action::Cancel] {
+            help_text.push(keymap.help_line(action, glyphs));
+        }
+
+        help_text.extend([
+            String::new(),
+            "Search Mode:".to_string(),
+            "  Type to search scripts by name/description; letters and digits".to_string(),
+            "  always go into the query".to_string(),
+            "  tag:backup cat:LinuxTools author:\"Toolbox Team\" body:\"certbot renew\"".to_string(),
+            "  is:dangerous|available|sudo|default  has:params|tags|info  source:LABEL".to_string(),
+            "  Prefix a qualifier with - to exclude, e.g. -tag:experimental".to_string(),
+        ]);
+        for action in [Action::Complete, Action::ToggleBodies, Action::Cancel] {
+            help_text.push(keymap.help_line(action, glyphs));
+        }
+
+        help_text.extend([String::new(), "Batch Runs:".to_string()]);
+        for action in [Action::Mark, Action::RunMarked] {
+            help_text.push(keymap.help_line(action, glyphs));
+        }
+        help_text.extend([
+            "  Marked scripts run one after another or in parallel, either".to_string(),
+            "  stopping at the first failure or carrying on; parameters are".to_string(),
+            "  asked for before the batch starts".to_string(),
+        ]);
+
+        help_text.extend([
+            String::new(),
+            "Mouse:".to_string(),
+            format!("  {:<14} - {}", "Click", "Select an entry or jump to a breadcrumb"),
+            format!("  {:<14} - {}", "Double-click", "Run the entry"),
+            format!("  {:<14} - {}", "Wheel", "Move through the list, or scroll the details"),
+        ]);
+
+        help_text.extend([String::new(), "Script Features:".to_string()]);
+        let mut lines: Vec<Line> = help_text.into_iter().map(Line::from).collect();
+
+        // Colour legend from the active theme, including custom Z-codes
+        for zcode in &theme.zcodes {
+            lines.push(Line::from(vec![
+                Span::raw("  "),
+                Span::styled(format!("{} {:<10}", glyphs.swatch, zcode.code), zcode.style),
+                Span::raw(format!("- {}", zcode.label)),
+            ]));
+        }
+        lines.extend(
+            [
+                format!("  {} - Requires root (runs through sudo)", glyphs::pad(glyphs.sudo, 12)),
+                String::new(),
+                format!(
+                    "Scroll with the wheel or {}/{}; press any other key to close this help...",
+                    keymap.primary_key(Action::Up, glyphs),
+                    keymap.primary_key(Action::Down, glyphs),
+                ),
+            ]
+            .map(Line::from),
+        );
+
+        if let Some(terminal) = &mut self.terminal {
+            let mut scroll: u16 = 0;
+            loop {
+                let mut max_scroll = 0;
+                let mut popup = Rect::default();
+                terminal.draw(|f| {
+                    let area = f.size();
+
+                    // Create a centered popup
+                    let popup_area = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([
+                            Constraint::Percentage(5),
+                            Constraint::Percentage(90),
+                            Constraint::Percentage(5),
+                        ])
+                        .split(area)[1];
+
+                    let popup_area = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([
+                            Constraint::Percentage(10),
+                            Constraint::Percentage(80),
+                            Constraint::Percentage(10),
+                        ])
+                        .split(popup_area)[1];
+
+                    max_scroll = (lines.len() as u16).saturating_sub(popup_area.height.saturating_sub(2));
+                    scroll = scroll.min(max_scroll);
+                    popup = popup_area;
+
+                    f.render_widget(ClearWidget, popup_area);
+
+                    let help_paragraph = Paragraph::new(lines.clone())
+                        .style(theme.text)
+                        .block(
+                            Block::default()
+                                .borders(Borders::ALL)
+                                .border_set(glyphs.borders)
+                                .title(format!("Help ({} keys, {} theme)", keymap.preset_name(), theme.name))
+                                .style(theme.header),
+                        )
+                        .wrap(Wrap { trim: true })
+                        .scroll((scroll, 0));
+
+                    f.render_widget(help_paragraph, popup_area);
+                })?;
+
+                // Scroll keys and the wheel move the text; any other key or a
+                // click outside the popup closes it
+                match event::read()? {
+                    Event::Key(key) => match keymap.action_for(&key) {
+                        Some(Action::Up) => scroll = scroll.saturating_sub(1),
+                        Some(Action::Down) => scroll = (scroll + 1).min(max_scroll),
+                        Some(Action::PageUp) => scroll = scroll.saturating_sub(popup.height / 2),
+                        Some(Action::PageDown) => scroll = (scroll + popup.height / 2).min(max_scroll),
+                        _ => break,
+                    },
+                    Event::Mouse(mouse) => match mouse.kind {
+                        MouseEventKind::ScrollUp => scroll = scroll.saturating_sub(WHEEL_SCROLL_LINES),
+                        MouseEventKind::ScrollDown => scroll = (scroll + WHEEL_SCROLL_LINES).min(max_scroll),
+                        MouseEventKind::Down(_) if !contains(popup, mouse.column, mouse.row) => break,
+                        _ => {}
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn show_history_dialog(&mut self, records: &[ExecutionRecord]) -> Result<()> {
+        let (theme, glyphs) = (&self.theme, &self.glyphs);
         if let Some(terminal) = &mut self.terminal {
             terminal.draw(|f| {
                 let area = f.size();
-                
-                // Create a centered popup
+
                 let popup_area = Layout::default()
                     .direction(Direction::Vertical)
                     .constraints([
-                        Constraint::Percentage(20),
-                        Constraint::Percentage(60),
-                        Constraint::Percentage(20),
+                        Constraint::Percentage(10),
+                        Constraint::Percentage(80),
+                        Constraint::Percentage(10),
                     ])
                     .split(area)[1];
-                
+
                 let popup_area = Layout::default()
                     .direction(Direction::Horizontal)
                     .constraints([
-                        Constraint::Percentage(10),
-                        Constraint::Percentage(80),
-                        Constraint::Percentage(10),
+                        Constraint::Percentage(5),
+                        Constraint::Percentage(90),
+                        Constraint::Percentage(5),
                     ])
                     .split(popup_area)[1];
 
                 f.render_widget(ClearWidget, popup_area);
 
-                let help_text = vec![
-                    "🛡️ Toolbox Menu Help",
-                    "",
-                    "Navigation:",
-                    "  ↑↓ or j/k    - Move selection up/down",
-                    "  Enter        - Execute selected item",
-                    "  1-9, 0       - Quick select by number",
-                    "  Page Up/Down - Move 10 items at once",
-                    "  Home/End     - Go to first/last item",
-                    "",
-                    "Shortcuts:",
-                    "  X            - Go back to previous menu",
-                    "  H            - Go to home menu",
-                    "  S            - Enter search mode",
-                    "  Q or Ctrl+Q  - Quit application",
-                    "  Esc          - Exit current mode/quit",
-                    "",
-                    "Search Mode:",
-                    "  Type to search scripts by name/description",
-                    "  X or Esc     - Exit search mode",
-                    "",
-                    "Script Features:",
-                    "  🔴 Red       - Dangerous operations",
-                    "  🟡 Yellow    - Caution required",
-                    "  🟢 Green     - Safe operations",
-                    "  🔵 Blue      - Information/utilities",
-                    "",
-                    "Press any key to close this help..."
-                ];
-
-                let help_paragraph = Paragraph::new(help_text.join("\n"))
-                    .style(Style::default().fg(Color::White))
-                    .block(
-                        Block::default()
-                            .borders(Borders::ALL)
-                            .title("Help")
-                            .style(Style::default().fg(Color::Cyan)),
-                    )
-                    .wrap(Wrap { trim: true });
-
-                f.render_widget(help_paragraph, popup_area);
+                let items: Vec<ListItem> = if records.is_empty() {
+                    vec![ListItem::new("No scripts have been executed yet")]
+                } else {
+                    let mut items = Vec::new();
+                    let mut current_batch = None;
+                    for record in records {
+                        let batch_id = record.batch.as_ref().map(|batch| batch.id);
+                        if let Some(batch) = record.batch.as_ref().filter(|_| batch_id != current_batch) {
+                            items.push(ListItem::new(Line::from(Span::styled(
+                                format!("{} {}", glyphs.package, batch.describe()),
+                                theme.muted,
+                            ))));
+                        }
+                        current_batch = batch_id;
+
+                        let gutter = if batch_id.is_some() {
+                            format!("{} ", glyphs.gutter)
+                        } else {
+                            "  ".to_string()
+                        };
+                        items.push({
+                            let outcome = &record.outcome;
+                            let outcome_style = if outcome.success() {
+                                theme.success
+                            } else if outcome.timed_out || outcome.cancelled {
+                                theme.warning
+                            } else {
+                                theme.error
+                            };
+
+                            ListItem::new(Line::from(vec![
+                                Span::styled(gutter, theme.muted),
+                                Span::styled(
+                                    outcome.started_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string(),
+                                    theme.muted,
+                                ),
+                                Span::raw("  "),
+                                Span::styled(format!("{:<28}", outcome.describe()), outcome_style),
+                                Span::styled(
+                                    format!("{:>8.1}s  ", outcome.duration_ms() as f64 / 1000.0),
+                                    theme.muted,
+                                ),
+                                Span::styled(record.script_name.clone(), theme.text),
+                                Span::styled(
+                                    record
+                                        .revision
+                                        .as_deref()
+                                        .map(|revision| format!(" @ {}", sources::short(revision)))
+                                        .unwrap_or_default(),
+                                    theme.muted,
+                                ),
+                                Span::styled(
+                                    format!("  {}@{}", outcome.user, outcome.hostname),
+                                    theme.muted,
+                                ),
+                            ]))
+                        });
+                    }
+                    items
+                };
+
+                let list = List::new(items).block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_set(glyphs.borders)
+                        .title("Execution History (press any key to close)")
+                        .style(theme.header),
+                );
+
+                f.render_widget(list, popup_area);
             })?;
 
             // Wait for any key press
@@ -428,11 +1108,13 @@ impl MenuUI {
                 }
             }
         }
-        
+
         Ok(())
     }
 
+    #[allow(dead_code)]
     pub fn show_progress_bar(&mut self, title: &str, progress: f64) -> Result<()> {
+        let (theme, glyphs) = (&self.theme, &self.glyphs);
         if let Some(terminal) = &mut self.terminal {
             terminal.draw(|f| {
                 let area = f.size();
@@ -449,8 +1131,8 @@ impl MenuUI {
                 f.render_widget(ClearWidget, popup_area);
 
                 let gauge = Gauge::default()
-                    .block(Block::default().borders(Borders::ALL).title(title))
-                    .gauge_style(Style::default().fg(Color::Cyan))
+                    .block(Block::default().borders(Borders::ALL).border_set(glyphs.borders).title(title))
+                    .gauge_style(theme.search)
                     .percent((progress * 100.0) as u16);
 
                 f.render_widget(gauge, popup_area);
@@ -459,4 +1141,8 @@ impl MenuUI {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    column >= area.x && column < area.x + area.width && row >= area.y && row < area.y + area.height
+}
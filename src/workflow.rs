@@ -0,0 +1,958 @@
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::batch::{self, cancelled, FailurePolicy, Summary};
+use crate::database::Database;
+use crate::display::ScriptExecutor;
+use crate::glyphs::Glyphs;
+use crate::models::{ExecutionOutcome, ParameterType, Script, ScriptParameter};
+use crate::params::{self, ParameterValues, Schema};
+use crate::roots::Roots;
+
+/// Workflow files live alongside the scripts and are found by the scan.
+pub const WORKFLOW_SUFFIX: &str = ".workflow.toml";
+
+/// A workflow file as written:
+///
+/// ```toml
+/// name = "Renew certificates"
+/// description = "Check expiry and renew when due"
+///
+/// [params.domain]
+/// label = "Domain"
+/// required = true
+///
+/// [[steps]]
+/// id = "check"
+/// script = "Certificates/check_expiry.sh"
+/// with = { domain = "${params.domain}", days = 30 }
+///
+/// [[steps]]
+/// id = "renew"
+/// script = "Certificates/renew.sh"
+/// when = "steps.check.outputs.DUE == 'yes'"
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkflowFile {
+    name: Option<String>,
+    description: Option<String>,
+    icon: Option<String>,
+    order: Option<i32>,
+    category: Option<String>,
+    #[serde(default)]
+    on_failure: FailurePolicy,
+    #[serde(default)]
+    params: Schema,
+    steps: Vec<StepFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepFile {
+    id: Option<String>,
+    name: Option<String>,
+    script: String,
+    #[serde(default)]
+    with: BTreeMap<String, toml::Value>,
+    when: Option<String>,
+    #[serde(default)]
+    continue_on_error: bool,
+}
+
+/// A workflow file, checked and ready to run.
+pub struct WorkflowDefinition {
+    pub path: PathBuf,
+    pub name: String,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub order: Option<i32>,
+    pub category: Option<String>,
+    pub on_failure: FailurePolicy,
+    /// Asked for once when the workflow starts; steps use them as `${params.NAME}`.
+    pub params: Vec<ScriptParameter>,
+    pub steps: Vec<Step>,
+}
+
+pub struct Step {
+    pub id: String,
+    pub name: Option<String>,
+    /// Script path, relative to the workflow file or the toolbox directory.
+    pub script: String,
+    /// Fixed parameter values, which may contain `${...}` references.
+    pub with: Vec<(String, String)>,
+    pub when: Option<Condition>,
+    pub continue_on_error: bool,
+}
+
+impl Step {
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// Something a condition or `${...}` template can refer to.
+#[derive(Debug, Clone, PartialEq)]
+enum Reference {
+    Param(String),
+    Step { id: String, field: StepField },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum StepField {
+    ExitCode,
+    Success,
+    Failed,
+    Skipped,
+    Output(String),
+}
+
+impl Reference {
+    /// `params.NAME`, or `steps.ID.` followed by `exit_code`, `success`,
+    /// `failed`, `skipped` or `outputs.NAME`.
+    fn parse(text: &str) -> std::result::Result<Self, String> {
+        let parts: Vec<&str> = text.split('.').collect();
+        match parts.as_slice() {
+            ["params", name] => Ok(Reference::Param(name.to_string())),
+            ["steps", id, field] => {
+                let field = match *field {
+                    "exit_code" => StepField::ExitCode,
+                    "success" => StepField::Success,
+                    "failed" => StepField::Failed,
+                    "skipped" => StepField::Skipped,
+                    _ => return Err(format!("unknown step field in '{}'", text)),
+                };
+                Ok(Reference::Step { id: id.to_string(), field })
+            }
+            ["steps", id, "outputs", name] => Ok(Reference::Step {
+                id: id.to_string(),
+                field: StepField::Output(name.to_string()),
+            }),
+            _ => Err(format!("'{}' is not params.NAME or steps.ID.FIELD", text)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    Reference(Reference),
+    Literal(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Clause {
+    negated: bool,
+    left: Operand,
+    comparison: Option<(Comparison, Operand)>,
+}
+
+/// A step's `when` expression, e.g. `steps.check.exit_code == 1 && params.force`.
+/// Clauses are joined with `&&` and `||` (`&&` binds tighter); a clause is a
+/// comparison, or a single value that is true unless empty, `false` or `0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    /// Alternatives joined by `||`, each a list of clauses joined by `&&`.
+    any: Vec<Vec<Clause>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Operator(&'static str),
+}
+
+const OPERATORS: [&str; 9] = ["==", "!=", "<=", ">=", "&&", "||", "<", ">", "!"];
+
+fn tokenize(text: &str) -> std::result::Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(operator) = OPERATORS.iter().find(|operator| rest.starts_with(**operator)) {
+            tokens.push(Token::Operator(operator));
+            rest = &rest[operator.len()..];
+        } else if c == '\'' || c == '"' {
+            let end = rest[1..].find(c).ok_or_else(|| format!("unterminated string in '{}'", text))?;
+            tokens.push(Token::Quoted(rest[1..end + 1].to_string()));
+            rest = &rest[end + 2..];
+        } else if c.is_alphanumeric() || matches!(c, '_' | '.' | '-') {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '.' | '-')))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Word(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            return Err(format!("unexpected '{}' in '{}'", c, text));
+        }
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> std::result::Result<Self, String> {
+        let tokens = tokenize(text)?;
+        let mut any = Vec::new();
+
+        for alternative in tokens.split(|token| *token == Token::Operator("||")) {
+            let mut all = Vec::new();
+            for clause in alternative.split(|token| *token == Token::Operator("&&")) {
+                all.push(Self::parse_clause(clause, text)?);
+            }
+            any.push(all);
+        }
+
+        Ok(Condition { any })
+    }
+
+    fn parse_clause(mut tokens: &[Token], text: &str) -> std::result::Result<Clause, String> {
+        let mut negated = false;
+        while let [Token::Operator("!"), rest @ ..] = tokens {
+            negated = !negated;
+            tokens = rest;
+        }
+
+        let operand = |token: &Token| -> std::result::Result<Operand, String> {
+            match token {
+                Token::Word(word) if word.starts_with("steps.") || word.starts_with("params.") => {
+                    Reference::parse(word).map(Operand::Reference)
+                }
+                Token::Word(word) | Token::Quoted(word) => Ok(Operand::Literal(word.clone())),
+                Token::Operator(operator) => Err(format!("unexpected '{}' in '{}'", operator, text)),
+            }
+        };
+
+        match tokens {
+            [left] => Ok(Clause { negated, left: operand(left)?, comparison: None }),
+            [left, Token::Operator(operator), right] => {
+                let comparison = match *operator {
+                    "==" => Comparison::Equal,
+                    "!=" => Comparison::NotEqual,
+                    "<" => Comparison::Less,
+                    "<=" => Comparison::LessOrEqual,
+                    ">" => Comparison::Greater,
+                    ">=" => Comparison::GreaterOrEqual,
+                    _ => return Err(format!("unexpected '{}' in '{}'", operator, text)),
+                };
+                Ok(Clause { negated, left: operand(left)?, comparison: Some((comparison, operand(right)?)) })
+            }
+            [] => Err(format!("missing value in '{}'", text)),
+            _ => Err(format!("expected VALUE or VALUE OP VALUE in '{}'", text)),
+        }
+    }
+
+    fn references(&self) -> impl Iterator<Item = &Reference> {
+        self.any.iter().flatten().flat_map(|clause| {
+            std::iter::once(&clause.left)
+                .chain(clause.comparison.as_ref().map(|(_, right)| right))
+                .filter_map(|operand| match operand {
+                    Operand::Reference(reference) => Some(reference),
+                    Operand::Literal(_) => None,
+                })
+        })
+    }
+
+    fn evaluate(&self, context: &RunContext) -> bool {
+        let value = |operand: &Operand| match operand {
+            Operand::Reference(reference) => context.resolve(reference).unwrap_or_default(),
+            Operand::Literal(literal) => literal.clone(),
+        };
+
+        self.any.iter().any(|all| {
+            all.iter().all(|clause| {
+                let left = value(&clause.left);
+                let result = match &clause.comparison {
+                    None => is_truthy(&left),
+                    Some((comparison, right)) => compare(&left, *comparison, &value(right)),
+                };
+                result != clause.negated
+            })
+        })
+    }
+}
+
+fn is_truthy(value: &str) -> bool {
+    !(value.is_empty() || value == "false" || value == "0")
+}
+
+/// Numbers compare as numbers; anything else only as equal or not.
+fn compare(left: &str, comparison: Comparison, right: &str) -> bool {
+    if let (Ok(left), Ok(right)) = (left.parse::<f64>(), right.parse::<f64>()) {
+        return match comparison {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+        };
+    }
+    match comparison {
+        Comparison::Equal => left == right,
+        Comparison::NotEqual => left != right,
+        _ => false,
+    }
+}
+
+fn template_pattern() -> Regex {
+    Regex::new(r"\$\{\s*([^}]*?)\s*\}").unwrap()
+}
+
+/// Read and check a workflow file. Every reference must name a declared
+/// parameter or a step that runs earlier, so mistakes are reported before
+/// anything runs rather than halfway through.
+pub fn load(path: &Path) -> Result<WorkflowDefinition> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read workflow: {}", path.display()))?;
+    let file: WorkflowFile = toml::from_str(&content)
+        .with_context(|| format!("Invalid workflow file: {}", path.display()))?;
+
+    if file.steps.is_empty() {
+        bail!("Workflow {} has no steps", path.display());
+    }
+
+    let params = file.params.into_parameters();
+    let template = template_pattern();
+    let mut steps: Vec<Step> = Vec::new();
+
+    for (index, step) in file.steps.into_iter().enumerate() {
+        let id = step.id.unwrap_or_else(|| format!("step{}", index + 1));
+        if id.is_empty() || !id.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+            bail!("Step id '{}' may only contain letters, digits, '_' and '-'", id);
+        }
+        if steps.iter().any(|earlier| earlier.id == id) {
+            bail!("Step id '{}' is used more than once", id);
+        }
+
+        let check = |reference: &Reference| -> Result<()> {
+            match reference {
+                Reference::Param(name) if !params.iter().any(|param| &param.name == name) => {
+                    bail!("Step '{}' refers to params.{}, which is not declared under [params]", id, name)
+                }
+                Reference::Step { id: step_id, .. } if !steps.iter().any(|earlier| &earlier.id == step_id) => {
+                    bail!("Step '{}' refers to step '{}', which does not run before it", id, step_id)
+                }
+                _ => Ok(()),
+            }
+        };
+
+        let mut with = Vec::new();
+        for (name, value) in step.with {
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => value.to_string(),
+                _ => bail!("Step '{}': parameter '{}' must be a string, number or boolean", id, name),
+            };
+            for captures in template.captures_iter(&value) {
+                let reference = Reference::parse(&captures[1]).map_err(|e| anyhow!("Step '{}': {}", id, e))?;
+                check(&reference)?;
+            }
+            with.push((name, value));
+        }
+
+        let when = match step.when {
+            Some(text) => {
+                let condition = Condition::parse(&text).map_err(|e| anyhow!("Step '{}': invalid when: {}", id, e))?;
+                for reference in condition.references() {
+                    check(reference)?;
+                }
+                Some(condition)
+            }
+            None => None,
+        };
+
+        steps.push(Step {
+            id,
+            name: step.name,
+            script: step.script,
+            with,
+            when,
+            continue_on_error: step.continue_on_error,
+        });
+    }
+
+    let name = file.name.unwrap_or_else(|| {
+        let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("workflow");
+        file_name.strip_suffix(WORKFLOW_SUFFIX).unwrap_or(file_name).to_string()
+    });
+
+    Ok(WorkflowDefinition {
+        path: path.to_path_buf(),
+        name,
+        description: file.description,
+        icon: file.icon,
+        order: file.order,
+        category: file.category,
+        on_failure: file.on_failure,
+        params,
+        steps,
+    })
+}
+
+/// Find a workflow given on the command line as a file path (absolute,
+/// relative to the current directory or a toolbox root) or by name.
+pub async fn locate(database: &Database, roots: &Roots, target: &str) -> Result<Option<PathBuf>> {
+    if target.ends_with(WORKFLOW_SUFFIX) {
+        for candidate in [PathBuf::from(target), roots.resolve(Path::new(target))] {
+            if candidate.is_file() {
+                return Ok(Some(candidate));
+            }
+        }
+    }
+
+    Ok(database.find_workflow(target).await?.map(|workflow| workflow.path))
+}
+
+/// The names `--param` accepts for a workflow, with their definitions: the
+/// workflow's own parameters, then `STEP.NAME` for each step parameter the
+/// workflow does not fix with `with`.
+pub async fn preset_names(
+    database: &Database,
+    roots: &Roots,
+    path: &Path,
+) -> Result<Vec<(String, ScriptParameter)>> {
+    let definition = load(path)?;
+    let mut names: Vec<(String, ScriptParameter)> =
+        definition.params.iter().map(|param| (param.name.clone(), param.clone())).collect();
+
+    for step in &definition.steps {
+        let script = definition.find_script(database, roots, step).await?;
+        for parameter in script.parse_parameters().unwrap_or_default() {
+            if !step.with.iter().any(|(name, _)| name == &parameter.name) {
+                names.push((format!("{}.{}", step.id, parameter.name), parameter));
+            }
+        }
+    }
+    Ok(names)
+}
+
+impl WorkflowDefinition {
+    /// Step scripts are looked up next to the workflow file first, then in
+    /// the toolbox roots, highest first.
+    fn script_path(&self, roots: &Roots, script: &str) -> PathBuf {
+        let script = Path::new(script);
+        if script.is_absolute() {
+            return script.to_path_buf();
+        }
+        let beside = self.path.parent().unwrap_or(Path::new("")).join(script);
+        if beside.is_file() {
+            beside
+        } else {
+            roots.resolve(script)
+        }
+    }
+
+    async fn find_script(&self, database: &Database, roots: &Roots, step: &Step) -> Result<Script> {
+        let path = self.script_path(roots, &step.script);
+        for candidate in [path.clone(), normalize_path(&path)] {
+            if let Some(script) = database.get_script_by_path(&candidate.to_string_lossy()).await? {
+                return Ok(script);
+            }
+        }
+        bail!(
+            "Step '{}': {} is not in the index - check the path or run toolbox --scan",
+            step.id,
+            path.display()
+        )
+    }
+}
+
+/// Remove `.` and `..` components without touching the file system.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+enum StepResult {
+    Ran(ExecutionOutcome),
+    Skipped,
+    /// The step could not be started, e.g. a reference had no value.
+    Error(String),
+}
+
+impl StepResult {
+    fn failed(&self) -> bool {
+        match self {
+            StepResult::Ran(outcome) => !outcome.success(),
+            StepResult::Skipped => false,
+            StepResult::Error(_) => true,
+        }
+    }
+}
+
+/// What the steps so far produced, for conditions and templates.
+struct RunContext<'a> {
+    params: &'a ParameterValues,
+    results: HashMap<String, StepResult>,
+    outputs: HashMap<String, HashMap<String, String>>,
+}
+
+impl RunContext<'_> {
+    fn resolve(&self, reference: &Reference) -> Option<String> {
+        match reference {
+            Reference::Param(name) => self.params.get(name).map(str::to_string),
+            Reference::Step { id, field } => {
+                let result = self.results.get(id);
+                let flag = |value: bool| Some(value.to_string());
+                match field {
+                    StepField::ExitCode => match result {
+                        Some(StepResult::Ran(outcome)) => outcome.exit_code.map(|code| code.to_string()),
+                        _ => None,
+                    },
+                    StepField::Success => flag(matches!(result, Some(StepResult::Ran(outcome)) if outcome.success())),
+                    StepField::Failed => flag(result.is_some_and(StepResult::failed)),
+                    StepField::Skipped => flag(matches!(result, Some(StepResult::Skipped))),
+                    StepField::Output(name) => self.outputs.get(id).and_then(|outputs| outputs.get(name)).cloned(),
+                }
+            }
+        }
+    }
+
+    /// Substitute `${...}` references in a `with` value. The flag is set when
+    /// a password parameter went into the value.
+    fn expand(&self, template: &Regex, value: &str) -> std::result::Result<(String, bool), String> {
+        let mut secret = false;
+        let mut missing = None;
+
+        let expanded = template.replace_all(value, |captures: &regex::Captures| {
+            let reference = Reference::parse(&captures[1]).expect("checked when loading");
+            if let Reference::Param(name) = &reference {
+                secret |= self.params.is_secret(name);
+            }
+            self.resolve(&reference).unwrap_or_else(|| {
+                missing.get_or_insert_with(|| captures[0].to_string());
+                String::new()
+            })
+        });
+
+        match missing {
+            Some(reference) => Err(format!("{} has no value", reference)),
+            None => Ok((expanded.into_owned(), secret)),
+        }
+    }
+}
+
+/// `NAME=value` lines a step wrote to `$TOOLBOX_OUTPUT`.
+fn read_outputs(path: &Path) -> HashMap<String, String> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), value.to_string()))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+/// Values given with `--param`, sorted into the workflow's own parameters
+/// (`NAME=VALUE`) and each step's (`STEP.NAME=VALUE`).
+struct Presets<'a> {
+    workflow: Vec<(&'a str, &'a str)>,
+    steps: Vec<Vec<(&'a str, &'a str)>>,
+}
+
+fn find_preset<'a>(presets: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    presets.iter().find(|(preset, _)| *preset == name).map(|(_, value)| *value)
+}
+
+/// Sort `--param` values, checking that each names an existing parameter.
+fn split_presets<'a>(
+    definition: &WorkflowDefinition,
+    scripts: &[(Script, Vec<ScriptParameter>)],
+    presets: &'a [(String, String)],
+) -> Result<Presets<'a>> {
+    let mut workflow_presets = Vec::new();
+    let mut step_presets = vec![Vec::new(); definition.steps.len()];
+
+    for (name, value) in presets {
+        match name.split_once('.') {
+            None if definition.params.iter().any(|param| &param.name == name) => {
+                workflow_presets.push((name.as_str(), value.as_str()))
+            }
+            None => bail!("Workflow '{}' has no parameter '{}'", definition.name, name),
+            Some((step_id, param_name)) => {
+                let Some(index) = definition.steps.iter().position(|step| step.id == step_id) else {
+                    bail!("Workflow '{}' has no step '{}'", definition.name, step_id);
+                };
+                if !scripts[index].1.iter().any(|param| param.name == param_name) {
+                    bail!("Step '{}' has no parameter '{}'", step_id, param_name);
+                }
+                step_presets[index].push((param_name, value.as_str()));
+            }
+        }
+    }
+
+    Ok(Presets { workflow: workflow_presets, steps: step_presets })
+}
+
+/// Run a workflow. Its parameters, and those of each step's script that the
+/// workflow does not fill in, are asked for before the first step starts
+/// (unless given as `presets`). Steps run in order; each step's run is
+/// recorded in history under one `batch_runs` row for the workflow.
+///
+/// Returns `None` when the workflow was cancelled before anything ran,
+/// otherwise whether it succeeded.
+pub async fn run(
+    executor: &ScriptExecutor,
+    database: &Database,
+    roots: &Roots,
+    path: &Path,
+    presets: &[(String, String)],
+    glyphs: &Glyphs,
+) -> Result<Option<bool>> {
+    let definition = load(path)?;
+
+    let mut scripts = Vec::new();
+    for step in &definition.steps {
+        let script = definition.find_script(database, roots, step).await?;
+        let parameters = script
+            .parse_parameters()
+            .with_context(|| format!("Invalid parameter definitions in {}", script.path.display()))?;
+        if let Some((name, _)) = step.with.iter().find(|(name, _)| !parameters.iter().any(|param| &param.name == name)) {
+            bail!("Step '{}' has no parameter '{}'", step.id, name);
+        }
+        scripts.push((script, parameters));
+    }
+    let presets = split_presets(&definition, &scripts, presets)?;
+
+    println!("{} Workflow: {}", glyphs.workflow, definition.name);
+    if let Some(description) = &definition.description {
+        println!("{} {}", glyphs.description, description);
+    }
+    for (i, (step, (script, _))) in definition.steps.iter().zip(&scripts).enumerate() {
+        println!("  {:>2}. {} ({})", i + 1, step.label(), script.display_name());
+    }
+    println!();
+
+    // Refuse the whole workflow rather than stop at a step the user may not run
+    if let Some((script, denied)) = scripts.iter().find_map(|(script, _)| Some((script, executor.access_denied(script)?))) {
+        println!("{} {}", glyphs.unavailable, denied);
+        executor.refuse(script, &ParameterValues::default(), None, &denied);
+        return cancelled("Workflow", glyphs);
+    }
+
+    // Workflow parameters
+    let mut values = ParameterValues::default();
+    let mut unanswered = Vec::new();
+    for parameter in &definition.params {
+        match find_preset(&presets.workflow, &parameter.name) {
+            Some(value) => params::apply_preset(&mut values, parameter, value)?,
+            None => unanswered.push(parameter.clone()),
+        }
+    }
+    let heading = format!("Parameters for {}", definition.name);
+    let Some(prompted) = params::prompt_all(&heading, &unanswered, glyphs)? else {
+        return cancelled("Workflow", glyphs);
+    };
+    for parameter in &unanswered {
+        let value = prompted.get(&parameter.name).unwrap_or_default().to_string();
+        values.insert(&parameter.name, value, prompted.is_secret(&parameter.name));
+    }
+
+    // Step parameters not fixed by the workflow
+    let mut step_values = Vec::new();
+    for (i, (step, (script, parameters))) in definition.steps.iter().zip(&scripts).enumerate() {
+        let mut given = ParameterValues::default();
+        let mut unanswered = Vec::new();
+        for parameter in parameters {
+            if step.with.iter().any(|(name, _)| name == &parameter.name) {
+                continue;
+            }
+            match find_preset(&presets.steps[i], &parameter.name) {
+                Some(value) => params::apply_preset(&mut given, parameter, value)?,
+                None => unanswered.push(parameter.clone()),
+            }
+        }
+
+        let heading = format!("Parameters for step {}: {} ({})", i + 1, step.label(), script.display_name());
+        let Some(prompted) = params::prompt_all(&heading, &unanswered, glyphs)? else {
+            return cancelled("Workflow", glyphs);
+        };
+        for parameter in &unanswered {
+            let value = prompted.get(&parameter.name).unwrap_or_default().to_string();
+            given.insert(&parameter.name, value, prompted.is_secret(&parameter.name));
+        }
+        step_values.push(given);
+    }
+
+    if !batch::authenticate(executor, scripts.iter().map(|(script, _)| script), "steps", glyphs) {
+        return cancelled("Workflow", glyphs);
+    }
+
+    let run_id = database
+        .start_batch_run("workflow", definition.on_failure.as_str(), definition.steps.len(), Some(&definition.name))
+        .await?;
+
+    let template = template_pattern();
+    let mut context = RunContext { params: &values, results: HashMap::new(), outputs: HashMap::new() };
+    let mut halted = false;
+    let mut interrupted = false;
+
+    for (i, ((step, (script, parameters)), given)) in
+        definition.steps.iter().zip(&scripts).zip(step_values).enumerate()
+    {
+        // Steps with a condition decide for themselves whether to run after
+        // a failure, so clean-up and notification steps are possible
+        let run_step = !interrupted
+            && match &step.when {
+                Some(condition) => condition.evaluate(&context),
+                None => !halted,
+            };
+        if !run_step {
+            println!("{} Step {} of {}: {} - skipped", glyphs.skipped, i + 1, definition.steps.len(), step.label());
+            context.results.insert(step.id.clone(), StepResult::Skipped);
+            continue;
+        }
+
+        println!("{} Step {} of {}: {}", glyphs.workflow, i + 1, definition.steps.len(), step.label());
+        let result = match fill_in(&context, &template, step, parameters, given) {
+            Err(message) => {
+                println!("{} Step '{}' not run: {}", glyphs.failure, step.id, message);
+                StepResult::Error(message)
+            }
+            Ok(mut step_parameters) => {
+                // Created exclusively and readable by this user only, so nobody
+                // can plant a link there or inject outputs; removed on drop
+                let output_file = tempfile::Builder::new()
+                    .prefix("toolbox-output-")
+                    .tempfile()
+                    .context("Failed to create the step's output file")?;
+                step_parameters.set_output_file(output_file.path().to_path_buf());
+
+                let outcome = executor.execute(script, &step_parameters).await?;
+                database
+                    .record_execution(script, &outcome, step_parameters.to_history_json().as_deref(), Some(run_id))
+                    .await?;
+
+                context.outputs.insert(step.id.clone(), read_outputs(output_file.path()));
+
+                // Ctrl+C stops the whole workflow, conditions or not
+                interrupted = outcome.cancelled;
+                StepResult::Ran(outcome)
+            }
+        };
+
+        if result.failed() && !step.continue_on_error && definition.on_failure == FailurePolicy::StopOnError {
+            halted = true;
+        }
+        context.results.insert(step.id.clone(), result);
+        println!();
+    }
+
+    let results: Vec<&StepResult> = definition.steps.iter().map(|step| &context.results[&step.id]).collect();
+    let succeeded = results.iter().filter(|result| matches!(result, StepResult::Ran(outcome) if outcome.success())).count();
+    let skipped = results.iter().filter(|result| matches!(result, StepResult::Skipped)).count();
+    let failed = results.len() - succeeded - skipped;
+    database.finish_batch_run(run_id, succeeded, failed, skipped).await?;
+
+    let rows: Vec<(&str, Summary)> = definition
+        .steps
+        .iter()
+        .zip(&results)
+        .map(|(step, result)| {
+            let summary = match result {
+                StepResult::Ran(outcome) => Summary::Ran(outcome),
+                StepResult::Skipped => Summary::Skipped,
+                StepResult::Error(message) => Summary::Error(message),
+            };
+            (step.label(), summary)
+        })
+        .collect();
+    batch::print_summary(&format!("{} Workflow {} summary", glyphs.workflow, definition.name), &rows, glyphs);
+
+    let success = !interrupted
+        && definition
+            .steps
+            .iter()
+            .zip(&results)
+            .all(|(step, result)| step.continue_on_error || !result.failed());
+    Ok(Some(success))
+}
+
+/// The parameters for one step: its `with` values, with references filled
+/// in and checked against the script's schema, plus the answers given up front.
+fn fill_in(
+    context: &RunContext,
+    template: &Regex,
+    step: &Step,
+    parameters: &[ScriptParameter],
+    mut given: ParameterValues,
+) -> std::result::Result<ParameterValues, String> {
+    for (name, value) in &step.with {
+        let (value, from_secret) = context.expand(template, value)?;
+        let parameter = parameters
+            .iter()
+            .find(|parameter| &parameter.name == name)
+            .ok_or_else(|| format!("no parameter '{}'", name))?;
+        params::validate(parameter, &value).map_err(|e| format!("{}: {}", name, e))?;
+        let secret = from_secret || parameter.param_type == ParameterType::Password;
+        given.insert(name, value, secret);
+    }
+    Ok(given)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str) -> Token {
+        Token::Word(text.to_string())
+    }
+
+    fn ran(exit_code: i32) -> StepResult {
+        let now = chrono::Utc::now();
+        StepResult::Ran(ExecutionOutcome {
+            exit_code: Some(exit_code),
+            signal: None,
+            core_dumped: false,
+            timed_out: false,
+            cancelled: false,
+            refused: false,
+            started_at: now,
+            finished_at: now,
+            hostname: String::new(),
+            user: String::new(),
+        })
+    }
+
+    fn context(params: &ParameterValues) -> RunContext<'_> {
+        let mut context = RunContext { params, results: HashMap::new(), outputs: HashMap::new() };
+        context.results.insert("check".to_string(), ran(1));
+        context.results.insert("build".to_string(), ran(0));
+        context.results.insert("deploy".to_string(), StepResult::Skipped);
+        context
+            .outputs
+            .insert("check".to_string(), HashMap::from([("DUE".to_string(), "yes".to_string())]));
+        context
+    }
+
+    fn holds(condition: &str) -> bool {
+        let mut params = ParameterValues::default();
+        params.insert("force", "false".to_string(), false);
+        params.insert("days", "30".to_string(), false);
+        Condition::parse(condition).unwrap().evaluate(&context(&params))
+    }
+
+    #[test]
+    fn tokenize_splits_words_strings_and_operators() {
+        assert_eq!(
+            tokenize("steps.check.exit_code>=1&&!params.force").unwrap(),
+            vec![
+                word("steps.check.exit_code"),
+                Token::Operator(">="),
+                word("1"),
+                Token::Operator("&&"),
+                Token::Operator("!"),
+                word("params.force"),
+            ]
+        );
+        assert_eq!(
+            tokenize(r#"  'a b' != "c'd"  "#).unwrap(),
+            vec![Token::Quoted("a b".to_string()), Token::Operator("!="), Token::Quoted("c'd".to_string())]
+        );
+        assert_eq!(tokenize("x < -1.5").unwrap(), vec![word("x"), Token::Operator("<"), word("-1.5")]);
+        assert_eq!(tokenize("   ").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn tokenize_rejects_unterminated_strings_and_stray_characters() {
+        assert!(tokenize("params.x == 'open").unwrap_err().contains("unterminated"));
+        assert!(tokenize("params.x = 1").unwrap_err().contains("unexpected '='"));
+        assert!(tokenize("(params.x)").unwrap_err().contains("unexpected '('"));
+    }
+
+    #[test]
+    fn parse_groups_and_clauses_within_or_alternatives() {
+        let condition = Condition::parse("params.a || !params.b && steps.c.outputs.X == 'y'").unwrap();
+        assert_eq!(condition.any.len(), 2);
+        assert_eq!(condition.any[0].len(), 1);
+        assert_eq!(
+            condition.any[1],
+            vec![
+                Clause { negated: true, left: Operand::Reference(Reference::Param("b".to_string())), comparison: None },
+                Clause {
+                    negated: false,
+                    left: Operand::Reference(Reference::Step {
+                        id: "c".to_string(),
+                        field: StepField::Output("X".to_string()),
+                    }),
+                    comparison: Some((Comparison::Equal, Operand::Literal("y".to_string()))),
+                },
+            ]
+        );
+        assert!(!Condition::parse("!!params.a").unwrap().any[0][0].negated);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_conditions() {
+        assert!(Condition::parse("").is_err());
+        assert!(Condition::parse("params.a &&").is_err());
+        assert!(Condition::parse("params.a == == 1").is_err());
+        assert!(Condition::parse("params.a 1").is_err());
+        assert!(Condition::parse("params.a && params.b params.c").is_err());
+        assert!(Condition::parse("steps.check.stdout").unwrap_err().contains("unknown step field"));
+        assert!(Condition::parse("params.a.b").unwrap_err().contains("is not params.NAME"));
+    }
+
+    #[test]
+    fn evaluate_compares_numbers_numerically_and_text_for_equality() {
+        assert!(holds("steps.check.exit_code == 1"));
+        assert!(holds("steps.check.exit_code == 1.0"));
+        assert!(holds("params.days > 7"));
+        assert!(holds("params.days <= 30"));
+        assert!(!holds("params.days < 30"));
+        assert!(holds("steps.check.outputs.DUE == 'yes'"));
+        assert!(holds("steps.check.outputs.DUE != no"));
+        assert!(!holds("steps.check.outputs.DUE > 'a'"));
+    }
+
+    #[test]
+    fn evaluate_treats_empty_false_and_zero_as_false() {
+        assert!(!holds("params.force"));
+        assert!(holds("!params.force"));
+        assert!(!holds("params.missing"));
+        assert!(!holds("steps.build.exit_code"));
+        assert!(holds("steps.check.exit_code"));
+        assert!(!holds("steps.deploy.exit_code"));
+        assert!(!holds("steps.unknown.outputs.DUE"));
+    }
+
+    #[test]
+    fn evaluate_reads_step_results() {
+        assert!(holds("steps.build.success && steps.check.failed"));
+        assert!(holds("steps.deploy.skipped"));
+        assert!(!holds("steps.deploy.failed"));
+        assert!(!holds("steps.check.success"));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert!(holds("steps.build.success || params.force && steps.check.success"));
+        assert!(!holds("params.force && steps.check.success || steps.deploy.failed"));
+        assert!(holds("params.force && steps.check.success || steps.deploy.skipped"));
+    }
+}